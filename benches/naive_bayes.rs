@@ -0,0 +1,136 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use cs145_bayes::{
+    bayes::NaiveBayesClassifier, config::Config, features::Features, generate::GenerativeModel,
+    rng::Rng,
+};
+use std::hint::black_box;
+
+/// The shape of a synthetic data set.
+struct Shape {
+    classes: usize,
+    vocabulary: usize,
+    records: usize,
+    symptoms_per_class: usize,
+}
+
+const SHAPES: &[Shape] = &[
+    Shape {
+        classes: 10,
+        vocabulary: 100,
+        records: 1_000,
+        symptoms_per_class: 10,
+    },
+    Shape {
+        classes: 50,
+        vocabulary: 1_000,
+        records: 10_000,
+        symptoms_per_class: 20,
+    },
+    Shape {
+        classes: 200,
+        vocabulary: 5_000,
+        records: 20_000,
+        symptoms_per_class: 40,
+    },
+];
+
+/// Records drawn from a random generative model of the shape, the same every run:
+fn generate(shape: &Shape, seed: u64) -> Vec<(String, Features)> {
+    GenerativeModel::random(
+        shape.classes,
+        shape.vocabulary,
+        shape.symptoms_per_class,
+        0.05,
+        0,
+    )
+    .generate(shape.records, seed)
+    .unwrap()
+}
+
+/// Adds background symptoms that show up in every record with the same probability, whatever its
+/// disease, the kind of symptoms pruning skips:
+fn with_background(
+    mut records: Vec<(String, Features)>,
+    background: usize,
+    seed: u64,
+) -> Vec<(String, Features)> {
+    let mut rng = Rng::seeded(seed);
+    for (_, features) in &mut records {
+        for i in 0..background {
+            if rng.next_f64() < 0.5 {
+                features
+                    .counts
+                    .insert(format!("background{}", i).into(), 1.0);
+            }
+        }
+    }
+    records
+}
+
+fn name(shape: &Shape) -> String {
+    format!(
+        "{}c_{}v_{}r",
+        shape.classes, shape.vocabulary, shape.records
+    )
+}
+
+fn training(c: &mut Criterion) {
+    let mut group = c.benchmark_group("train");
+    group.sample_size(10);
+    for shape in SHAPES {
+        let records = generate(shape, 1);
+        group.throughput(Throughput::Elements(shape.records as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(name(shape)),
+            &records,
+            |b, r| {
+                b.iter(|| NaiveBayesClassifier::fit(r.iter().cloned(), Config::default()).unwrap())
+            },
+        );
+    }
+    group.finish();
+}
+
+fn prediction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("predict");
+    group.sample_size(10);
+    for shape in SHAPES {
+        let classifier = NaiveBayesClassifier::fit(generate(shape, 1), Config::default()).unwrap();
+        let records: Vec<Features> = generate(shape, 2).into_iter().map(|(_, f)| f).collect();
+        group.throughput(Throughput::Elements(records.len() as u64));
+        group.bench_with_input(
+            BenchmarkId::from_parameter(name(shape)),
+            &records,
+            |b, r| b.iter(|| black_box(classifier.predict_batch(r))),
+        );
+    }
+    group.finish();
+}
+
+fn pruned_prediction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("predict_pruned");
+    group.sample_size(10);
+    for shape in SHAPES {
+        let background = (shape.vocabulary / 10).min(100);
+        let training = with_background(generate(shape, 1), background, 1);
+        let records: Vec<Features> = with_background(generate(shape, 2), background, 2)
+            .into_iter()
+            .map(|(_, f)| f)
+            .collect();
+        group.throughput(Throughput::Elements(records.len() as u64));
+        for (variant, prune_uninformative) in [("exact", None), ("pruned", Some(0.5))] {
+            let config = Config {
+                prune_uninformative,
+                ..Config::default()
+            };
+            let classifier = NaiveBayesClassifier::fit(training.clone(), config).unwrap();
+            group.bench_with_input(BenchmarkId::new(variant, name(shape)), &records, |b, r| {
+                b.iter(|| black_box(classifier.predict_batch(r)))
+            });
+        }
+    }
+    group.finish();
+}
+
+criterion_group!(benches, training, prediction, pruned_prediction);
+criterion_main!(benches);
@@ -0,0 +1,80 @@
+#ifndef NAIVE_BAYES_H
+#define NAIVE_BAYES_H
+
+/* Generated by cbindgen from src/ffi.rs, don't edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/*
+ A trained classifier, only ever handled through a pointer.
+ */
+typedef struct NbClassifier NbClassifier;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/*
+ Trains a classifier on a training csv file. Returns null on failure.
+
+ # Safety
+
+ `path` must be a valid, nul terminated string.
+ */
+struct NbClassifier *nb_train(const char *path);
+
+/*
+ Loads a classifier saved with `NaiveBayesClassifier::save`. Returns null on failure.
+
+ # Safety
+
+ `path` must be a valid, nul terminated string.
+ */
+struct NbClassifier *nb_load(const char *path);
+
+/*
+ Predicts the `k` most likely diseases of a record given as json like the records of jsonl
+ files, e.g. `{"symptoms": ["fever", "cough"]}`. Returns json like
+ `{"predictions": [{"disease": "Flu", "probability": 0.9}]}` that must be freed with
+ `nb_free_string`, or null on failure.
+
+ # Safety
+
+ `classifier` must come from `nb_train` or `nb_load` and not have been freed, and `record` must
+ be a valid, nul terminated string.
+ */
+char *nb_predict_json(const struct NbClassifier *classifier, const char *record, size_t k);
+
+/*
+ Frees a classifier, null is ignored.
+
+ # Safety
+
+ `classifier` must come from `nb_train` or `nb_load` and not have been freed already.
+ */
+void nb_free(struct NbClassifier *classifier);
+
+/*
+ Frees a string returned by this library, null is ignored.
+
+ # Safety
+
+ `s` must come from `nb_predict_json` and not have been freed already.
+ */
+void nb_free_string(char *s);
+
+/*
+ Describes the last failure on this thread, or null if nothing failed yet. The string is owned
+ by the library and only valid until the next call on this thread.
+ */
+const char *nb_last_error(void);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* NAIVE_BAYES_H */
@@ -1,140 +1,1594 @@
-use anyhow::{Context, Result};
-use csv::{Reader, StringRecord};
+use crate::{
+    builder::NaiveBayesBuilder,
+    cache::PredictionCache,
+    calibration::{CalibrationMethod, Calibrator},
+    categorical::CategoricalModel,
+    checkpoint::{Checkpoint, CheckpointConfig, InputFile},
+    compress,
+    config::{Config, ModelKind, Priors},
+    diagnostics::{self, Diagnostics},
+    diff::ModelDiff,
+    drift::DriftReport,
+    error::{BayesError, Result},
+    explain::{Contribution, Explanation},
+    features::Features,
+    format::{self, Format, ParseReport, PredictOutputConfig, PredictionWriter},
+    gaussian::GaussianModel,
+    hashing::FeatureHasher,
+    interaction::FeaturePairs,
+    missing::MissingValues,
+    progress::{Stage, Tracker},
+    selection, spreadsheet,
+    stats::{SufficientStats, ENTRY_BYTES},
+    symbols::Symbol,
+    text::TfIdf,
+};
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
 use std::{
-    collections::{hash_map::Entry, HashMap, HashSet},
+    borrow::Cow,
+    cmp::Ordering,
+    collections::{btree_map::Entry, BTreeMap, HashMap, HashSet},
+    io::{Read, Write},
+    mem,
     path::Path,
+    sync::Mutex,
+    thread,
 };
 
+/// The outcome of predicting a single record.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Prediction<'a> {
+    Disease {
+        disease: &'a str,
+        probability: f64,
+    },
+    /// The top disease wasn't probable enough (or close enough to the runner up) to be trusted.
+    Uncertain,
+}
+
+/// The prediction of one record of a batch.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BatchPrediction {
+    /// The ID of the record, its (1 based) position in the batch unless read from the input.
+    pub id: String,
+    /// Whether the ID was read from the input, rather than being the record's position.
+    pub id_from_input: bool,
+    /// The predicted disease, or the unknown label when the classifier abstained.
+    pub disease: Symbol,
+    pub score: f64,
+    /// The probability of the predicted disease (of the most likely one when the classifier
+    /// abstained), only when the config's output asks for probabilities.
+    pub probability: Option<f64>,
+    /// The next most likely diseases with their probabilities, up to as many as the config's
+    /// output asks for.
+    pub alternatives: Vec<(Symbol, f64)>,
+}
+
+impl BatchPrediction {
+    /// Fills in the probability and alternatives when the output asks for them, from the
+    /// probability of every disease (most likely first) which is only computed then:
+    pub(crate) fn describe<'a, F>(&mut self, output: &PredictOutputConfig, probabilities: F)
+    where
+        F: FnOnce() -> Vec<(&'a str, f64)>,
+    {
+        if !output.wants_probabilities() {
+            return;
+        }
+        let probabilities = probabilities();
+        self.probability = probabilities
+            .iter()
+            .find(|&&(disease, _)| self.disease == *disease)
+            .or(probabilities.first())
+            .map(|&(_, p)| p);
+        self.alternatives = probabilities
+            .iter()
+            .filter(|&&(disease, _)| self.disease != *disease)
+            .take(output.alternatives)
+            .map(|&(disease, p)| (Symbol::new(disease), p))
+            .collect();
+    }
+}
+
+/// Diseases with fewer (weighted) training records than this are warned about.
+const FEW_RECORDS: f64 = 5.0;
+
+/// How many records are counted up at least between estimates of the memory used.
+const MEMORY_CHECK_INTERVAL: usize = 10_000;
+
+/// The feature rare (and unknown) symptoms are pooled into when pooling is enabled.
+pub const OTHER_FEATURE: &str = "<other>";
+
+/// The feature bernoulli explanations list the likelihood of every symptom being absent under.
+/// Present symptoms then contribute the swap from absent to present.
+pub const ABSENT_FEATURE: &str = "<absent symptoms>";
+
+/// The weighted training records of every disease, kept sorted by disease so that training
+/// always visits them in the same order.
+pub type DiseaseRecords = BTreeMap<String, Vec<(Features, f64)>>;
+
+/// A trained classifier. It can be persisted with any serde format, or as (optionally
+/// compressed) json with `save` and `load`. Only the counts it was trained on are stored, the
+/// probabilities are derived from them again when it's read back.
 pub struct NaiveBayesClassifier {
-    disease_betas: HashMap<String, HashMap<String, f64>>,
-    disease_pis: HashMap<String, f64>,
+    disease_betas: BTreeMap<Symbol, HashMap<Symbol, f64>>,
+    disease_pis: BTreeMap<Symbol, f64>,
+    // The log probability of a record having none of the symptoms, for bernoulli models:
+    absent_log_probs: BTreeMap<Symbol, f64>,
+    // Symptoms that (nearly) don't tell the diseases apart, with the term they add to every
+    // disease's log score:
+    uninformative: HashMap<Symbol, f64>,
+    config: Config,
+    calibrator: Option<Calibrator>,
+    feature_ranking: Option<Vec<(String, f64)>>,
+    tf_idf: Option<TfIdf>,
+    feature_pairs: Option<FeaturePairs>,
+    categorical: CategoricalModel,
+    gaussian: GaussianModel,
+    missing_values: MissingValues,
+    stats: SufficientStats,
+    training_report: ParseReport,
 }
 
 impl NaiveBayesClassifier {
     pub fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let mut reader = csv::Reader::from_path(path)?;
+        Self::with_config(path, Config::default())
+    }
+
+    pub fn with_config<P: AsRef<Path>>(path: P, config: Config) -> Result<Self> {
+        Self::with_config_files(&[path.as_ref()], config)
+    }
+
+    /// Trains on every csv file as if they were one, e.g. the daily shards of a data set. When
+    /// the counts simply add up, the files are counted in parallel and their counts merged in the
+    /// order of the files. Rows skipped as malformed are reported along with their file when
+    /// there's more than one:
+    pub fn with_config_files<P: AsRef<Path> + Sync>(paths: &[P], config: Config) -> Result<Self> {
+        check_config(&config)?;
+        if paths.is_empty() {
+            return Err(BayesError::EmptyTrainingSet);
+        }
+
+        let (mut classifier, reports) = if counts_add_up(&config) {
+            // Only the counts are kept, so the files can be far larger than memory:
+            let (counters, reports): (Vec<_>, Vec<_>) =
+                count_files(paths, &config)?.into_iter().unzip();
+            let mut counters = counters.into_iter();
+            let mut counter = counters.next().unwrap();
+            for other in counters {
+                counter.stats.merge(&other.stats);
+            }
+            (counter.finish(config)?, reports)
+        } else {
+            let mut records = Vec::new();
+            let mut bytes = 0;
+            let mut reports = Vec::with_capacity(paths.len());
+            for path in paths {
+                let reader = spreadsheet::open(path, &config)?;
+                let report = format::for_each_training_record(reader, &config, |record| {
+                    bytes += ENTRY_BYTES + record.0.len() + record.1.estimated_bytes();
+                    check_memory(bytes, config.memory_limit)?;
+                    records.push(record);
+                    Ok(())
+                })?;
+                reports.push(report);
+            }
+            (Self::fit_weighted(records, config)?, reports)
+        };
+        classifier.training_report = merge_reports(paths, reports);
+        Ok(classifier)
+    }
+
+    /// Trains on a csv file like `with_config`, saving the counts so far to a checkpoint every so
+    /// many rows. Resuming starts from the checkpoint (if there is one) and skips the rows it
+    /// already counted, so the file (checked by its path, size, and modification time) and the
+    /// options for reading and counting records have to be the same; rows skipped as malformed
+    /// before the checkpoint aren't reported again. The checkpoint is removed once training is
+    /// done. Only configs whose counts simply add up can be checkpointed:
+    pub fn with_checkpoints<P: AsRef<Path>>(
+        path: P,
+        config: Config,
+        checkpoints: &CheckpointConfig,
+    ) -> Result<Self> {
+        check_config(&config)?;
+        if !counts_add_up(&config) {
+            return Err(BayesError::InvalidConfig(
+                "only training without pooling, tf-idf, feature selection, or imputation can be \
+                 checkpointed."
+                    .to_string(),
+            ));
+        }
+        if checkpoints.every == 0 {
+            return Err(BayesError::InvalidConfig(
+                "checkpoints must be at least one row apart.".to_string(),
+            ));
+        }
+
+        let input = InputFile::of(path.as_ref())?;
+        let mut counter = Counter::new(&config);
+        let mut skip = 0;
+        if checkpoints.resume {
+            if let Some(checkpoint) = Checkpoint::load(&checkpoints.path, &config, &input)? {
+                log::info!(
+                    "resuming after row {} of the training file",
+                    checkpoint.rows
+                );
+                skip = checkpoint.rows;
+                counter.stats = checkpoint.stats;
+            }
+        }
+
+        let mut next_checkpoint = skip + checkpoints.every;
+        let reader = spreadsheet::open(path, &config)?;
+        let report =
+            format::for_each_training_record_after(reader, &config, skip, |record, rows| {
+                let (disease, features, weight) = record;
+                counter.add(&disease, features, weight)?;
+                if rows >= next_checkpoint {
+                    Checkpoint::save(&checkpoints.path, &config, &input, rows, &counter.stats)?;
+                    next_checkpoint = rows + checkpoints.every;
+                }
+                Ok(())
+            })?;
+
+        let mut classifier = counter.finish(config)?;
+        classifier.training_report = report;
+        Checkpoint::remove(&checkpoints.path)?;
+        Ok(classifier)
+    }
+
+    pub fn builder() -> NaiveBayesBuilder {
+        NaiveBayesBuilder::new()
+    }
+
+    /// Trains on (disease, features) records:
+    pub fn fit<I, S>(records: I, config: Config) -> Result<Self>
+    where
+        I: IntoIterator<Item = (S, Features)>,
+        S: Into<String>,
+    {
+        let records = records
+            .into_iter()
+            .map(|(disease, features)| (disease, features, 1.0));
+        Self::fit_weighted(records, config)
+    }
+
+    /// Trains on (disease, features, weight) records, where a record counts as that (possibly
+    /// fractional) number of records towards the priors and betas:
+    pub fn fit_weighted<I, S>(records: I, config: Config) -> Result<Self>
+    where
+        I: IntoIterator<Item = (S, Features, f64)>,
+        S: Into<String>,
+    {
+        check_config(&config)?;
+
+        // Records whose counts don't depend on the rest of the training set are counted up as
+        // they come, without holding on to them:
+        if counts_add_up(&config) {
+            let mut counter = Counter::new(&config);
+            for (disease, features, weight) in records {
+                counter.add(&disease.into(), features, weight)?;
+            }
+            return counter.finish(config);
+        }
 
         // Collect all disease information:
 
-        let mut diseases_map = HashMap::new();
-        let mut all_symptoms = HashSet::new();
-        let mut num_records = 0;
-        for record in reader.records() {
-            let record = record?;
-            let disease = record.get(0).context("csv record missing disease entry.")?;
-
-            let mut symptoms = HashSet::new();
-            for symptom in record.iter().skip(1) {
-                if symptom.is_empty() {
-                    continue;
+        let mut diseases_map = group_by_disease(records)?;
+        if let Some(hasher) = &config.feature_hasher {
+            for (symptoms, _) in diseases_map.values_mut().flatten() {
+                hasher.transform(symptoms);
+            }
+        }
+        let mut all_symptoms: HashSet<Symbol> = diseases_map
+            .values()
+            .flatten()
+            .flat_map(|(symptoms, _)| symptoms.counts.keys().cloned())
+            .collect();
+        let weights: BTreeMap<Symbol, f64> = diseases_map
+            .iter()
+            .map(|(disease, symptoms_instances)| {
+                let weight = symptoms_instances.iter().map(|(_, weight)| weight).sum();
+                (Symbol::new(disease), weight)
+            })
+            .collect();
+        check_training_set(&weights)?;
+
+        // Drop (or pool) symptoms that (barely) ever show up, and all but the most frequent ones
+        // if there are too many. Records that are transformed any further need this done to them
+        // directly, otherwise it's done to the counts once they're collected:
+        if transforms_records(&config) && prunes(&config) {
+            let mut occurrences: HashMap<&str, f64> = HashMap::new();
+            for (symptoms, weight) in diseases_map.values().flatten() {
+                for symptom in symptoms.counts.keys() {
+                    *occurrences.entry(symptom.as_str()).or_default() += weight;
+                }
+            }
+            let kept: HashSet<Symbol> = most_frequent(occurrences, &config)
+                .into_iter()
+                .map(Symbol::new)
+                .collect();
+
+            for (symptoms, _) in diseases_map.values_mut().flatten() {
+                if config.pool_rare_features {
+                    pool_into_other(&mut symptoms.counts, |symptom| kept.contains(symptom));
+                } else {
+                    symptoms.counts.retain(|symptom, _| kept.contains(symptom));
                 }
-                symptoms.insert(symptom.trim().to_string());
-                all_symptoms.insert(symptom.trim().to_string());
             }
+            all_symptoms.retain(|symptom| kept.contains(symptom));
+            if config.pool_rare_features {
+                all_symptoms.insert(Symbol::new(OTHER_FEATURE));
+            }
+        }
+
+        // Fill in missing categorical and numeric values as the schema asks:
+        let missing_values = MissingValues::fit(&config.schema, &diseases_map);
+        for (symptoms, _) in diseases_map.values_mut().flatten() {
+            missing_values.fill(symptoms);
+        }
+
+        // Add the most dependent pairs of symptoms as features if asked to:
+        let feature_pairs = config.feature_pairs.map(|max_pairs| {
+            let feature_pairs = FeaturePairs::fit(&diseases_map, max_pairs);
+            for (symptoms, _) in diseases_map.values_mut().flatten() {
+                feature_pairs.transform(symptoms);
+            }
+            all_symptoms.extend(feature_pairs.names());
+            feature_pairs
+        });
+
+        // Re-weight the counts of every record with TF-IDF if asked to:
+        let tf_idf = if config.tf_idf {
+            let tf_idf = TfIdf::fit(
+                diseases_map
+                    .values()
+                    .flatten()
+                    .map(|(symptoms, _)| symptoms),
+            );
+            for (symptoms, _) in diseases_map.values_mut().flatten() {
+                tf_idf.transform(symptoms);
+            }
+            Some(tf_idf)
+        } else {
+            None
+        };
 
-            match diseases_map.entry(disease.to_string()) {
-                Entry::Occupied(entry) => entry.into_mut(),
-                Entry::Vacant(entry) => entry.insert(Vec::new()),
+        // Only keep the most informative symptoms if asked to:
+        let feature_ranking = config.feature_selection.map(|selection| {
+            selection::rank_features(&diseases_map, &all_symptoms, selection.score)
+        });
+        if let (Some(selection), Some(ranking)) = (config.feature_selection, &feature_ranking) {
+            all_symptoms = ranking
+                .iter()
+                .take(selection.keep)
+                .map(|(symptom, _)| Symbol::new(symptom))
+                .collect();
+            for symptoms_instances in diseases_map.values_mut() {
+                for (symptoms, _) in symptoms_instances.iter_mut() {
+                    symptoms
+                        .counts
+                        .retain(|symptom, _| all_symptoms.contains(symptom));
+                }
             }
-            .push(symptoms);
+        }
+
+        let mut stats = SufficientStats::collect(&diseases_map);
+        if transforms_records(&config) {
+            stats.vocabulary = all_symptoms.into_iter().collect();
+        }
+
+        Self::from_stats(
+            stats,
+            config,
+            missing_values,
+            tf_idf,
+            feature_pairs,
+            feature_ranking,
+        )
+    }
 
-            num_records += 1;
+    /// Derives the probabilities of every disease, symptom, and column from the counts of the
+    /// training records:
+    fn from_stats(
+        stats: SufficientStats,
+        config: Config,
+        missing_values: MissingValues,
+        tf_idf: Option<TfIdf>,
+        feature_pairs: Option<FeaturePairs>,
+        feature_ranking: Option<Vec<(String, f64)>>,
+    ) -> Result<Self> {
+        let vocabulary = kept_symptoms(&stats, &config);
+        let has_columns = !stats.categories.is_empty() || !stats.numbers.is_empty();
+        if vocabulary.is_empty() && !has_columns {
+            return Err(BayesError::NoFeatures);
         }
 
-        // Calculate all of the beta values:
+        // Calculate all of the beta values (every record counts as many times as its weight):
+
+        let alpha = config.smoothing;
+        let total_num_symptoms = vocabulary.len() as f64; // N
+        let no_counts = BTreeMap::new();
+        let mut disease_betas = BTreeMap::new();
+        let mut absent_log_probs = BTreeMap::new();
+        for (disease, &num_instances) in &stats.records {
+            let symptom_counts = stats.symptom_counts.get(disease).unwrap_or(&no_counts);
 
-        let total_num_symptoms = all_symptoms.len() as f64; // N
-        let mut disease_betas = HashMap::new();
-        for (disease, symptoms_instances) in &diseases_map {
             // Get total number of symptoms for this disease:
-            let num_symptoms = symptoms_instances
+            let num_symptoms: f64 = vocabulary
                 .iter()
-                .fold(0, |acc, symptoms| acc + symptoms.len())
-                as f64;
+                .filter_map(|&symptom| symptom_counts.get(symptom))
+                .sum();
 
             // Now, for each symptom, we calculate the beta value:
             let mut betas = HashMap::new();
-            for symptom in &all_symptoms {
+            for &symptom in &vocabulary {
                 // Count how often this occurs for this disease:
-                let num_symptom = symptoms_instances.iter().fold(0, |acc, symptoms| {
-                    if symptoms.contains(symptom) {
-                        acc + 1
-                    } else {
-                        acc
-                    }
-                }) as f64;
+                let num_symptom = symptom_counts.get(symptom).copied().unwrap_or(0.0);
 
-                let beta = (num_symptom + 1.0) / (num_symptoms + total_num_symptoms);
+                let beta = (num_symptom + alpha) / (num_symptoms + alpha * total_num_symptoms);
                 betas.insert(symptom.clone(), beta);
             }
 
+            // A bernoulli model instead uses the fraction of records with the symptom, and
+            // absent symptoms count against a disease too:
+            if config.model_kind == ModelKind::Bernoulli {
+                let symptom_records = stats.symptom_records.get(disease).unwrap_or(&no_counts);
+                for &symptom in &vocabulary {
+                    let num_symptom = symptom_records.get(symptom).copied().unwrap_or(0.0);
+
+                    let beta = (num_symptom + alpha) / (num_instances + 2.0 * alpha);
+                    betas.insert(symptom.clone(), beta);
+                }
+
+                // Sum in a fixed order so the result is always the same:
+                let mut absent: Vec<f64> = betas.values().map(|beta| (1.0 - beta).ln()).collect();
+                absent.sort_by(|a, b| a.total_cmp(b));
+                absent_log_probs.insert(disease.clone(), absent.iter().sum::<f64>());
+            }
+
             disease_betas.insert(disease.clone(), betas);
         }
+        let uninformative = match config.prune_uninformative {
+            Some(tolerance) => uninformative_symptoms(&disease_betas, config.model_kind, tolerance),
+            None => HashMap::new(),
+        };
+
+        // Learn the distributions of the categorical and numeric columns:
+        let categorical = CategoricalModel::fit(&stats.categories, &stats.records, alpha);
+        let gaussian = GaussianModel::fit(&stats.numbers);
 
         // Calculate all of the pi values:
-        let mut disease_pis = HashMap::new();
-        for (disease, symptoms_instances) in &diseases_map {
-            let pi = (symptoms_instances.len() as f64) / (num_records as f64);
-            disease_pis.insert(disease.clone(), pi);
+        let mut disease_pis = BTreeMap::new();
+        match &config.priors {
+            Priors::Empirical => {
+                let num_records = stats.num_records();
+                for (disease, weight) in &stats.records {
+                    disease_pis.insert(disease.clone(), weight / num_records);
+                }
+            }
+            Priors::Uniform => {
+                for disease in stats.records.keys() {
+                    disease_pis.insert(disease.clone(), 1.0 / stats.records.len() as f64);
+                }
+            }
+            Priors::Custom(weights) => {
+                let mut total = 0.0;
+                for disease in stats.records.keys() {
+                    let &weight = weights.get(disease.as_str()).ok_or_else(|| {
+                        BayesError::InvalidConfig(format!(
+                            "no prior given for disease {}.",
+                            disease
+                        ))
+                    })?;
+                    if weight.is_nan() || weight < 0.0 {
+                        return Err(BayesError::InvalidConfig(format!(
+                            "invalid prior for disease {}: {}",
+                            disease, weight
+                        )));
+                    }
+                    disease_pis.insert(disease.clone(), weight);
+                    total += weight;
+                }
+
+                if total <= 0.0 {
+                    return Err(BayesError::InvalidConfig(
+                        "custom priors must not all be zero.".to_string(),
+                    ));
+                }
+                disease_pis.values_mut().for_each(|pi| *pi /= total);
+            }
         }
 
+        log::info!(
+            "trained {} classes on {} features",
+            disease_pis.len(),
+            vocabulary.len()
+        );
         Ok(NaiveBayesClassifier {
             disease_betas,
             disease_pis,
+            absent_log_probs,
+            uninformative,
+            config,
+            calibrator: None,
+            feature_ranking,
+            tf_idf,
+            feature_pairs,
+            categorical,
+            gaussian,
+            missing_values,
+            stats,
+            training_report: ParseReport::default(),
         })
     }
 
-    // Predicts a bunch of values from a test.csv path:
-    pub fn predict<P: AsRef<Path>>(&self, inpath: P, outpath: P) -> Result<()> {
-        let mut reader = csv::Reader::from_path(inpath)?;
+    /// Adds the training records of another classifier to this one, as if it had been trained on
+    /// both sets of records, e.g. to train on shards of a data set in parallel. The other
+    /// classifier must have read its records the same way (schema, tokenizer, normalizer), this
+    /// classifier's options are used for everything else. Records that were transformed based on
+    /// the whole training set (pooled, TF-IDF weighted, selected from, or with imputed values)
+    /// can't be merged. Any calibration is dropped since it no longer applies:
+    pub fn merge(&mut self, other: &NaiveBayesClassifier) -> Result<()> {
+        self.check_mergeable()?;
+        other.check_mergeable()?;
+        self.add_stats(&other.stats)
+    }
 
-        let mut results = Vec::new();
-        for record in reader.records() {
-            let record = record?;
+    /// Trains further on (disease, features, weight) records, as if they had been part of the
+    /// original training records. The same classifiers as with `merge` can be updated:
+    pub fn update_weighted<I, S>(&mut self, records: I) -> Result<()>
+    where
+        I: IntoIterator<Item = (S, Features, f64)>,
+        S: Into<String>,
+    {
+        self.check_mergeable()?;
 
-            let mut symptoms = HashSet::new();
-            for symptom in record.iter().skip(1) {
-                if symptom.is_empty() {
-                    continue;
-                }
-                symptoms.insert(symptom.trim().to_string());
-            }
+        let mut counter = Counter::new(&self.config);
+        for (disease, features, weight) in records {
+            counter.add(&disease.into(), features, weight)?;
+        }
+        self.add_stats(&counter.stats)
+    }
+
+    /// Trains further on the records of a csv file (without holding on to them), returning the
+    /// malformed rows that were skipped:
+    pub fn update_with_csv<P: AsRef<Path>>(&mut self, path: P) -> Result<ParseReport> {
+        self.check_mergeable()?;
+
+        let mut counter = Counter::new(&self.config);
+        let report = format::for_each_training_record(
+            spreadsheet::open(path, &self.config)?,
+            &self.config,
+            |record| {
+                let (disease, features, weight) = record;
+                counter.add(&disease, features, weight)
+            },
+        )?;
+        self.add_stats(&counter.stats)?;
+        Ok(report)
+    }
 
-            results.push(self.predict_one(&symptoms));
+    fn check_mergeable(&self) -> Result<()> {
+        if !counts_add_up(&self.config) {
+            return Err(BayesError::Unsupported(
+                "classifiers with pooled, TF-IDF weighted, paired, selected, or imputed features \
+                 can't be merged or updated."
+                    .to_string(),
+            ));
         }
+        Ok(())
+    }
+
+    fn add_stats(&mut self, stats: &SufficientStats) -> Result<()> {
+        let mut merged_stats = self.stats.clone();
+        merged_stats.merge(stats);
+        let merged = Self::from_stats(
+            merged_stats,
+            self.config.clone(),
+            self.missing_values.clone(),
+            None,
+            None,
+            None,
+        )?;
+        *self = NaiveBayesClassifier {
+            training_report: mem::take(&mut self.training_report),
+            ..merged
+        };
+        Ok(())
+    }
+
+    /// The weighted counts the classifier was derived from:
+    pub fn stats(&self) -> &SufficientStats {
+        &self.stats
+    }
+
+    /// Writes the classifier as json, compressed according to the path's extension:
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = compress::create(path)?;
+        serde_json::to_writer(&mut writer, self)?;
+        writer.finish()
+    }
+
+    /// Reads a classifier written by `save`:
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Ok(serde_json::from_reader(compress::open(path)?)?)
+    }
 
-        // Now we can write the result:
-        let mut writer = csv::Writer::from_path(outpath)?;
+    /// Fits a calibration map on held out (disease, features) records so that the probabilities
+    /// returned by `predict_top_k` match how often the predictions are actually right. Every
+    /// disease's probability of every record is one sample:
+    pub fn calibrate(
+        &mut self,
+        holdout: &[(String, Features)],
+        method: CalibrationMethod,
+    ) -> Result<()> {
+        self.calibrator = None;
 
-        writer.write_record(&["ID", "Disease"])?;
-        for (i, result) in results.iter().enumerate() {
-            writer.write_record(&[(i + 1).to_string(), result.to_string()])?;
+        let mut samples = Vec::new();
+        for (disease, features) in holdout {
+            for (class, p) in self.probabilities(features) {
+                samples.push((p, class == disease));
+            }
         }
 
+        self.calibrator = Some(Calibrator::fit(method, &samples)?);
         Ok(())
     }
 
-    /// Given a record of symptoms, makes a prediction as to which disease it is:
-    fn predict_one(&self, psymptoms: &HashSet<String>) -> &str {
-        let (best_disease, _) = self.disease_betas.iter().fold(
-            ("", -1.0),
-            |(best_disease, best_score), (disease, betas)| {
-                let product_betas = psymptoms.iter().fold(1.0, |acc, psymptom| {
-                    let &beta = betas.get(psymptom).unwrap(); // this should always succeed.
-                    acc * beta
+    // Predicts a bunch of values from a test file, the format of each file is picked from its
+    // extension unless one is given. Returns the malformed rows that were skipped:
+    pub fn predict<P: AsRef<Path>>(
+        &self,
+        inpath: P,
+        outpath: P,
+        format: Option<Format>,
+    ) -> Result<ParseReport> {
+        format::predict_file(inpath, outpath, format, &self.config, |records| {
+            self.predict_batch(records)
+        })
+    }
+
+    /// Predicts every record, returning the predictions in the same order:
+    pub fn predict_batch(&self, records: &[Features]) -> Vec<BatchPrediction> {
+        let mut tracker = Tracker::start(&self.config, Stage::Predicting);
+        let results: Vec<_> = if self.config.cache_predictions {
+            // Many records share the same symptoms, so only score each combination once:
+            let mut cache = PredictionCache::new(self.config.cache_capacity);
+            records
+                .iter()
+                .map(|symptoms| {
+                    tracker.record();
+                    cache.get_or_insert_with(symptoms, || self.predict_one(symptoms))
+                })
+                .collect()
+        } else {
+            records
+                .iter()
+                .map(|symptoms| {
+                    tracker.record();
+                    self.predict_one(symptoms)
+                })
+                .collect()
+        };
+        log::info!("predicted {} records", tracker.finish());
+
+        results
+            .into_iter()
+            .zip(records)
+            .enumerate()
+            .map(|(i, ((disease, score), symptoms))| {
+                let mut prediction = BatchPrediction {
+                    id: (i + 1).to_string(),
+                    id_from_input: false,
+                    disease: self.symbol(disease),
+                    score,
+                    probability: None,
+                    alternatives: Vec::new(),
+                };
+                prediction.describe(&self.config.output, || {
+                    self.predict_top_k(symptoms, self.disease_pis.len())
                 });
+                prediction
+            })
+            .collect()
+    }
+
+    // Predicts records read from `input` as they arrive and writes each prediction to `output`
+    // immediately (flushing after every record), so it can be used in a pipeline:
+    pub fn predict_stream<R: Read, W: Write>(
+        &self,
+        input: R,
+        output: W,
+        format: Format,
+    ) -> Result<ParseReport> {
+        let mut writer = PredictionWriter::new(output, format, &self.config.output)?;
+        writer.flush()?;
+
+        let mut tracker = Tracker::start(&self.config, Stage::Predicting);
+        let mut position = 0;
+        let report = format::for_each_record(input, format, &self.config, |id, symptoms| {
+            position += 1;
+            let (disease, score) = self.predict_one(&symptoms);
+            let mut prediction = BatchPrediction {
+                id_from_input: id.is_some(),
+                id: id.unwrap_or_else(|| position.to_string()),
+                disease: self.symbol(disease),
+                score,
+                probability: None,
+                alternatives: Vec::new(),
+            };
+            prediction.describe(&self.config.output, || {
+                self.predict_top_k(&symptoms, self.disease_pis.len())
+            });
+            writer.write(&prediction, &symptoms)?;
+            tracker.record();
+            writer.flush()
+        })?;
+
+        log::info!("predicted {} records", tracker.finish());
+        Ok(report)
+    }
+
+    /// Predicts the disease of a record (the one with the lowest expected cost given a cost
+    /// matrix), unless the most likely disease falls below the configured confidence or margin:
+    pub fn predict_single(&self, psymptoms: &Features) -> Prediction<'_> {
+        let top = match &self.config.costs {
+            Some(_) => self.predict_top_k(psymptoms, self.disease_pis.len()),
+            None => self.predict_top_k(psymptoms, 2),
+        };
+        let probability = match top.first() {
+            Some(&(_, probability)) => probability,
+            None => return Prediction::Uncertain,
+        };
+        let runner_up = top.get(1).map_or(0.0, |&(_, p)| p);
+
+        if probability < self.config.min_confidence
+            || probability - runner_up < self.config.min_margin
+        {
+            return Prediction::Uncertain;
+        }
+        let (disease, probability) = match &self.config.costs {
+            Some(costs) => costs.decide(&top).unwrap(),
+            None => top[0],
+        };
+        Prediction::Disease {
+            disease,
+            probability,
+        }
+    }
+
+    /// Given a record of symptoms, makes a prediction as to which disease it is (along with its
+    /// score). Uncertain predictions are given the unknown label:
+    fn predict_one(&self, psymptoms: &Features) -> (&str, f64) {
+        let scores = self.log_scores(psymptoms);
+        let best = match &self.config.costs {
+            // The disease with the lowest expected cost rather than the most likely one:
+            Some(costs) => {
+                let probabilities = self.predict_top_k(psymptoms, scores.len());
+                costs
+                    .decide(&probabilities)
+                    .and_then(|(disease, _)| scores.iter().find(|&&(d, _)| d == disease).copied())
+            }
+            None => scores.iter().copied().min_by(|&a, &b| self.rank(a, b)),
+        };
+        let (best_disease, best_score) = best.unwrap_or(("", f64::NEG_INFINITY));
+
+        let abstains = self.config.min_confidence > 0.0 || self.config.min_margin > 0.0;
+        if abstains && self.predict_single(psymptoms) == Prediction::Uncertain {
+            return (&self.config.unknown_label, best_score.exp());
+        }
+
+        (best_disease, best_score.exp())
+    }
+
+    /// The shared symbol of a disease (or the unknown label) the classifier predicted:
+    fn symbol(&self, disease: &str) -> Symbol {
+        match self.disease_pis.get_key_value(disease) {
+            Some((symbol, _)) => symbol.clone(),
+            None => Symbol::new(disease),
+        }
+    }
+
+    /// Returns the `k` most likely diseases for the symptoms along with their (normalized)
+    /// probabilities, most likely first:
+    pub fn predict_top_k(&self, psymptoms: &Features, k: usize) -> Vec<(&str, f64)> {
+        let mut scores = self.probabilities(psymptoms);
+
+        // Calibrate each probability and then make sure they sum to one again:
+        if let Some(calibrator) = &self.calibrator {
+            for (_, p) in &mut scores {
+                *p = calibrator.apply(*p);
+            }
+            let total: f64 = scores.iter().map(|(_, p)| p).sum();
+            if total > 0.0 {
+                scores.iter_mut().for_each(|(_, p)| *p /= total);
+            }
+        }
+
+        scores.sort_by(|&a, &b| self.rank(a, b));
+        scores.truncate(k);
+        scores
+    }
+
+    /// Orders (disease, score) pairs best first. Ties go to the disease with the higher prior
+    /// and then to the alphabetically first disease, so predictions never depend on chance:
+    fn rank(&self, (disease_a, a): (&str, f64), (disease_b, b): (&str, f64)) -> Ordering {
+        b.total_cmp(&a)
+            .then_with(|| {
+                let pi_a = self.disease_pis.get(disease_a).copied().unwrap_or(0.0);
+                let pi_b = self.disease_pis.get(disease_b).copied().unwrap_or(0.0);
+                pi_b.total_cmp(&pi_a)
+            })
+            .then_with(|| disease_a.cmp(disease_b))
+    }
+
+    /// The (uncalibrated) posterior probability of every disease:
+    pub(crate) fn probabilities(&self, psymptoms: &Features) -> Vec<(&str, f64)> {
+        let mut scores = self.log_scores(psymptoms);
+
+        // Normalize in log space so that small products don't underflow:
+        let max_score = scores
+            .iter()
+            .fold(f64::NEG_INFINITY, |acc, &(_, score)| acc.max(score));
+        let total: f64 = scores
+            .iter()
+            .map(|(_, score)| (score - max_score).exp())
+            .sum();
+        for (_, score) in &mut scores {
+            *score = (*score - max_score).exp() / total;
+        }
+        scores
+    }
+
+    /// The log of pi * (product of betas) for every disease, where every beta is raised to the
+    /// count of its symptom (or the bernoulli likelihood of the symptoms), times the probabilities
+    /// of the categorical values and the densities of the numeric values. Symptoms that never
+    /// showed up during training carry no information and are skipped:
+    fn log_scores(&self, psymptoms: &Features) -> Vec<(&str, f64)> {
+        let psymptoms = &*self.prepare(psymptoms);
+
+        // Uninformative symptoms add (about) the same to every disease's score, so they're added
+        // once up front rather than looked up for every disease:
+        let mut shared = 0.0;
+        let informative: Vec<(&str, f64)> = psymptoms
+            .counts
+            .iter()
+            .filter(|&(_, &count)| self.config.model_kind == ModelKind::Multinomial || count > 0.0)
+            .filter_map(|(psymptom, &count)| {
+                if self.uninformative.is_empty() {
+                    return Some((psymptom.as_str(), count));
+                }
+                match self.uninformative.get(psymptom.as_str()) {
+                    Some(term) if self.config.model_kind == ModelKind::Multinomial => {
+                        shared += count * term;
+                        None
+                    }
+                    Some(term) => {
+                        shared += term;
+                        None
+                    }
+                    None => Some((psymptom.as_str(), count)),
+                }
+            })
+            .collect();
+
+        self.disease_betas
+            .iter()
+            .map(|(disease, betas)| {
+                let log_betas: f64 = match self.config.model_kind {
+                    ModelKind::Multinomial => informative
+                        .iter()
+                        .filter_map(|&(psymptom, count)| Some(count * betas.get(psymptom)?.ln()))
+                        .sum(),
+                    // Start from every symptom being absent and swap in the present ones:
+                    ModelKind::Bernoulli => {
+                        let present: f64 = informative
+                            .iter()
+                            .filter_map(|&(psymptom, _)| betas.get(psymptom))
+                            .map(|beta| beta.ln() - (1.0 - beta).ln())
+                            .sum();
+                        self.absent_log_probs[disease] + present
+                    }
+                };
+                let log_categories = self
+                    .categorical
+                    .log_likelihood(disease, &psymptoms.categories);
+                let log_numbers = self.gaussian.log_likelihood(disease, &psymptoms.numbers);
 
                 let pi = self.disease_pis.get(disease).unwrap();
-                let score = pi * product_betas;
+                (
+                    disease.as_str(),
+                    pi.ln() + shared + log_betas + log_categories + log_numbers,
+                )
+            })
+            .collect()
+    }
 
-                if score > best_score {
-                    (&disease, score)
-                } else {
-                    (best_disease, best_score)
+    /// Records are scored with the same missing values, pooling, symptom pairs, and weighting they
+    /// were trained with:
+    pub(crate) fn prepare<'a>(&self, psymptoms: &'a Features) -> Cow<'a, Features> {
+        let hashed = self.hash(psymptoms);
+        if self.tf_idf.is_none()
+            && self.feature_pairs.is_none()
+            && self.missing_values.is_empty()
+            && !self.config.pool_rare_features
+        {
+            return hashed;
+        }
+
+        let mut features = hashed.into_owned();
+        self.missing_values.fill(&mut features);
+        if self.config.pool_rare_features {
+            if let Some(betas) = self.disease_betas.values().next() {
+                pool_into_other(&mut features.counts, |symptom| betas.contains_key(symptom));
+            }
+        }
+        if let Some(feature_pairs) = &self.feature_pairs {
+            feature_pairs.transform(&mut features);
+        }
+        if let Some(tf_idf) = &self.tf_idf {
+            tf_idf.transform(&mut features);
+        }
+        Cow::Owned(features)
+    }
+
+    /// Hashes the symptoms of a record into buckets if the classifier was trained with feature
+    /// hashing:
+    pub(crate) fn hash<'a>(&self, psymptoms: &'a Features) -> Cow<'a, Features> {
+        match &self.config.feature_hasher {
+            Some(hasher) => {
+                let mut features = psymptoms.clone();
+                hasher.transform(&mut features);
+                Cow::Owned(features)
+            }
+            None => Cow::Borrowed(psymptoms),
+        }
+    }
+
+    /// Explains the prediction of a record by how much its prior and every feature add to the
+    /// log score of the predicted disease compared to the runner up:
+    pub fn explain(&self, psymptoms: &Features) -> Explanation {
+        let mut scores = self.log_scores(psymptoms);
+        scores.sort_by(|&a, &b| self.rank(a, b));
+        let winner = scores.first().map_or("", |&(disease, _)| disease);
+        let runner_up = scores.get(1).map(|&(disease, _)| disease);
+
+        let log_prior = |disease: Option<&str>| {
+            disease
+                .and_then(|disease| self.disease_pis.get(disease))
+                .map_or(0.0, |pi| pi.ln())
+        };
+        let prior = Contribution {
+            feature: "prior".to_string(),
+            winner: log_prior(Some(winner)),
+            runner_up: log_prior(runner_up),
+        };
+
+        // (winner, runner up) of every feature:
+        let psymptoms = self.prepare(psymptoms);
+        let mut contributions: BTreeMap<String, (f64, f64)> = BTreeMap::new();
+        for (feature, log_likelihood) in self.contributions(winner, &psymptoms) {
+            contributions.entry(feature).or_default().0 += log_likelihood;
+        }
+        if let Some(runner_up) = runner_up {
+            for (feature, log_likelihood) in self.contributions(runner_up, &psymptoms) {
+                contributions.entry(feature).or_default().1 += log_likelihood;
+            }
+        }
+
+        let mut features: Vec<Contribution> = contributions
+            .into_iter()
+            .map(|(feature, (winner, runner_up))| Contribution {
+                feature,
+                winner,
+                runner_up,
+            })
+            .collect();
+        features.sort_by(|a, b| {
+            b.difference()
+                .abs()
+                .total_cmp(&a.difference().abs())
+                .then_with(|| a.feature.cmp(&b.feature))
+        });
+
+        Explanation {
+            winner: winner.to_string(),
+            runner_up: runner_up.map(|disease| disease.to_string()),
+            prior,
+            features,
+        }
+    }
+
+    /// Compares how often every feature shows up in records to predict against the training
+    /// records, along with how many of their features are unseen and how likely every class is,
+    /// to tell when the classifier should be retrained:
+    pub fn drift_report(&self, new_data: &[Features]) -> DriftReport {
+        DriftReport::compute(self, new_data)
+    }
+
+    /// Compares this model against a newer one: added and removed classes and symptoms, changed
+    /// priors, and symptom likelihoods that moved by more than the threshold (in log
+    /// probability), to review a retrained model before replacing this one with it:
+    pub fn diff(&self, new: &NaiveBayesClassifier, threshold: f64) -> ModelDiff {
+        ModelDiff::compute(self, new, threshold)
+    }
+
+    /// The log scores of the two highest scoring diseases for a record, along with how many of
+    /// its features are unseen and how many are used, to triage which predictions to trust:
+    pub fn diagnose(&self, psymptoms: &Features) -> Diagnostics {
+        let mut scores = self.log_scores(psymptoms);
+        scores.sort_by(|&a, &b| self.rank(a, b));
+        let entry = |i: usize| {
+            scores
+                .get(i)
+                .map(|&(disease, score)| (disease.to_string(), score))
+        };
+
+        Diagnostics {
+            top: entry(0),
+            runner_up: entry(1),
+            unseen_features: self.count_features(&self.hash(psymptoms)).1,
+            used_features: self.count_features(&self.prepare(psymptoms)).0,
+        }
+    }
+
+    /// Writes the diagnostics of every record of a file as csv. The format of the input is
+    /// picked from its extension unless one is given. Returns the malformed rows that were
+    /// skipped:
+    pub fn write_diagnostics<P: AsRef<Path>>(
+        &self,
+        inpath: P,
+        outpath: P,
+        format: Option<Format>,
+    ) -> Result<ParseReport> {
+        let format = format.unwrap_or_else(|| Format::from_path(&inpath));
+        let (records, report) = format::read_records(inpath, format, &self.config)?;
+        let records: Vec<_> = records
+            .iter()
+            .enumerate()
+            .map(|(i, (id, features))| {
+                let id = id.clone().unwrap_or_else(|| (i + 1).to_string());
+                (id, self.diagnose(features))
+            })
+            .collect();
+        diagnostics::write_csv(outpath, &records)?;
+        Ok(report)
+    }
+
+    /// The number of (present) features of a record that showed up during training, and the
+    /// number that didn't:
+    fn count_features(&self, psymptoms: &Features) -> (usize, usize) {
+        let Some((disease, betas)) = self.disease_betas.iter().next() else {
+            return (0, 0);
+        };
+        let numeric_columns = self.gaussian.columns();
+        let symptoms = psymptoms
+            .counts
+            .iter()
+            .filter(|&(_, &count)| count > 0.0)
+            .map(|(symptom, _)| betas.contains_key(symptom.as_str()));
+        // Every disease has a probability for every value seen in a column:
+        let categories = psymptoms.categories.iter().map(|(column, value)| {
+            self.categorical
+                .value_log_prob(disease, column, value)
+                .is_some()
+        });
+        let numbers = psymptoms
+            .numbers
+            .keys()
+            .map(|column| numeric_columns.contains(&column.as_str()));
+
+        let known = symptoms.chain(categories).chain(numbers);
+        known.fold((0, 0), |(seen, unseen), known| {
+            if known {
+                (seen + 1, unseen)
+            } else {
+                (seen, unseen + 1)
+            }
+        })
+    }
+
+    /// The terms of a (prepared) record's log likelihood for the disease, one per feature, that
+    /// `log_scores` adds up:
+    fn contributions(&self, disease: &str, psymptoms: &Features) -> Vec<(String, f64)> {
+        let mut contributions = Vec::new();
+        if let Some(betas) = self.disease_betas.get(disease) {
+            match self.config.model_kind {
+                ModelKind::Multinomial => {
+                    for (psymptom, count) in &psymptoms.counts {
+                        if let Some(beta) = betas.get(psymptom.as_str()) {
+                            contributions.push((psymptom.to_string(), count * beta.ln()));
+                        }
+                    }
                 }
-            },
-        );
+                ModelKind::Bernoulli => {
+                    contributions
+                        .push((ABSENT_FEATURE.to_string(), self.absent_log_probs[disease]));
+                    for (psymptom, _) in psymptoms.counts.iter().filter(|&(_, &c)| c > 0.0) {
+                        if let Some(beta) = betas.get(psymptom.as_str()) {
+                            contributions
+                                .push((psymptom.to_string(), beta.ln() - (1.0 - beta).ln()));
+                        }
+                    }
+                }
+            }
+        }
+        for (column, value) in &psymptoms.categories {
+            if let Some(log_prob) = self.categorical.value_log_prob(disease, column, value) {
+                contributions.push((format!("{}={}", column, value), log_prob));
+            }
+        }
+        for (column, &x) in &psymptoms.numbers {
+            if let Some(log_density) = self.gaussian.log_density(disease, column, x) {
+                contributions.push((format!("{}={}", column, x), log_density));
+            }
+        }
+        contributions
+    }
 
-        best_disease
+    /// The malformed rows skipped while reading the training file in lenient mode:
+    pub fn training_report(&self) -> &ParseReport {
+        &self.training_report
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// All of the diseases the classifier knows about (sorted):
+    pub fn classes(&self) -> Vec<&str> {
+        self.disease_pis.keys().map(|c| c.as_str()).collect()
+    }
+
+    /// All of the symptoms seen during training (sorted):
+    pub fn features(&self) -> Vec<&str> {
+        let mut features: Vec<&str> = self
+            .disease_betas
+            .values()
+            .next()
+            .map(|betas| betas.keys().map(|f| f.as_str()).collect())
+            .unwrap_or_default();
+        features.sort();
+        features
+    }
+
+    /// The log of the pi value of a disease:
+    pub fn class_log_prior(&self, class: &str) -> Option<f64> {
+        self.disease_pis.get(class).map(|pi| pi.ln())
+    }
+
+    /// The categorical columns seen during training (sorted):
+    pub fn categorical_columns(&self) -> Vec<&str> {
+        self.categorical.columns()
+    }
+
+    /// The log probability of a categorical column taking on the value for a disease:
+    pub fn category_log_prob(&self, class: &str, column: &str, value: &str) -> Option<f64> {
+        self.categorical.value_log_prob(class, column, value)
+    }
+
+    /// The numeric columns seen during training (sorted):
+    pub fn numeric_columns(&self) -> Vec<&str> {
+        self.gaussian.columns()
+    }
+
+    /// The log density of a numeric column taking on the value for a disease:
+    pub fn numeric_log_density(&self, class: &str, column: &str, x: f64) -> Option<f64> {
+        self.gaussian.log_density(class, column, x)
+    }
+
+    /// The mean and variance of a numeric column for a disease:
+    pub fn numeric_params(&self, class: &str, column: &str) -> Option<(f64, f64)> {
+        self.gaussian.params(class, column)
+    }
+
+    /// The log of the beta value of a symptom for a disease:
+    pub fn feature_log_prob(&self, class: &str, feature: &str) -> Option<f64> {
+        self.disease_betas
+            .get(class)?
+            .get(feature)
+            .map(|beta| beta.ln())
+    }
+
+    /// The calibrator fitted to the probabilities, if the classifier has been calibrated:
+    pub(crate) fn calibrator(&self) -> Option<&Calibrator> {
+        self.calibrator.as_ref()
+    }
+
+    /// The pairs of symptoms added as features, when the classifier was trained with any:
+    pub fn feature_pairs(&self) -> Option<&FeaturePairs> {
+        self.feature_pairs.as_ref()
+    }
+
+    /// Every training symptom with its feature selection score, highest first. Only available
+    /// when the classifier was trained with feature selection:
+    pub fn feature_ranking(&self) -> Option<&[(String, f64)]> {
+        self.feature_ranking.as_deref()
+    }
+
+    /// The `n` symptoms with the highest beta value for a disease, most likely first:
+    pub fn top_features(&self, class: &str, n: usize) -> Vec<(&str, f64)> {
+        let mut features: Vec<(&str, f64)> = match self.disease_betas.get(class) {
+            Some(betas) => betas
+                .iter()
+                .map(|(feature, beta)| (feature.as_str(), beta.ln()))
+                .collect(),
+            None => return Vec::new(),
+        };
+
+        // Break ties by name so the report is the same every time:
+        features.sort_by(|(fa, a), (fb, b)| b.total_cmp(a).then_with(|| fa.cmp(fb)));
+        features.truncate(n);
+        features
+    }
+}
+
+/// Groups (disease, features, weight) records by disease:
+fn group_by_disease<I, S>(records: I) -> Result<DiseaseRecords>
+where
+    I: IntoIterator<Item = (S, Features, f64)>,
+    S: Into<String>,
+{
+    let mut diseases_map = DiseaseRecords::new();
+    for (disease, symptoms, weight) in records {
+        check_weight(weight)?;
+
+        match diseases_map.entry(disease.into()) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(Vec::new()),
+        }
+        .push((symptoms, weight));
+    }
+    Ok(diseases_map)
+}
+
+fn check_config(config: &Config) -> Result<()> {
+    if config.smoothing.is_nan() || config.smoothing <= 0.0 {
+        return Err(BayesError::InvalidConfig(format!(
+            "smoothing must be positive: {}",
+            config.smoothing
+        )));
+    }
+    if let Some(costs) = &config.costs {
+        costs.check()?;
+    }
+    if let Some(tolerance) = config.prune_uninformative {
+        if tolerance.is_nan() || tolerance < 0.0 {
+            return Err(BayesError::InvalidConfig(format!(
+                "uninformative symptom tolerance must not be negative: {}",
+                tolerance
+            )));
+        }
+    }
+    if config
+        .feature_hasher
+        .is_some_and(|hasher| hasher.buckets == 0)
+    {
+        return Err(BayesError::InvalidConfig(
+            "feature hashing needs at least one bucket.".to_string(),
+        ));
+    }
+    Ok(())
+}
+
+fn check_weight(weight: f64) -> Result<()> {
+    if weight.is_nan() || weight < 0.0 {
+        return Err(BayesError::InvalidConfig(format!(
+            "invalid weight: {}",
+            weight
+        )));
+    }
+    Ok(())
+}
+
+/// Fails if there is nothing to train on, and warns about what can still be trained on but
+/// probably isn't what was meant:
+fn check_training_set(weights: &BTreeMap<Symbol, f64>) -> Result<()> {
+    if weights.is_empty() || weights.values().sum::<f64>() <= 0.0 {
+        return Err(BayesError::EmptyTrainingSet);
+    }
+
+    if weights.len() == 1 {
+        log::warn!("only one disease to train on, every record will be predicted as it");
+    }
+    for (disease, &weight) in weights {
+        if weight < FEW_RECORDS {
+            log::warn!("disease {} only has {} training records", disease, weight);
+        }
+    }
+    Ok(())
+}
+
+fn check_memory(bytes: usize, limit: Option<usize>) -> Result<()> {
+    match limit {
+        Some(limit) if bytes > limit => Err(BayesError::MemoryLimit(limit)),
+        _ => Ok(()),
+    }
+}
+
+/// Counts up every file on its own, in parallel, returning the counters in the order of the files:
+fn count_files<P: AsRef<Path> + Sync>(
+    paths: &[P],
+    config: &Config,
+) -> Result<Vec<(Counter, ParseReport)>> {
+    // Progress bars from many threads would garble each other:
+    let quiet = Config {
+        progress: None,
+        ..config.clone()
+    };
+    let config = if paths.len() > 1 { &quiet } else { config };
+    let num_threads = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(paths.len());
+
+    // Each thread takes the next uncounted file until there are none left:
+    let next = Mutex::new(0);
+    let results = Mutex::new(paths.iter().map(|_| None).collect::<Vec<_>>());
+    thread::scope(|scope| {
+        for _ in 0..num_threads {
+            scope.spawn(|| loop {
+                let i = {
+                    let mut next = next.lock().unwrap();
+                    *next += 1;
+                    *next - 1
+                };
+                let Some(path) = paths.get(i) else {
+                    break;
+                };
+
+                let result = spreadsheet::open(path, config).and_then(|reader| {
+                    let mut counter = Counter::new(config);
+                    let report = format::for_each_training_record(reader, config, |record| {
+                        let (disease, features, weight) = record;
+                        counter.add(&disease, features, weight)
+                    })?;
+                    Ok((counter, report))
+                });
+                results.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    results
+        .into_inner()
+        .unwrap()
+        .into_iter()
+        .map(|result| result.unwrap())
+        .collect()
+}
+
+/// The rows skipped in every file, naming the file if there's more than one:
+fn merge_reports<P: AsRef<Path>>(paths: &[P], reports: Vec<ParseReport>) -> ParseReport {
+    if reports.len() == 1 {
+        return reports.into_iter().next().unwrap();
+    }
+    let mut merged = ParseReport::default();
+    for (path, report) in paths.iter().zip(reports) {
+        for mut skipped in report.skipped {
+            skipped.reason = format!("{}: {}", path.as_ref().display(), skipped.reason);
+            merged.skipped.push(skipped);
+        }
+    }
+    merged
+}
+
+/// Counts up training records one at a time, for configs where a record's counts don't depend on
+/// the rest of the training set. Only the counts are held on to, however many records there are.
+struct Counter {
+    memory_limit: Option<usize>,
+    feature_hasher: Option<FeatureHasher>,
+    stats: SufficientStats,
+    missing_values: MissingValues,
+    added: usize,
+    next_memory_check: usize,
+}
+
+impl Counter {
+    fn new(config: &Config) -> Self {
+        Counter {
+            memory_limit: config.memory_limit,
+            feature_hasher: config.feature_hasher,
+            stats: SufficientStats::default(),
+            // Nothing is imputed, so the fills don't depend on the records:
+            missing_values: MissingValues::fit(&config.schema, &DiseaseRecords::new()),
+            added: 0,
+            next_memory_check: MEMORY_CHECK_INTERVAL,
+        }
+    }
+
+    fn add(&mut self, disease: &str, mut features: Features, weight: f64) -> Result<()> {
+        check_weight(weight)?;
+        if let Some(hasher) = &self.feature_hasher {
+            hasher.transform(&mut features);
+        }
+        self.missing_values.fill(&mut features);
+        self.stats.add(disease, &features, weight);
+
+        // Estimating the memory used takes a pass over the counts, so it's done less often the
+        // more counts there are:
+        self.added += 1;
+        if self.memory_limit.is_some() && self.added >= self.next_memory_check {
+            let bytes = self.stats.estimated_bytes();
+            check_memory(bytes, self.memory_limit)?;
+            self.next_memory_check = self.added + MEMORY_CHECK_INTERVAL.max(bytes / ENTRY_BYTES);
+        }
+        Ok(())
+    }
+
+    fn finish(self, config: Config) -> Result<NaiveBayesClassifier> {
+        if self.memory_limit.is_some() {
+            check_memory(self.stats.estimated_bytes(), self.memory_limit)?;
+        }
+        check_training_set(&self.stats.records)?;
+        NaiveBayesClassifier::from_stats(self.stats, config, self.missing_values, None, None, None)
+    }
+}
+
+/// What a classifier is persisted as, everything else is derived from it:
+#[derive(Serialize)]
+struct StoredClassifierRef<'a> {
+    config: &'a Config,
+    stats: &'a SufficientStats,
+    missing_values: &'a MissingValues,
+    tf_idf: &'a Option<TfIdf>,
+    feature_pairs: &'a Option<FeaturePairs>,
+    feature_ranking: &'a Option<Vec<(String, f64)>>,
+    calibrator: &'a Option<Calibrator>,
+    training_report: &'a ParseReport,
+}
+
+#[derive(Deserialize)]
+struct StoredClassifier {
+    config: Config,
+    stats: SufficientStats,
+    missing_values: MissingValues,
+    tf_idf: Option<TfIdf>,
+    feature_pairs: Option<FeaturePairs>,
+    feature_ranking: Option<Vec<(String, f64)>>,
+    calibrator: Option<Calibrator>,
+    training_report: ParseReport,
+}
+
+impl Serialize for NaiveBayesClassifier {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        StoredClassifierRef {
+            config: &self.config,
+            stats: &self.stats,
+            missing_values: &self.missing_values,
+            tf_idf: &self.tf_idf,
+            feature_pairs: &self.feature_pairs,
+            feature_ranking: &self.feature_ranking,
+            calibrator: &self.calibrator,
+            training_report: &self.training_report,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for NaiveBayesClassifier {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        let stored = StoredClassifier::deserialize(deserializer)?;
+        let classifier = NaiveBayesClassifier::from_stats(
+            stored.stats,
+            stored.config,
+            stored.missing_values,
+            stored.tf_idf,
+            stored.feature_pairs,
+            stored.feature_ranking,
+        )
+        .map_err(de::Error::custom)?;
+        Ok(NaiveBayesClassifier {
+            calibrator: stored.calibrator,
+            training_report: stored.training_report,
+            ..classifier
+        })
+    }
+}
+
+/// Whether training transforms the records based on the whole training set, after which their
+/// counts no longer add up with those of other records:
+fn transforms_records(config: &Config) -> bool {
+    config.pool_rare_features
+        || config.tf_idf
+        || config.feature_pairs.is_some()
+        || config.feature_selection.is_some()
+}
+
+/// Whether the counts of records (trained on separately) simply add up, which they don't if
+/// records are transformed based on the rest of the training set:
+fn counts_add_up(config: &Config) -> bool {
+    !transforms_records(config) && !config.schema.imputes()
+}
+
+/// Whether rare (or too many) symptoms are dropped:
+fn prunes(config: &Config) -> bool {
+    config.min_feature_count > 1 || config.max_features.is_some()
+}
+
+/// The symptoms seen at least the minimum number of times, limited to the most frequent ones. Ties
+/// are broken by name:
+fn most_frequent<'a, I>(occurrences: I, config: &Config) -> HashSet<&'a str>
+where
+    I: IntoIterator<Item = (&'a str, f64)>,
+{
+    let mut frequent: Vec<(&str, f64)> = occurrences
+        .into_iter()
+        .filter(|&(_, count)| count >= config.min_feature_count as f64)
+        .collect();
+    frequent.sort_by(|(sa, a), (sb, b)| b.total_cmp(a).then_with(|| sa.cmp(sb)));
+    frequent.truncate(config.max_features.unwrap_or(usize::MAX));
+    frequent.into_iter().map(|(symptom, _)| symptom).collect()
+}
+
+/// The symptoms whose term of the log score (the log of the beta, or for bernoulli models the log
+/// odds of the beta) differs by at most the tolerance between any two diseases, along with the
+/// mean of their terms:
+fn uninformative_symptoms(
+    disease_betas: &BTreeMap<Symbol, HashMap<Symbol, f64>>,
+    model_kind: ModelKind,
+    tolerance: f64,
+) -> HashMap<Symbol, f64> {
+    let term = |beta: f64| match model_kind {
+        ModelKind::Multinomial => beta.ln(),
+        ModelKind::Bernoulli => beta.ln() - (1.0 - beta).ln(),
+    };
+    let Some(first) = disease_betas.values().next() else {
+        return HashMap::new();
+    };
+
+    let mut uninformative = HashMap::new();
+    for symptom in first.keys() {
+        let (mut min, mut max, mut total) = (f64::INFINITY, f64::NEG_INFINITY, 0.0);
+        // Every disease has a beta for every symptom of the vocabulary, visited in order:
+        for betas in disease_betas.values() {
+            let term = term(betas[symptom]);
+            min = min.min(term);
+            max = max.max(term);
+            total += term;
+        }
+        if max - min <= tolerance {
+            uninformative.insert(symptom.clone(), total / disease_betas.len() as f64);
+        }
+    }
+    log::info!(
+        "{} of {} symptoms are skipped as uninformative when predicting",
+        uninformative.len(),
+        first.len()
+    );
+    uninformative
+}
+
+/// The symptoms of the vocabulary the classifier is trained on, sorted:
+fn kept_symptoms<'a>(stats: &'a SufficientStats, config: &Config) -> Vec<&'a Symbol> {
+    let vocabulary = stats.vocabulary.iter();
+    if transforms_records(config) || !prunes(config) {
+        return vocabulary.collect();
+    }
+
+    let mut occurrences: HashMap<&str, f64> = HashMap::new();
+    for symptom_records in stats.symptom_records.values() {
+        for (symptom, weight) in symptom_records {
+            *occurrences.entry(symptom.as_str()).or_default() += weight;
+        }
+    }
+    let kept = most_frequent(occurrences, config);
+    vocabulary
+        .filter(|symptom| kept.contains(symptom.as_str()))
+        .collect()
+}
+
+/// Moves the counts of every symptom that isn't kept into the other feature:
+fn pool_into_other<F: Fn(&str) -> bool>(counts: &mut BTreeMap<Symbol, f64>, keep: F) {
+    let mut other = 0.0;
+    counts.retain(|symptom, count| {
+        if symptom == OTHER_FEATURE || keep(symptom) {
+            true
+        } else {
+            other += *count;
+            false
+        }
+    });
+    if other > 0.0 {
+        *counts.entry(Symbol::new(OTHER_FEATURE)).or_default() += other;
     }
 }
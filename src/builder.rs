@@ -0,0 +1,209 @@
+use crate::{
+    bayes::NaiveBayesClassifier,
+    config::{Config, IdColumn, ModelKind, ParseMode, Priors},
+    cost::CostMatrix,
+    error::Result,
+    features::Features,
+    hashing::FeatureHasher,
+    normalize::Normalizer,
+    progress::{Progress, ProgressCallback},
+    schema::CsvSchema,
+    selection::FeatureSelection,
+    text::Tokenizer,
+};
+use std::path::Path;
+
+/// Configures a classifier option by option before training it, e.g.
+///
+/// ```no_run
+/// # use cs145_bayes::{bayes::NaiveBayesClassifier, config::Priors};
+/// let classifier = NaiveBayesClassifier::builder()
+///     .smoothing(0.5)
+///     .priors(Priors::Uniform)
+///     .min_feature_count(2)
+///     .fit_csv("train.csv")?;
+/// # Ok::<(), cs145_bayes::error::BayesError>(())
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NaiveBayesBuilder {
+    config: Config,
+}
+
+impl NaiveBayesBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Starts from an existing config:
+    pub fn from_config(config: Config) -> Self {
+        NaiveBayesBuilder { config }
+    }
+
+    pub fn smoothing(mut self, alpha: f64) -> Self {
+        self.config.smoothing = alpha;
+        self
+    }
+
+    pub fn priors(mut self, priors: Priors) -> Self {
+        self.config.priors = priors;
+        self
+    }
+
+    pub fn schema(mut self, schema: CsvSchema) -> Self {
+        self.config.schema = schema;
+        self
+    }
+
+    pub fn model_kind(mut self, model_kind: ModelKind) -> Self {
+        self.config.model_kind = model_kind;
+        self
+    }
+
+    pub fn min_feature_count(mut self, count: usize) -> Self {
+        self.config.min_feature_count = count;
+        self
+    }
+
+    pub fn max_features(mut self, max_features: usize) -> Self {
+        self.config.max_features = Some(max_features);
+        self
+    }
+
+    /// Pools dropped and unknown symptoms into an other feature instead of ignoring them:
+    pub fn pool_rare_features(mut self, pool: bool) -> Self {
+        self.config.pool_rare_features = pool;
+        self
+    }
+
+    pub fn weight_column<S: Into<String>>(mut self, column: S) -> Self {
+        self.config.weight_column = Some(column.into());
+        self
+    }
+
+    /// Normalizes symptom names (and tokens) before they're counted or looked up:
+    pub fn normalizer(mut self, normalizer: Normalizer) -> Self {
+        self.config.normalizer = Some(normalizer);
+        self
+    }
+
+    /// Switches to text mode:
+    pub fn tokenizer(mut self, tokenizer: Tokenizer) -> Self {
+        self.config.tokenizer = Some(tokenizer);
+        self
+    }
+
+    /// Hashes symptom names into this many buckets:
+    pub fn feature_hashing(mut self, buckets: usize) -> Self {
+        self.config.feature_hasher = Some(FeatureHasher::new(buckets));
+        self
+    }
+
+    /// Adds up to `max_pairs` of the most dependent pairs of symptoms as features:
+    pub fn feature_pairs(mut self, max_pairs: usize) -> Self {
+        self.config.feature_pairs = Some(max_pairs);
+        self
+    }
+
+    pub fn tf_idf(mut self, tf_idf: bool) -> Self {
+        self.config.tf_idf = tf_idf;
+        self
+    }
+
+    pub fn feature_selection(mut self, selection: FeatureSelection) -> Self {
+        self.config.feature_selection = Some(selection);
+        self
+    }
+
+    /// Adds symptoms whose log likelihood differs by at most `tolerance` between the diseases to
+    /// every score once when predicting:
+    pub fn prune_uninformative(mut self, tolerance: f64) -> Self {
+        self.config.prune_uninformative = Some(tolerance);
+        self
+    }
+
+    /// Memoizes up to `capacity` predictions during batch prediction:
+    pub fn cache(mut self, capacity: usize) -> Self {
+        self.config.cache_predictions = true;
+        self.config.cache_capacity = capacity;
+        self
+    }
+
+    pub fn min_confidence(mut self, probability: f64) -> Self {
+        self.config.min_confidence = probability;
+        self
+    }
+
+    pub fn min_margin(mut self, margin: f64) -> Self {
+        self.config.min_margin = margin;
+        self
+    }
+
+    pub fn unknown_label<S: Into<String>>(mut self, label: S) -> Self {
+        self.config.unknown_label = label.into();
+        self
+    }
+
+    pub fn costs(mut self, costs: CostMatrix) -> Self {
+        self.config.costs = Some(costs);
+        self
+    }
+
+    pub fn id_column(mut self, column: IdColumn) -> Self {
+        self.config.id_column = Some(column);
+        self
+    }
+
+    pub fn parse_mode(mut self, mode: ParseMode) -> Self {
+        self.config.parse_mode = mode;
+        self
+    }
+
+    /// Fails training rather than (roughly) use more than `bytes` of memory:
+    pub fn memory_limit(mut self, bytes: usize) -> Self {
+        self.config.memory_limit = Some(bytes);
+        self
+    }
+
+    /// Seeds everything random done with the config, e.g. bootstrap samples:
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.config.seed = Some(seed);
+        self
+    }
+
+    pub fn progress<C: ProgressCallback + 'static>(mut self, callback: C) -> Self {
+        self.config.progress = Some(Progress::new(callback));
+        self
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    /// Trains on a csv file:
+    pub fn fit_csv<P: AsRef<Path>>(self, path: P) -> Result<NaiveBayesClassifier> {
+        NaiveBayesClassifier::with_config(path, self.config)
+    }
+
+    /// Trains on several csv files as if they were one:
+    pub fn fit_csv_files<P: AsRef<Path> + Sync>(self, paths: &[P]) -> Result<NaiveBayesClassifier> {
+        NaiveBayesClassifier::with_config_files(paths, self.config)
+    }
+
+    /// Trains on (disease, features) records:
+    pub fn fit<I, S>(self, records: I) -> Result<NaiveBayesClassifier>
+    where
+        I: IntoIterator<Item = (S, Features)>,
+        S: Into<String>,
+    {
+        NaiveBayesClassifier::fit(records, self.config)
+    }
+
+    /// Trains on (disease, features, weight) records:
+    pub fn fit_weighted<I, S>(self, records: I) -> Result<NaiveBayesClassifier>
+    where
+        I: IntoIterator<Item = (S, Features, f64)>,
+        S: Into<String>,
+    {
+        NaiveBayesClassifier::fit_weighted(records, self.config)
+    }
+}
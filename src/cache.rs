@@ -0,0 +1,77 @@
+use crate::{features::Features, symbols::Symbol};
+use std::collections::{HashMap, VecDeque};
+
+// Counts and numbers sorted by name (with the bits of the floats so they can be hashed), and
+// sorted categories:
+type Key = (
+    Vec<(Symbol, u64)>,
+    Vec<(Symbol, Symbol)>,
+    Vec<(Symbol, u64)>,
+);
+
+/// Memoizes predictions by their (normalized) features. Once `capacity` entries are stored the
+/// oldest entry is evicted to make room for a new one.
+pub struct PredictionCache<V> {
+    entries: HashMap<Key, V>,
+    order: VecDeque<Key>,
+    capacity: usize,
+}
+
+impl<V: Clone> PredictionCache<V> {
+    pub fn new(capacity: usize) -> Self {
+        PredictionCache {
+            entries: HashMap::new(),
+            order: VecDeque::new(),
+            capacity,
+        }
+    }
+
+    /// Returns the cached value for the features, computing (and storing) it with `f` if missing:
+    pub fn get_or_insert_with<F: FnOnce() -> V>(&mut self, features: &Features, f: F) -> V {
+        let key = Self::key(features);
+        if let Some(value) = self.entries.get(&key) {
+            return value.clone();
+        }
+
+        let value = f();
+        if self.capacity == 0 {
+            return value;
+        }
+
+        if self.entries.len() >= self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.entries.remove(&oldest);
+            }
+        }
+        self.order.push_back(key.clone());
+        self.entries.insert(key, value.clone());
+
+        value
+    }
+
+    // Maps have no order, so sort them so identical features produce the same key:
+    fn key(features: &Features) -> Key {
+        let mut counts: Vec<_> = features
+            .counts
+            .iter()
+            .map(|(feature, count)| (feature.clone(), count.to_bits()))
+            .collect();
+        counts.sort();
+
+        let mut categories: Vec<_> = features
+            .categories
+            .iter()
+            .map(|(column, value)| (column.clone(), value.clone()))
+            .collect();
+        categories.sort();
+
+        let mut numbers: Vec<_> = features
+            .numbers
+            .iter()
+            .map(|(column, x)| (column.clone(), x.to_bits()))
+            .collect();
+        numbers.sort();
+
+        (counts, categories, numbers)
+    }
+}
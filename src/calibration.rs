@@ -0,0 +1,146 @@
+use crate::error::{BayesError, Result};
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// How raw posterior probabilities are mapped to calibrated ones.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum CalibrationMethod {
+    /// Fits a sigmoid to the log odds of the raw probabilities.
+    Platt,
+    /// Fits a non-decreasing step function (pool adjacent violators).
+    Isotonic,
+}
+
+impl FromStr for CalibrationMethod {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "platt" => Ok(CalibrationMethod::Platt),
+            "isotonic" => Ok(CalibrationMethod::Isotonic),
+            _ => Err(BayesError::InvalidConfig(format!(
+                "unknown calibration method: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// A fitted map from raw probabilities to calibrated probabilities.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub enum Calibrator {
+    /// p' = 1 / (1 + exp(a * logit(p) + b))
+    Platt { a: f64, b: f64 },
+    /// Sorted (raw probability, calibrated probability) points that are interpolated between.
+    Isotonic { points: Vec<(f64, f64)> },
+}
+
+// Keeps logits finite for probabilities of exactly 0 or 1:
+const EPSILON: f64 = 1e-12;
+
+impl Calibrator {
+    /// Fits a calibrator on (raw probability, whether the class was correct) samples:
+    pub fn fit(method: CalibrationMethod, samples: &[(f64, bool)]) -> Result<Self> {
+        if samples.is_empty() {
+            return Err(BayesError::EmptyHoldoutSet);
+        }
+
+        match method {
+            CalibrationMethod::Platt => Ok(Self::fit_platt(samples)),
+            CalibrationMethod::Isotonic => Ok(Self::fit_isotonic(samples)),
+        }
+    }
+
+    /// Maps a raw probability to a calibrated one:
+    pub fn apply(&self, p: f64) -> f64 {
+        match self {
+            Calibrator::Platt { a, b } => 1.0 / (1.0 + (a * logit(p) + b).exp()),
+            Calibrator::Isotonic { points } => {
+                let i = points.partition_point(|&(x, _)| x < p);
+                if i == 0 {
+                    points[0].1
+                } else if i == points.len() {
+                    points[i - 1].1
+                } else {
+                    let (x0, y0) = points[i - 1];
+                    let (x1, y1) = points[i];
+                    y0 + (y1 - y0) * (p - x0) / (x1 - x0)
+                }
+            }
+        }
+    }
+
+    // Platt's method: Newton's method on the log loss, using his smoothed targets so that
+    // perfectly separable data doesn't blow up the parameters:
+    fn fit_platt(samples: &[(f64, bool)]) -> Self {
+        let num_pos = samples.iter().filter(|(_, correct)| *correct).count() as f64;
+        let num_neg = samples.len() as f64 - num_pos;
+        let target_pos = (num_pos + 1.0) / (num_pos + 2.0);
+        let target_neg = 1.0 / (num_neg + 2.0);
+
+        let mut a = 0.0;
+        let mut b = ((num_neg + 1.0) / (num_pos + 1.0)).ln();
+        for _ in 0..100 {
+            let (mut g_a, mut g_b) = (0.0, 0.0);
+            let (mut h_aa, mut h_ab, mut h_bb) = (1e-12, 0.0, 1e-12);
+            for &(p, correct) in samples {
+                let f = logit(p);
+                let t = if correct { target_pos } else { target_neg };
+                let q = 1.0 / (1.0 + (a * f + b).exp());
+
+                // Derivatives of the log loss with respect to a and b:
+                let d = t - q;
+                g_a += d * f;
+                g_b += d;
+
+                let w = q * (1.0 - q);
+                h_aa += w * f * f;
+                h_ab += w * f;
+                h_bb += w;
+            }
+
+            let det = h_aa * h_bb - h_ab * h_ab;
+            if det.abs() < 1e-18 {
+                break;
+            }
+            let step_a = (h_bb * g_a - h_ab * g_b) / det;
+            let step_b = (h_aa * g_b - h_ab * g_a) / det;
+            a -= step_a;
+            b -= step_b;
+
+            if step_a.abs() < 1e-10 && step_b.abs() < 1e-10 {
+                break;
+            }
+        }
+
+        Calibrator::Platt { a, b }
+    }
+
+    fn fit_isotonic(samples: &[(f64, bool)]) -> Self {
+        let mut sorted = samples.to_vec();
+        sorted.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        // Pool adjacent violators, each block is (sum of x, sum of y, count):
+        let mut blocks: Vec<(f64, f64, f64)> = Vec::new();
+        for (p, correct) in sorted {
+            blocks.push((p, if correct { 1.0 } else { 0.0 }, 1.0));
+            while blocks.len() > 1 {
+                let (x1, y1, n1) = blocks[blocks.len() - 1];
+                let (x0, y0, n0) = blocks[blocks.len() - 2];
+                if y0 / n0 <= y1 / n1 {
+                    break;
+                }
+                blocks.pop();
+                *blocks.last_mut().unwrap() = (x0 + x1, y0 + y1, n0 + n1);
+            }
+        }
+
+        let points = blocks.iter().map(|&(x, y, n)| (x / n, y / n)).collect();
+        Calibrator::Isotonic { points }
+    }
+}
+
+fn logit(p: f64) -> f64 {
+    let p = p.clamp(EPSILON, 1.0 - EPSILON);
+    (p / (1.0 - p)).ln()
+}
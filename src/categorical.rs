@@ -0,0 +1,75 @@
+use crate::symbols::Symbol;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// Per disease distributions over the values of every categorical column.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CategoricalModel {
+    // column -> disease -> value -> log probability
+    log_probs: HashMap<Symbol, HashMap<Symbol, HashMap<Symbol, f64>>>,
+}
+
+impl CategoricalModel {
+    /// Learns the (additively smoothed by `alpha`) distribution of each column's values for each
+    /// disease from the weighted counts of the training records' values. Records without a value
+    /// for a column don't count towards that column:
+    pub fn fit(
+        categories: &BTreeMap<Symbol, BTreeMap<Symbol, BTreeMap<Symbol, f64>>>,
+        diseases: &BTreeMap<Symbol, f64>,
+        alpha: f64,
+    ) -> Self {
+        let mut log_probs = HashMap::new();
+        for (column, disease_counts) in categories {
+            // Every value the column takes on:
+            let values: BTreeSet<&Symbol> = disease_counts
+                .values()
+                .flat_map(|counts| counts.keys())
+                .collect();
+
+            let mut disease_probs = HashMap::new();
+            for disease in diseases.keys() {
+                let counts = disease_counts.get(disease);
+                let total: f64 = counts.map_or(0.0, |counts| counts.values().sum());
+
+                let num_values = values.len() as f64;
+                let probs = values
+                    .iter()
+                    .map(|&value| {
+                        let count = counts.and_then(|c| c.get(value)).copied().unwrap_or(0.0);
+                        let p = (count + alpha) / (total + alpha * num_values);
+                        (value.clone(), p.ln())
+                    })
+                    .collect();
+                disease_probs.insert(disease.clone(), probs);
+            }
+            log_probs.insert(column.clone(), disease_probs);
+        }
+
+        CategoricalModel { log_probs }
+    }
+
+    /// The sum of the log probabilities of a record's categorical values for the disease. Columns
+    /// and values never seen during training carry no information and are skipped:
+    pub fn log_likelihood(&self, disease: &str, categories: &BTreeMap<Symbol, Symbol>) -> f64 {
+        categories
+            .iter()
+            .filter_map(|(column, value)| self.value_log_prob(disease, column, value))
+            .sum()
+    }
+
+    /// The log probability of a column taking on the value for the disease:
+    pub fn value_log_prob(&self, disease: &str, column: &str, value: &str) -> Option<f64> {
+        self.log_probs
+            .get(column)?
+            .get(disease)?
+            .get(value)
+            .copied()
+    }
+
+    /// All of the categorical columns seen during training (sorted):
+    pub fn columns(&self) -> Vec<&str> {
+        let mut columns: Vec<&str> = self.log_probs.keys().map(|c| c.as_str()).collect();
+        columns.sort();
+        columns
+    }
+}
@@ -0,0 +1,187 @@
+use crate::{
+    config::Config,
+    dialect::CsvDialect,
+    error::{BayesError, Result},
+    hashing::FeatureHasher,
+    normalize::Normalizer,
+    schema::CsvSchema,
+    spreadsheet::SheetConfig,
+    stats::SufficientStats,
+    text::Tokenizer,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Where and how often training saves the counts it has so far, so that it can be resumed after
+/// being interrupted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CheckpointConfig {
+    pub path: PathBuf,
+    /// How many rows of the training file are read between checkpoints.
+    pub every: u64,
+    /// Whether training continues from the checkpoint at the path (if there is one) rather than
+    /// starting over.
+    pub resume: bool,
+}
+
+impl Default for CheckpointConfig {
+    fn default() -> Self {
+        CheckpointConfig {
+            path: PathBuf::from("checkpoint.json"),
+            every: 1_000_000,
+            resume: false,
+        }
+    }
+}
+
+impl CheckpointConfig {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        CheckpointConfig {
+            path: path.into(),
+            ..Self::default()
+        }
+    }
+}
+
+/// The training file a checkpoint counted the first rows of. Resuming on a file that was moved,
+/// grew, or was touched since would skip the wrong rows.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct InputFile {
+    path: PathBuf,
+    size: u64,
+    /// The modification time as seconds and nanoseconds since the epoch, if the platform has it.
+    modified: Option<(u64, u32)>,
+}
+
+impl InputFile {
+    pub fn of(path: &Path) -> Result<Self> {
+        let metadata = fs::metadata(path)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|since| (since.as_secs(), since.subsec_nanos()));
+        Ok(InputFile {
+            path: fs::canonicalize(path)?,
+            size: metadata.len(),
+            modified,
+        })
+    }
+}
+
+/// The options that decide how the rows of a training file are counted. Everything else (e.g.
+/// smoothing, or where the model is saved) can change between runs without invalidating the
+/// counts:
+#[derive(Serialize)]
+struct CountingConfig<'a> {
+    weight_column: &'a Option<String>,
+    schema: &'a CsvSchema,
+    tokenizer: &'a Option<Tokenizer>,
+    normalizer: &'a Option<Normalizer>,
+    feature_hasher: &'a Option<FeatureHasher>,
+    sheet: &'a SheetConfig,
+    dialect: &'a CsvDialect,
+}
+
+impl<'a> CountingConfig<'a> {
+    fn to_value(config: &'a Config) -> Result<serde_json::Value> {
+        Ok(serde_json::to_value(CountingConfig {
+            weight_column: &config.weight_column,
+            schema: &config.schema,
+            tokenizer: &config.tokenizer,
+            normalizer: &config.normalizer,
+            feature_hasher: &config.feature_hasher,
+            sheet: &config.sheet,
+            dialect: &config.dialect,
+        })?)
+    }
+}
+
+/// The counts of the first rows of a training file, along with the file and the options they
+/// were counted with.
+#[derive(Deserialize)]
+pub(crate) struct Checkpoint {
+    config: serde_json::Value,
+    input: InputFile,
+    pub rows: u64,
+    pub stats: SufficientStats,
+}
+
+#[derive(Serialize)]
+struct CheckpointRef<'a> {
+    config: serde_json::Value,
+    input: &'a InputFile,
+    rows: u64,
+    stats: &'a SufficientStats,
+}
+
+impl Checkpoint {
+    /// Reads the checkpoint at the path if there is one. Counts of a different (or changed)
+    /// training file, or counted with different options, can't be resumed from:
+    pub fn load(path: &Path, config: &Config, input: &InputFile) -> Result<Option<Self>> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        let checkpoint: Checkpoint = serde_json::from_reader(BufReader::new(file))?;
+        if checkpoint.config != CountingConfig::to_value(config)? {
+            return Err(BayesError::InvalidConfig(format!(
+                "checkpoint {} was made with different options for reading and counting \
+                 records.",
+                path.display()
+            )));
+        }
+        if checkpoint.input != *input {
+            return Err(BayesError::InvalidConfig(format!(
+                "checkpoint {} was made from {}, which has changed since (or is a different \
+                 file).",
+                path.display(),
+                checkpoint.input.path.display()
+            )));
+        }
+        Ok(Some(checkpoint))
+    }
+
+    /// Writes the checkpoint next to the path first and then moves it over the old one, so an
+    /// interruption never leaves a half written checkpoint behind:
+    pub fn save(
+        path: &Path,
+        config: &Config,
+        input: &InputFile,
+        rows: u64,
+        stats: &SufficientStats,
+    ) -> Result<()> {
+        let mut temporary = path.as_os_str().to_owned();
+        temporary.push(".tmp");
+
+        let mut writer = BufWriter::new(File::create(&temporary)?);
+        let checkpoint = CheckpointRef {
+            config: CountingConfig::to_value(config)?,
+            input,
+            rows,
+            stats,
+        };
+        serde_json::to_writer(&mut writer, &checkpoint)?;
+        writer.flush()?;
+        writer.get_ref().sync_all()?;
+        drop(writer);
+
+        fs::rename(&temporary, path)?;
+        log::info!("checkpointed {} rows to {}", rows, path.display());
+        Ok(())
+    }
+
+    /// Removes the checkpoint once training is done with it:
+    pub fn remove(path: &Path) -> Result<()> {
+        match fs::remove_file(path) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,167 @@
+//! Training on and predicting Apache Arrow record batches and Parquet files, so data doesn't have
+//! to be exported to csv first.
+
+use crate::{
+    bayes::{BatchPrediction, NaiveBayesClassifier},
+    config::Config,
+    error::{BayesError, Result},
+    features::Features,
+    format::TrainingRecord,
+};
+use arrow::{
+    array::{Array, AsArray},
+    compute,
+    datatypes::{DataType, Float64Type},
+    record_batch::RecordBatch,
+    util::display::{ArrayFormatter, FormatOptions},
+};
+use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+use std::{fs::File, path::Path};
+
+/// Which columns of a record batch hold what.
+#[derive(Clone, Debug, Default)]
+pub struct ArrowColumns {
+    /// The column holding the disease of training records, or the ID of records being predicted
+    /// (the first column if there is none).
+    pub label: Option<String>,
+    /// The columns holding features (every other column if there are none). They are read the
+    /// way the schema says, just like csv columns.
+    pub features: Option<Vec<String>>,
+}
+
+/// Reads every record batch of a Parquet file:
+pub fn read_parquet<P: AsRef<Path>>(path: P) -> Result<Vec<RecordBatch>> {
+    let reader = ParquetRecordBatchReaderBuilder::try_new(File::open(path)?)?.build()?;
+    Ok(reader.collect::<Result<_, _>>()?)
+}
+
+/// Reads the label (the disease or ID), features, and weight (from the configured weight
+/// column, 1 otherwise) of every row of a record batch:
+pub fn read_batch(
+    batch: &RecordBatch,
+    columns: &ArrowColumns,
+    config: &Config,
+) -> Result<Vec<TrainingRecord>> {
+    let schema = batch.schema();
+    let index_of = |column: &str| {
+        schema
+            .index_of(column)
+            .map_err(|_| BayesError::MissingColumn(column.to_string()))
+    };
+
+    let label_index = match &columns.label {
+        Some(column) => index_of(column)?,
+        None if batch.num_columns() > 0 => 0,
+        None => return Err(BayesError::MissingColumn("label".to_string())),
+    };
+    let weight_index = config.weight_column.as_deref().map(index_of).transpose()?;
+    let feature_indices: Vec<usize> = match &columns.features {
+        Some(features) => features
+            .iter()
+            .map(|column| index_of(column))
+            .collect::<Result<_>>()?,
+        None => (0..batch.num_columns())
+            .filter(|&i| i != label_index && Some(i) != weight_index)
+            .collect(),
+    };
+
+    let options = FormatOptions::default();
+    let labels = ArrayFormatter::try_new(batch.column(label_index).as_ref(), &options)?;
+    let weights = match weight_index {
+        Some(index) => Some(compute::cast(batch.column(index), &DataType::Float64)?),
+        None => None,
+    };
+    let features = feature_indices
+        .iter()
+        .map(|&i| {
+            let formatter = ArrayFormatter::try_new(batch.column(i).as_ref(), &options)?;
+            Ok((schema.field(i).name().as_str(), batch.column(i), formatter))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    let mut records = Vec::with_capacity(batch.num_rows());
+    for row in 0..batch.num_rows() {
+        let weight = match &weights {
+            Some(weights) if weights.is_null(row) => 1.0,
+            Some(weights) => weights.as_primitive::<Float64Type>().value(row),
+            None => 1.0,
+        };
+
+        // Nulls are blank fields:
+        let values: Vec<(&str, String)> = features
+            .iter()
+            .map(|(column, array, formatter)| {
+                let value = if array.is_null(row) {
+                    String::new()
+                } else {
+                    formatter.value(row).to_string()
+                };
+                (*column, value)
+            })
+            .collect();
+        let fields = values
+            .iter()
+            .map(|(column, value)| (*column, value.as_str()));
+
+        let label = labels.value(row).to_string();
+        records.push((label, Features::from_fields(fields, config), weight));
+    }
+
+    Ok(records)
+}
+
+impl NaiveBayesClassifier {
+    /// Trains on record batches, e.g. read from a data lake:
+    pub fn fit_record_batches(
+        batches: &[RecordBatch],
+        columns: &ArrowColumns,
+        config: Config,
+    ) -> Result<Self> {
+        let mut records = Vec::new();
+        for batch in batches {
+            records.extend(read_batch(batch, columns, &config)?);
+        }
+        Self::fit_weighted(records, config)
+    }
+
+    /// Trains on a Parquet file:
+    pub fn fit_parquet<P: AsRef<Path>>(
+        path: P,
+        columns: &ArrowColumns,
+        config: Config,
+    ) -> Result<Self> {
+        Self::fit_record_batches(&read_parquet(path)?, columns, config)
+    }
+
+    /// Predicts every row of the record batches, the label column holds their IDs:
+    pub fn predict_record_batches(
+        &self,
+        batches: &[RecordBatch],
+        columns: &ArrowColumns,
+    ) -> Result<Vec<BatchPrediction>> {
+        let mut ids = Vec::new();
+        let mut records = Vec::new();
+        for batch in batches {
+            for (id, features, _) in read_batch(batch, columns, self.config())? {
+                ids.push(id);
+                records.push(features);
+            }
+        }
+
+        let mut results = self.predict_batch(&records);
+        for (result, id) in results.iter_mut().zip(ids) {
+            result.id = id;
+            result.id_from_input = true;
+        }
+        Ok(results)
+    }
+
+    /// Predicts every row of a Parquet file, the label column holds their IDs:
+    pub fn predict_parquet<P: AsRef<Path>>(
+        &self,
+        path: P,
+        columns: &ArrowColumns,
+    ) -> Result<Vec<BatchPrediction>> {
+        self.predict_record_batches(&read_parquet(path)?, columns)
+    }
+}
@@ -0,0 +1,129 @@
+use crate::error::Result;
+#[cfg(feature = "compression")]
+use flate2::{read::MultiGzDecoder, write::GzEncoder};
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+/// How a file is compressed, picked by its (last) extension.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        match path.as_ref().extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Compression::Gzip,
+            Some("zst") | Some("zstd") => Compression::Zstd,
+            _ => Compression::None,
+        }
+    }
+}
+
+/// Opens a file for reading, transparently decompressing it if its extension says so:
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Box<dyn Read>> {
+    let path = path.as_ref();
+    let file = BufReader::new(File::open(path).map_err(|e| with_path(e, path))?);
+    Ok(match Compression::from_path(path) {
+        Compression::None => Box::new(file),
+        #[cfg(feature = "compression")]
+        Compression::Gzip => Box::new(MultiGzDecoder::new(file)),
+        #[cfg(feature = "compression")]
+        Compression::Zstd => Box::new(zstd::Decoder::with_buffer(file)?),
+        #[cfg(not(feature = "compression"))]
+        _ => return Err(unsupported(path)),
+    })
+}
+
+/// Creates a file for writing, compressing what's written if its extension says so. The writer
+/// has to be finished with `finish`, dropping it instead loses any error writing the end of the
+/// file:
+pub fn create<P: AsRef<Path>>(path: P) -> Result<FileWriter> {
+    let path = path.as_ref();
+    let file = BufWriter::new(File::create(path).map_err(|e| with_path(e, path))?);
+    let stream = match Compression::from_path(path) {
+        Compression::None => Stream::Plain(file),
+        #[cfg(feature = "compression")]
+        Compression::Gzip => Stream::Gzip(GzEncoder::new(file, flate2::Compression::default())),
+        #[cfg(feature = "compression")]
+        Compression::Zstd => Stream::Zstd(zstd::Encoder::new(file, 0)?),
+        #[cfg(not(feature = "compression"))]
+        _ => return Err(unsupported(path)),
+    };
+    Ok(FileWriter { stream })
+}
+
+/// A file being written by `create`, compressed or not.
+pub struct FileWriter {
+    stream: Stream,
+}
+
+enum Stream {
+    Plain(BufWriter<File>),
+    #[cfg(feature = "compression")]
+    Gzip(GzEncoder<BufWriter<File>>),
+    #[cfg(feature = "compression")]
+    Zstd(zstd::Encoder<'static, BufWriter<File>>),
+}
+
+impl FileWriter {
+    /// Finishes the compressed stream (if any) and flushes everything to the file:
+    #[cfg(feature = "compression")]
+    pub fn finish(self) -> Result<()> {
+        let file = match self.stream {
+            Stream::Plain(file) => file,
+            Stream::Gzip(encoder) => encoder.finish()?,
+            Stream::Zstd(encoder) => encoder.finish()?,
+        };
+        file.into_inner().map_err(|e| e.into_error())?;
+        Ok(())
+    }
+
+    /// Flushes everything to the file:
+    #[cfg(not(feature = "compression"))]
+    pub fn finish(self) -> Result<()> {
+        let Stream::Plain(file) = self.stream;
+        file.into_inner().map_err(|e| e.into_error())?;
+        Ok(())
+    }
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.stream {
+            Stream::Plain(file) => file.write(buf),
+            #[cfg(feature = "compression")]
+            Stream::Gzip(encoder) => encoder.write(buf),
+            #[cfg(feature = "compression")]
+            Stream::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.stream {
+            Stream::Plain(file) => file.flush(),
+            #[cfg(feature = "compression")]
+            Stream::Gzip(encoder) => encoder.flush(),
+            #[cfg(feature = "compression")]
+            Stream::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+// Io errors don't say which file they're about:
+fn with_path(e: io::Error, path: &Path) -> io::Error {
+    io::Error::new(e.kind(), format!("{}: {}", path.display(), e))
+}
+
+#[cfg(not(feature = "compression"))]
+fn unsupported(path: &Path) -> crate::error::BayesError {
+    crate::error::BayesError::Unsupported(format!(
+        "{} is compressed, but compression support isn't enabled.",
+        path.display()
+    ))
+}
@@ -0,0 +1,239 @@
+use crate::{
+    cost::CostMatrix,
+    dialect::CsvDialect,
+    error::{BayesError, Result},
+    format::PredictOutputConfig,
+    hashing::FeatureHasher,
+    normalize::Normalizer,
+    progress::Progress,
+    schema::CsvSchema,
+    selection::FeatureSelection,
+    spreadsheet::SheetConfig,
+    text::Tokenizer,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+/// How the prior probability of each disease (its pi value) is chosen.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub enum Priors {
+    /// The fraction of training records with the disease.
+    #[default]
+    Empirical,
+    /// Every disease is equally likely.
+    Uniform,
+    /// User supplied weights per disease, normalized to sum to one. Every disease in the
+    /// training set must have one.
+    Custom(HashMap<String, f64>),
+}
+
+impl Priors {
+    /// Reads custom priors from a csv file with a disease and a weight column:
+    pub fn from_csv<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut reader = csv::Reader::from_path(path)?;
+
+        let mut priors = HashMap::new();
+        for record in reader.records() {
+            let record = record?;
+            let disease = record.get(0).ok_or_else(|| {
+                BayesError::parse(record.position(), None, "missing disease entry.")
+            })?;
+            let weight = record.get(1).ok_or_else(|| {
+                BayesError::parse(record.position(), None, "missing prior entry.")
+            })?;
+            let weight: f64 = weight.trim().parse().map_err(|_| {
+                let message = format!("invalid prior for {}: {}", disease, weight);
+                BayesError::parse(record.position(), None, message)
+            })?;
+            priors.insert(disease.trim().to_string(), weight);
+        }
+
+        Ok(Priors::Custom(priors))
+    }
+}
+
+/// How the symptoms (or tokens) of a record are modeled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ModelKind {
+    /// Every occurrence of a symptom is a draw from a per disease distribution over symptoms.
+    #[default]
+    Multinomial,
+    /// Every symptom is independently present or absent with a per disease probability, so
+    /// absent symptoms also count towards the likelihood.
+    Bernoulli,
+}
+
+impl FromStr for ModelKind {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "multinomial" => Ok(ModelKind::Multinomial),
+            "bernoulli" => Ok(ModelKind::Bernoulli),
+            _ => Err(BayesError::InvalidConfig(format!(
+                "unknown model kind: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// The column of the records being predicted holding their IDs.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum IdColumn {
+    /// A csv column or jsonl field with this name.
+    Name(String),
+    /// The (0 based) csv column at this position.
+    Index(usize),
+}
+
+impl FromStr for IdColumn {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().parse() {
+            Ok(index) => Ok(IdColumn::Index(index)),
+            Err(_) => Ok(IdColumn::Name(s.trim().to_string())),
+        }
+    }
+}
+
+/// What happens to malformed rows of input files.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ParseMode {
+    /// Fail on the first malformed row, pointing at its row and column.
+    #[default]
+    Strict,
+    /// Skip malformed rows, collecting them into a report.
+    Lenient,
+}
+
+impl FromStr for ParseMode {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "strict" => Ok(ParseMode::Strict),
+            "lenient" => Ok(ParseMode::Lenient),
+            _ => Err(BayesError::InvalidConfig(format!(
+                "unknown parse mode: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Options controlling how the classifier is trained and how it predicts. Fields missing when
+/// deserializing take their default values.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// How the symptoms of a record are modeled.
+    pub model_kind: ModelKind,
+    /// The additive (Laplace) smoothing count added to every symptom and category.
+    pub smoothing: f64,
+    /// How the disease priors are picked during training.
+    pub priors: Priors,
+    /// Symptoms seen (weighted) fewer than this many times during training are dropped.
+    pub min_feature_count: usize,
+    /// Only the most frequent training symptoms up to this many are kept.
+    pub max_features: Option<usize>,
+    /// Whether symptoms that aren't kept (and unknown symptoms at prediction time) are pooled
+    /// into a single other feature rather than dropped.
+    pub pool_rare_features: bool,
+    /// Name of a training csv column holding a weight for each record. A record then counts as
+    /// that (possibly fractional) number of records towards the priors and betas.
+    pub weight_column: Option<String>,
+    /// Declares how each csv column is interpreted (all columns are symptoms by default).
+    pub schema: CsvSchema,
+    /// Text mode: every field of a record is raw text that is split into tokens by the tokenizer
+    /// (at training and prediction time) rather than being a symptom.
+    pub tokenizer: Option<Tokenizer>,
+    /// Normalizes symptom (and token) names before they are used as features.
+    pub normalizer: Option<Normalizer>,
+    /// Hashes (normalized) symptom and token names into a fixed number of buckets, which are
+    /// then the features.
+    pub feature_hasher: Option<FeatureHasher>,
+    /// Adds up to this many pairs of symptoms as features of their own (present when both
+    /// symptoms are), picking the pairs that depend on each other the most given the disease.
+    pub feature_pairs: Option<usize>,
+    /// Whether feature counts are re-weighted by TF-IDF (fitted on the training records).
+    pub tf_idf: bool,
+    /// Only train on the highest scoring symptoms.
+    pub feature_selection: Option<FeatureSelection>,
+    /// Symptoms whose log likelihood differs by at most this much between any two diseases are
+    /// added to every score once when predicting, rather than looked up for every disease. With
+    /// 0 only symptoms that can't tell the diseases apart are, which leaves the probabilities the
+    /// same, larger tolerances trade accuracy for speed.
+    pub prune_uninformative: Option<f64>,
+    /// Whether batch prediction should memoize predictions of identical symptom sets.
+    pub cache_predictions: bool,
+    /// The most predictions the cache holds before the oldest ones are evicted.
+    pub cache_capacity: usize,
+    /// Predictions whose top probability is below this are reported as uncertain.
+    pub min_confidence: f64,
+    /// Predictions whose top probability beats the runner up by less than this are reported as
+    /// uncertain.
+    pub min_margin: f64,
+    /// The label written out for uncertain predictions.
+    pub unknown_label: String,
+    /// Misclassification costs: the classifier predicts the disease with the lowest expected
+    /// cost rather than the most likely one.
+    pub costs: Option<CostMatrix>,
+    /// What's written along with every prediction.
+    pub output: PredictOutputConfig,
+    /// Where the IDs of predicted records come from, they are numbered from 1 if there is none.
+    pub id_column: Option<IdColumn>,
+    /// Which sheet of a workbook (training and prediction files alike) records are read from.
+    pub sheet: SheetConfig,
+    /// How csv training and prediction files are encoded and delimited.
+    pub dialect: CsvDialect,
+    /// What happens to malformed rows of training and prediction files.
+    pub parse_mode: ParseMode,
+    /// Training fails rather than (roughly) use more than this many bytes for the records and
+    /// counts it holds.
+    pub memory_limit: Option<usize>,
+    /// Seeds everything random done with the config: cross validation folds, bootstrap samples,
+    /// self-training pools, and posterior samples. It takes the place of their own seeds, and
+    /// cross validation folds are only shuffled with one.
+    pub seed: Option<u64>,
+    /// Reports how many records have been read and predicted so far. It isn't serialized.
+    #[serde(skip)]
+    pub progress: Option<Progress>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            model_kind: ModelKind::Multinomial,
+            smoothing: 1.0,
+            priors: Priors::Empirical,
+            min_feature_count: 0,
+            max_features: None,
+            pool_rare_features: false,
+            weight_column: None,
+            schema: CsvSchema::new(),
+            tokenizer: None,
+            normalizer: None,
+            feature_hasher: None,
+            feature_pairs: None,
+            tf_idf: false,
+            feature_selection: None,
+            prune_uninformative: None,
+            cache_predictions: false,
+            cache_capacity: 10_000,
+            min_confidence: 0.0,
+            min_margin: 0.0,
+            unknown_label: "UNKNOWN".to_string(),
+            costs: None,
+            output: PredictOutputConfig::default(),
+            id_column: None,
+            sheet: SheetConfig::default(),
+            dialect: CsvDialect::default(),
+            parse_mode: ParseMode::Strict,
+            memory_limit: None,
+            seed: None,
+            progress: None,
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use crate::error::{BayesError, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+/// The cost of predicting one disease when a record actually has another. Pairs without a cost
+/// cost nothing when the prediction is right and 1 when it's wrong, so an empty matrix picks the
+/// most likely disease like the classifier does without one.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct CostMatrix {
+    // Actual disease -> predicted disease -> cost:
+    costs: BTreeMap<String, BTreeMap<String, f64>>,
+}
+
+impl CostMatrix {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a csv file whose header row names the predicted diseases (after a first cell that's
+    /// ignored), and whose other rows start with the actual disease followed by its costs, e.g.
+    ///
+    /// ```text
+    /// actual,Flu,Cold,Allergy
+    /// Flu,0,5,10
+    /// Cold,1,0,1
+    /// ```
+    ///
+    /// Empty cells keep the default cost:
+    pub fn from_csv<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut reader = csv::Reader::from_path(path)?;
+        let predicted: Vec<String> = reader
+            .headers()?
+            .iter()
+            .skip(1)
+            .map(|disease| disease.trim().to_string())
+            .collect();
+
+        let mut matrix = CostMatrix::new();
+        for record in reader.records() {
+            let record = record?;
+            let actual = record.get(0).unwrap_or("").trim();
+            for (field, predicted) in record.iter().skip(1).zip(&predicted) {
+                let field = field.trim();
+                if field.is_empty() {
+                    continue;
+                }
+                let cost = field.parse().map_err(|_| {
+                    BayesError::parse(
+                        record.position(),
+                        Some(predicted),
+                        format!("invalid cost: {}", field),
+                    )
+                })?;
+                matrix.set(actual, predicted, cost);
+            }
+        }
+        matrix.check()?;
+        Ok(matrix)
+    }
+
+    /// Sets the cost of predicting `predicted` when the record actually has `actual`:
+    pub fn set<S: Into<String>>(&mut self, actual: S, predicted: S, cost: f64) {
+        self.costs
+            .entry(actual.into())
+            .or_default()
+            .insert(predicted.into(), cost);
+    }
+
+    pub fn cost(&self, actual: &str, predicted: &str) -> f64 {
+        match self
+            .costs
+            .get(actual)
+            .and_then(|costs| costs.get(predicted))
+        {
+            Some(&cost) => cost,
+            None if actual == predicted => 0.0,
+            None => 1.0,
+        }
+    }
+
+    /// Costs have to be finite and not negative:
+    pub fn check(&self) -> Result<()> {
+        for (actual, costs) in &self.costs {
+            for (predicted, &cost) in costs {
+                if !cost.is_finite() || cost < 0.0 {
+                    return Err(BayesError::InvalidConfig(format!(
+                        "invalid cost of predicting {} for {}: {}",
+                        predicted, actual, cost
+                    )));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// The expected cost of predicting a disease given the probability of every disease:
+    pub fn expected_cost(&self, predicted: &str, probabilities: &[(&str, f64)]) -> f64 {
+        probabilities
+            .iter()
+            .map(|&(actual, p)| p * self.cost(actual, predicted))
+            .sum()
+    }
+
+    /// The disease with the lowest expected cost, along with its probability. Ties go to the
+    /// disease that comes first, so pass the probabilities most likely first:
+    pub fn decide<'a>(&self, probabilities: &[(&'a str, f64)]) -> Option<(&'a str, f64)> {
+        let mut best: Option<((&'a str, f64), f64)> = None;
+        for &(disease, p) in probabilities {
+            let cost = self.expected_cost(disease, probabilities);
+            if best.is_none_or(|(_, best_cost)| cost < best_cost) {
+                best = Some(((disease, p), cost));
+            }
+        }
+        best.map(|(prediction, _)| prediction)
+    }
+}
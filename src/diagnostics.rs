@@ -0,0 +1,69 @@
+use crate::{compress, error::Result};
+use std::path::Path;
+
+/// How far a prediction can be trusted: how far ahead of the runner up its disease scored, and
+/// how much of the record the classifier knew about.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Diagnostics {
+    /// The disease with the highest log score with that score (log of pi times the likelihood).
+    pub top: Option<(String, f64)>,
+    /// The disease with the second highest log score with that score.
+    pub runner_up: Option<(String, f64)>,
+    /// The symptoms, categorical values, and numeric columns of the record that never showed up
+    /// during training.
+    pub unseen_features: usize,
+    /// The features that went into the scores, after missing values were filled in and rare
+    /// symptoms were pooled.
+    pub used_features: usize,
+}
+
+impl Diagnostics {
+    /// The gap between the log scores of the top disease and the runner up, the log of how many
+    /// times likelier the top disease is. Infinite when there's no runner up:
+    pub fn margin(&self) -> f64 {
+        match (&self.top, &self.runner_up) {
+            (Some((_, top)), Some((_, runner_up))) => top - runner_up,
+            (Some(_), None) => f64::INFINITY,
+            _ => f64::NAN,
+        }
+    }
+}
+
+/// Writes the diagnostics of every record, along with its ID, as csv:
+pub fn write_csv<P: AsRef<Path>>(path: P, diagnostics: &[(String, Diagnostics)]) -> Result<()> {
+    let mut writer = csv::Writer::from_writer(compress::create(path)?);
+    writer.write_record([
+        "id",
+        "top_disease",
+        "top_log_score",
+        "runner_up",
+        "runner_up_log_score",
+        "margin",
+        "unseen_features",
+        "used_features",
+    ])?;
+    // Missing diseases and scores are left blank:
+    let split = |entry: &Option<(String, f64)>| match entry {
+        Some((disease, score)) => (disease.clone(), score.to_string()),
+        None => (String::new(), String::new()),
+    };
+    for (id, diagnostics) in diagnostics {
+        let (top, top_score) = split(&diagnostics.top);
+        let (runner_up, runner_up_score) = split(&diagnostics.runner_up);
+        let margin = match diagnostics.margin() {
+            margin if margin.is_nan() => String::new(),
+            margin => margin.to_string(),
+        };
+        writer.write_record([
+            id.clone(),
+            top,
+            top_score,
+            runner_up,
+            runner_up_score,
+            margin,
+            diagnostics.unseen_features.to_string(),
+            diagnostics.used_features.to_string(),
+        ])?;
+    }
+    writer.into_inner().map_err(|e| e.into_error())?.finish()
+}
@@ -0,0 +1,179 @@
+use crate::error::{BayesError, Result};
+use encoding_rs_io::{DecodeReaderBytes, DecodeReaderBytesBuilder};
+use serde::{Deserialize, Serialize};
+use std::{
+    fmt,
+    io::{Chain, Cursor, Read},
+    str::FromStr,
+};
+
+// How much of a file the delimiter is sniffed from:
+const SNIFF_BYTES: u64 = 64 * 1024;
+const SNIFF_ROWS: usize = 50;
+// The delimiters sniffing picks from, the first winning ties:
+const CANDIDATES: [u8; 4] = [b',', b';', b'\t', b'|'];
+
+/// The text encoding of csv files. A byte order mark at the start of a file always wins over it,
+/// so UTF-8 and UTF-16 files with one are read correctly either way.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Encoding {
+    #[default]
+    Utf8,
+    /// Windows-1252, which is what Latin-1 (ISO-8859-1) files are decoded as in practice.
+    Windows1252,
+}
+
+impl FromStr for Encoding {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "utf8" | "utf-8" => Ok(Encoding::Utf8),
+            "windows-1252" | "windows1252" | "cp1252" | "latin1" | "latin-1" | "iso-8859-1" => {
+                Ok(Encoding::Windows1252)
+            }
+            _ => Err(BayesError::InvalidConfig(format!(
+                "unknown encoding: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// The field delimiter of csv files.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Delimiter {
+    Byte(u8),
+    /// Picked per file from the first rows: the candidate (comma, semicolon, tab, or pipe) that
+    /// splits them into the same number of fields.
+    Sniff,
+}
+
+impl Default for Delimiter {
+    fn default() -> Self {
+        Delimiter::Byte(b',')
+    }
+}
+
+impl FromStr for Delimiter {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "auto" | "sniff" => Ok(Delimiter::Sniff),
+            "tab" | "\\t" | "tsv" => Ok(Delimiter::Byte(b'\t')),
+            "semicolon" => Ok(Delimiter::Byte(b';')),
+            "comma" => Ok(Delimiter::Byte(b',')),
+            "pipe" => Ok(Delimiter::Byte(b'|')),
+            _ if s.len() == 1 => Ok(Delimiter::Byte(s.as_bytes()[0])),
+            _ => Err(BayesError::InvalidConfig(format!(
+                "invalid delimiter: {}",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Delimiter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Delimiter::Byte(b'\t') => write!(f, "tab"),
+            Delimiter::Byte(byte) => write!(f, "{}", *byte as char),
+            Delimiter::Sniff => write!(f, "auto"),
+        }
+    }
+}
+
+/// How csv training and prediction files are encoded and delimited.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CsvDialect {
+    pub encoding: Encoding,
+    pub delimiter: Delimiter,
+}
+
+/// A csv file decoded to UTF-8, with the rows its delimiter was sniffed from put back in front:
+pub type DecodedReader<R> = Chain<Cursor<Vec<u8>>, DecodeReaderBytes<R, Vec<u8>>>;
+
+impl CsvDialect {
+    /// Reads csv in this dialect, decoding it to UTF-8 (dropping any byte order mark) and
+    /// sniffing its delimiter if it should be:
+    pub fn reader<R: Read>(&self, reader: R) -> Result<csv::Reader<DecodedReader<R>>> {
+        let mut decoder = DecodeReaderBytesBuilder::new();
+        match self.encoding {
+            // Valid UTF-8 is passed through as is, invalid UTF-8 is left to the csv reader:
+            Encoding::Utf8 => decoder.utf8_passthru(true),
+            Encoding::Windows1252 => decoder
+                .encoding(Some(encoding_rs::WINDOWS_1252))
+                .bom_override(true),
+        };
+        let mut reader = decoder.strip_bom(true).build(reader);
+
+        let mut sample = Vec::new();
+        let delimiter = match self.delimiter {
+            Delimiter::Byte(byte) => byte,
+            Delimiter::Sniff => {
+                (&mut reader).take(SNIFF_BYTES).read_to_end(&mut sample)?;
+                let complete = (sample.len() as u64) < SNIFF_BYTES;
+                let delimiter = sniff(&sample, complete);
+                log::info!("sniffed delimiter: {}", Delimiter::Byte(delimiter));
+                delimiter
+            }
+        };
+
+        Ok(csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .from_reader(Cursor::new(sample).chain(reader)))
+    }
+}
+
+/// Picks the delimiter that splits the sampled rows (quoted fields aside) into the same number of
+/// fields, the one giving the most fields if several do. If none does, the one splitting the
+/// header into the most fields wins. The last row is left out unless the sample is the whole
+/// file, as it may be cut off:
+fn sniff(sample: &[u8], complete: bool) -> u8 {
+    let mut rows: Vec<[usize; CANDIDATES.len()]> = Vec::new();
+    let mut counts = [0; CANDIDATES.len()];
+    let mut quoted = false;
+    let mut empty = true;
+    for &byte in sample {
+        match byte {
+            b'"' => quoted = !quoted,
+            b'\n' if !quoted => {
+                if !empty {
+                    rows.push(counts);
+                }
+                counts = [0; CANDIDATES.len()];
+                empty = true;
+                if rows.len() == SNIFF_ROWS {
+                    break;
+                }
+                continue;
+            }
+            _ if quoted => {}
+            _ => {
+                if let Some(i) = CANDIDATES.iter().position(|&c| c == byte) {
+                    counts[i] += 1;
+                }
+            }
+        }
+        if byte != b'\r' {
+            empty = false;
+        }
+    }
+    if complete && !empty && rows.len() < SNIFF_ROWS {
+        rows.push(counts);
+    }
+
+    let Some(header) = rows.first() else {
+        return CANDIDATES[0];
+    };
+    let score = |i: usize| {
+        let consistent = rows.iter().all(|row| row[i] == header[i]);
+        (header[i] > 0 && consistent, header[i])
+    };
+    // Later candidates only win if they score strictly higher:
+    let best =
+        (0..CANDIDATES.len()).fold(0, |best, i| if score(i) > score(best) { i } else { best });
+    CANDIDATES[best]
+}
@@ -0,0 +1,222 @@
+use crate::bayes::NaiveBayesClassifier;
+use std::{collections::BTreeSet, fmt};
+
+/// Likelihoods that moved by more than this (in log probability, so by more than a factor of
+/// about 1.65) have changed.
+pub const LIKELIHOOD_THRESHOLD: f64 = 0.5;
+
+// How many likelihood changes are printed:
+const SHOWN: usize = 50;
+
+/// The prior of a class in both models.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PriorChange {
+    pub class: String,
+    pub old: f64,
+    pub new: f64,
+}
+
+impl PriorChange {
+    pub fn delta(&self) -> f64 {
+        self.new - self.old
+    }
+}
+
+/// The log probability of a symptom for a class in both models.
+#[derive(Clone, Debug, PartialEq)]
+pub struct LikelihoodChange {
+    pub feature: String,
+    pub class: String,
+    pub old: f64,
+    pub new: f64,
+}
+
+impl LikelihoodChange {
+    pub fn delta(&self) -> f64 {
+        self.new - self.old
+    }
+}
+
+/// What changed between two trained models, e.g. an old model and its retrained replacement.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ModelDiff {
+    /// Classes only the new model knows about, sorted.
+    pub added_classes: Vec<String>,
+    /// Classes only the old model knows about, sorted.
+    pub removed_classes: Vec<String>,
+    /// The classes of both models whose prior changed, the largest change first.
+    pub priors: Vec<PriorChange>,
+    /// Symptoms only the new model has seen, sorted.
+    pub added_features: Vec<String>,
+    /// Symptoms only the old model has seen, sorted.
+    pub removed_features: Vec<String>,
+    /// The threshold likelihood changes were picked with.
+    pub threshold: f64,
+    /// The symptoms and classes of both models whose log probability moved by more than the
+    /// threshold, the largest change first.
+    pub likelihoods: Vec<LikelihoodChange>,
+}
+
+impl ModelDiff {
+    /// Compares the priors, vocabularies, and symptom likelihoods of the models:
+    pub fn compute(old: &NaiveBayesClassifier, new: &NaiveBayesClassifier, threshold: f64) -> Self {
+        let (old_classes, new_classes): (BTreeSet<&str>, BTreeSet<&str>) = (
+            old.classes().into_iter().collect(),
+            new.classes().into_iter().collect(),
+        );
+        let classes: Vec<&str> = old_classes.intersection(&new_classes).copied().collect();
+
+        let mut priors: Vec<PriorChange> = classes
+            .iter()
+            .filter_map(|&class| {
+                let change = PriorChange {
+                    class: class.to_string(),
+                    old: old.class_log_prior(class)?.exp(),
+                    new: new.class_log_prior(class)?.exp(),
+                };
+                (change.old != change.new).then_some(change)
+            })
+            .collect();
+        priors.sort_by(|a, b| {
+            b.delta()
+                .abs()
+                .total_cmp(&a.delta().abs())
+                .then_with(|| a.class.cmp(&b.class))
+        });
+
+        let (old_features, new_features): (BTreeSet<&str>, BTreeSet<&str>) = (
+            old.features().into_iter().collect(),
+            new.features().into_iter().collect(),
+        );
+        let mut likelihoods = Vec::new();
+        for &feature in old_features.intersection(&new_features) {
+            for &class in &classes {
+                let (Some(old), Some(new)) = (
+                    old.feature_log_prob(class, feature),
+                    new.feature_log_prob(class, feature),
+                ) else {
+                    continue;
+                };
+                if (new - old).abs() > threshold {
+                    likelihoods.push(LikelihoodChange {
+                        feature: feature.to_string(),
+                        class: class.to_string(),
+                        old,
+                        new,
+                    });
+                }
+            }
+        }
+        likelihoods.sort_by(|a, b| {
+            b.delta()
+                .abs()
+                .total_cmp(&a.delta().abs())
+                .then_with(|| (&a.feature, &a.class).cmp(&(&b.feature, &b.class)))
+        });
+
+        let difference = |a: &BTreeSet<&str>, b: &BTreeSet<&str>| -> Vec<String> {
+            a.difference(b).map(|s| s.to_string()).collect()
+        };
+        ModelDiff {
+            added_classes: difference(&new_classes, &old_classes),
+            removed_classes: difference(&old_classes, &new_classes),
+            priors,
+            added_features: difference(&new_features, &old_features),
+            removed_features: difference(&old_features, &new_features),
+            threshold,
+            likelihoods,
+        }
+    }
+
+    /// Whether the models have the same classes, symptoms, and priors, and no likelihood moved by
+    /// more than the threshold:
+    pub fn is_empty(&self) -> bool {
+        self.added_classes.is_empty()
+            && self.removed_classes.is_empty()
+            && self.priors.is_empty()
+            && self.added_features.is_empty()
+            && self.removed_features.is_empty()
+            && self.likelihoods.is_empty()
+    }
+}
+
+impl fmt::Display for ModelDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(
+            f,
+            "classes:  +{} -{}",
+            self.added_classes.len(),
+            self.removed_classes.len()
+        )?;
+        writeln!(
+            f,
+            "symptoms: +{} -{}",
+            self.added_features.len(),
+            self.removed_features.len()
+        )?;
+        writeln!(f, "changed priors: {}", self.priors.len())?;
+        writeln!(
+            f,
+            "likelihoods moved by more than {}: {}",
+            self.threshold,
+            self.likelihoods.len()
+        )?;
+
+        for (heading, classes) in [
+            ("added classes", &self.added_classes),
+            ("removed classes", &self.removed_classes),
+        ] {
+            if !classes.is_empty() {
+                writeln!(f, "\n{}:", heading)?;
+                for class in classes {
+                    writeln!(f, "  {}", class)?;
+                }
+            }
+        }
+        if !self.priors.is_empty() {
+            writeln!(f, "\nchanged priors (old -> new, change):")?;
+            for prior in &self.priors {
+                writeln!(
+                    f,
+                    "  {:>8.4} -> {:<8.4}  {:+.4}  {}",
+                    prior.old,
+                    prior.new,
+                    prior.delta(),
+                    prior.class
+                )?;
+            }
+        }
+        for (heading, features) in [
+            ("added symptoms", &self.added_features),
+            ("removed symptoms", &self.removed_features),
+        ] {
+            if !features.is_empty() {
+                writeln!(f, "\n{}:", heading)?;
+                for feature in features {
+                    writeln!(f, "  {}", feature)?;
+                }
+            }
+        }
+        if !self.likelihoods.is_empty() {
+            writeln!(
+                f,
+                "\nlargest likelihood changes (old -> new log probability, change):"
+            )?;
+            for change in self.likelihoods.iter().take(SHOWN) {
+                writeln!(
+                    f,
+                    "  {:>8.4} -> {:<8.4}  {:+.4}  {} | {}",
+                    change.old,
+                    change.new,
+                    change.delta(),
+                    change.feature,
+                    change.class
+                )?;
+            }
+            if self.likelihoods.len() > SHOWN {
+                writeln!(f, "  ... ({} in all)", self.likelihoods.len())?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{bayes::NaiveBayesClassifier, features::Features};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+/// Features and classes whose population stability index is above this have shifted. By the
+/// usual rule of thumb, below 0.1 is stable and above 0.25 is a major shift.
+pub const DRIFT_THRESHOLD: f64 = 0.2;
+
+// Rates are clamped away from 0 and 1 so features that never (or always) show up on one side
+// don't have an infinite index:
+const EPSILON: f64 = 1e-4;
+
+/// How often a feature (or class) showed up during training compared to in the new records.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shift {
+    /// A symptom, a `column=value` pair of a categorical column, or a class.
+    pub name: String,
+    /// The (weighted) fraction of training records with the feature or class.
+    pub training_rate: f64,
+    /// The fraction of new records with the feature, or the average probability of the class.
+    pub rate: f64,
+    /// The population stability index of the feature being present or not (or the class's term
+    /// of the index over all classes).
+    pub psi: f64,
+}
+
+impl Shift {
+    pub fn is_shifted(&self) -> bool {
+        self.psi > DRIFT_THRESHOLD
+    }
+}
+
+impl fmt::Display for Shift {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:>8.4}  {:>8.4} -> {:<8.4}  {}{}",
+            self.psi,
+            self.training_rate,
+            self.rate,
+            self.name,
+            if self.is_shifted() { "  (shifted)" } else { "" }
+        )
+    }
+}
+
+/// How much a batch of records to predict differs from the records a classifier was trained on.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DriftReport {
+    pub num_records: usize,
+    /// Every training feature, the largest shift first.
+    pub features: Vec<Shift>,
+    /// Every class, comparing its prior to how likely it is on average for the new records, the
+    /// largest shift first.
+    pub classes: Vec<Shift>,
+    /// The population stability index of the class distribution as a whole.
+    pub class_psi: f64,
+    /// The fraction of the new records' feature occurrences that never showed up during training.
+    pub unseen_rate: f64,
+    /// Features that never showed up during training with the number of new records they're in,
+    /// the most frequent first.
+    pub unseen: Vec<(String, usize)>,
+}
+
+impl DriftReport {
+    /// Compares the records against the counts the classifier was trained on:
+    pub fn compute(classifier: &NaiveBayesClassifier, new_data: &[Features]) -> Self {
+        let stats = classifier.stats();
+        let num_training: f64 = stats.records.values().sum();
+
+        // Weighted number of training records with every feature:
+        let mut training: BTreeMap<String, f64> = BTreeMap::new();
+        for counts in stats.symptom_records.values() {
+            for (symptom, records) in counts {
+                *training.entry(symptom.to_string()).or_default() += records;
+            }
+        }
+        for (column, diseases) in &stats.categories {
+            for counts in diseases.values() {
+                for (value, records) in counts {
+                    *training.entry(format!("{}={}", column, value)).or_default() += records;
+                }
+            }
+        }
+
+        // Number of new records with every feature:
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut unseen: HashMap<String, usize> = HashMap::new();
+        let (mut occurrences, mut unseen_occurrences) = (0, 0);
+        for features in new_data {
+            // The symptoms of the training records were hashed and paired too:
+            let mut features = classifier.hash(features);
+            if let Some(feature_pairs) = classifier.feature_pairs() {
+                feature_pairs.transform(features.to_mut());
+            }
+            let symptoms = features
+                .counts
+                .iter()
+                .filter(|&(_, &count)| count > 0.0)
+                .map(|(symptom, _)| symptom.to_string());
+            let categories = features
+                .categories
+                .iter()
+                .map(|(column, value)| format!("{}={}", column, value));
+            for feature in symptoms.chain(categories) {
+                occurrences += 1;
+                if training.contains_key(&feature) {
+                    *seen.entry(feature).or_default() += 1;
+                } else {
+                    unseen_occurrences += 1;
+                    *unseen.entry(feature).or_default() += 1;
+                }
+            }
+        }
+
+        let num_records = new_data.len();
+        let rate = |count: f64, total: f64| if total > 0.0 { count / total } else { 0.0 };
+        let mut features: Vec<Shift> = training
+            .into_iter()
+            .map(|(name, records)| {
+                let training_rate = rate(records, num_training);
+                let rate = rate(
+                    seen.get(&name).copied().unwrap_or(0) as f64,
+                    num_records as f64,
+                );
+                Shift {
+                    psi: presence_psi(training_rate, rate),
+                    name,
+                    training_rate,
+                    rate,
+                }
+            })
+            .collect();
+        sort_shifts(&mut features);
+
+        // The average (calibrated) probability of every class:
+        let mut probabilities: BTreeMap<&str, f64> = BTreeMap::new();
+        for features in new_data {
+            for (class, p) in classifier.predict_top_k(features, usize::MAX) {
+                *probabilities.entry(class).or_default() += p;
+            }
+        }
+        let mut classes: Vec<Shift> = classifier
+            .classes()
+            .into_iter()
+            .map(|class| {
+                let training_rate = rate(
+                    stats.records.get(class).copied().unwrap_or(0.0),
+                    num_training,
+                );
+                let rate = rate(
+                    probabilities.get(class).copied().unwrap_or(0.0),
+                    num_records as f64,
+                );
+                Shift {
+                    name: class.to_string(),
+                    training_rate,
+                    rate,
+                    psi: psi_term(training_rate, rate),
+                }
+            })
+            .collect();
+        let class_psi = classes.iter().map(|class| class.psi).sum();
+        sort_shifts(&mut classes);
+
+        let mut unseen: Vec<(String, usize)> = unseen.into_iter().collect();
+        unseen.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+        DriftReport {
+            num_records,
+            features,
+            classes,
+            class_psi,
+            unseen_rate: rate(unseen_occurrences as f64, occurrences as f64),
+            unseen,
+        }
+    }
+
+    pub fn shifted_features(&self) -> impl Iterator<Item = &Shift> {
+        self.features.iter().filter(|feature| feature.is_shifted())
+    }
+
+    pub fn shifted_classes(&self) -> impl Iterator<Item = &Shift> {
+        self.classes.iter().filter(|class| class.is_shifted())
+    }
+
+    /// Whether any feature, class, or the class distribution as a whole has shifted:
+    pub fn is_shifted(&self) -> bool {
+        self.class_psi > DRIFT_THRESHOLD
+            || self.shifted_features().next().is_some()
+            || self.shifted_classes().next().is_some()
+    }
+}
+
+impl fmt::Display for DriftReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "records:     {}", self.num_records)?;
+        writeln!(f, "class psi:   {:.4}", self.class_psi)?;
+        writeln!(
+            f,
+            "unseen rate: {:.4} ({} unseen features)",
+            self.unseen_rate,
+            self.unseen.len()
+        )?;
+
+        writeln!(f, "\nshifted classes (psi, training rate -> rate):")?;
+        for class in self.shifted_classes() {
+            writeln!(f, "  {}", class)?;
+        }
+        writeln!(f, "\nshifted features (psi, training rate -> rate):")?;
+        for feature in self.shifted_features() {
+            writeln!(f, "  {}", feature)?;
+        }
+        writeln!(f, "\nmost frequent unseen features:")?;
+        for (feature, records) in self.unseen.iter().take(10) {
+            writeln!(f, "  {:>8}  {}", records, feature)?;
+        }
+        Ok(())
+    }
+}
+
+/// The term of a population stability index of a single bin:
+fn psi_term(expected: f64, actual: f64) -> f64 {
+    let (expected, actual) = (
+        expected.clamp(EPSILON, 1.0 - EPSILON),
+        actual.clamp(EPSILON, 1.0 - EPSILON),
+    );
+    (actual - expected) * (actual / expected).ln()
+}
+
+/// The population stability index of a feature being present or absent:
+fn presence_psi(expected: f64, actual: f64) -> f64 {
+    psi_term(expected, actual) + psi_term(1.0 - expected, 1.0 - actual)
+}
+
+fn sort_shifts(shifts: &mut [Shift]) {
+    shifts.sort_by(|a, b| b.psi.total_cmp(&a.psi).then_with(|| a.name.cmp(&b.name)));
+}
@@ -0,0 +1,237 @@
+use crate::{
+    bayes::{BatchPrediction, NaiveBayesClassifier},
+    config::Config,
+    error::{BayesError, Result},
+    features::Features,
+    format::{self, Format, ParseReport, TrainingRecord},
+    rng::Rng,
+    symbols::Symbol,
+};
+use std::{collections::BTreeMap, path::Path, str::FromStr};
+
+/// How the predictions of the models of an ensemble are combined.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Aggregation {
+    /// The posteriors of the models are averaged in log space (a geometric mean) and normalized.
+    #[default]
+    MeanLogPosterior,
+    /// Every model votes for its top disease, the probability of a disease is its share of votes.
+    MajorityVote,
+}
+
+impl FromStr for Aggregation {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "mean" => Ok(Aggregation::MeanLogPosterior),
+            "vote" => Ok(Aggregation::MajorityVote),
+            _ => Err(BayesError::InvalidConfig(format!(
+                "unknown aggregation: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Options controlling how an ensemble is bagged.
+#[derive(Clone, Debug)]
+pub struct BaggingConfig {
+    /// The number of models, each trained on its own bootstrap sample.
+    pub num_models: usize,
+    pub aggregation: Aggregation,
+    /// Seeds the bootstrap samples (unless the config has a seed), the same seed always gives the
+    /// same ensemble.
+    pub seed: u64,
+}
+
+impl Default for BaggingConfig {
+    fn default() -> Self {
+        BaggingConfig {
+            num_models: 10,
+            aggregation: Aggregation::MeanLogPosterior,
+            seed: 0,
+        }
+    }
+}
+
+/// Naive bayes models trained on bootstrap samples of the training records whose predictions are
+/// combined, which smooths over noisy records.
+pub struct EnsembleClassifier {
+    models: Vec<NaiveBayesClassifier>,
+    aggregation: Aggregation,
+    training_report: ParseReport,
+}
+
+impl EnsembleClassifier {
+    pub fn with_config<P: AsRef<Path>>(
+        path: P,
+        config: Config,
+        bagging: &BaggingConfig,
+    ) -> Result<Self> {
+        let (records, report) = format::read_training_records(path, &config)?;
+        let mut ensemble = Self::fit_weighted(records, config, bagging)?;
+        ensemble.training_report = report;
+        Ok(ensemble)
+    }
+
+    /// Trains every model on (disease, features, weight) records sampled with replacement. The
+    /// records of each disease are sampled separately, so every model sees every disease as often
+    /// as the full training set does:
+    pub fn fit_weighted<I, S>(records: I, config: Config, bagging: &BaggingConfig) -> Result<Self>
+    where
+        I: IntoIterator<Item = (S, Features, f64)>,
+        S: Into<String>,
+    {
+        if bagging.num_models == 0 {
+            return Err(BayesError::InvalidConfig(
+                "an ensemble needs at least one model.".to_string(),
+            ));
+        }
+
+        let mut diseases: BTreeMap<String, Vec<(Features, f64)>> = BTreeMap::new();
+        for (disease, features, weight) in records {
+            diseases
+                .entry(disease.into())
+                .or_default()
+                .push((features, weight));
+        }
+
+        let mut rng = Rng::seeded(config.seed.unwrap_or(bagging.seed));
+        let mut models = Vec::with_capacity(bagging.num_models);
+        for _ in 0..bagging.num_models {
+            let mut sample: Vec<TrainingRecord> = Vec::new();
+            for (disease, records) in &diseases {
+                for _ in 0..records.len() {
+                    let (features, weight) = &records[rng.below(records.len())];
+                    sample.push((disease.clone(), features.clone(), *weight));
+                }
+            }
+            models.push(NaiveBayesClassifier::fit_weighted(sample, config.clone())?);
+        }
+
+        Ok(EnsembleClassifier {
+            models,
+            aggregation: bagging.aggregation,
+            training_report: ParseReport::default(),
+        })
+    }
+
+    pub fn models(&self) -> &[NaiveBayesClassifier] {
+        &self.models
+    }
+
+    /// The malformed rows skipped while reading the training file in lenient mode:
+    pub fn training_report(&self) -> &ParseReport {
+        &self.training_report
+    }
+
+    pub fn config(&self) -> &Config {
+        self.models[0].config()
+    }
+
+    /// Returns the `k` most likely diseases for the symptoms along with their combined
+    /// probabilities, most likely first. Ties go to the alphabetically first disease:
+    pub fn predict_top_k(&self, psymptoms: &Features, k: usize) -> Vec<(&str, f64)> {
+        let mut scores: BTreeMap<&str, f64> = BTreeMap::new();
+        for model in &self.models {
+            let probabilities = model.predict_top_k(psymptoms, usize::MAX);
+            match self.aggregation {
+                Aggregation::MeanLogPosterior => {
+                    for (disease, p) in probabilities {
+                        *scores.entry(disease).or_default() += p.ln();
+                    }
+                }
+                Aggregation::MajorityVote => {
+                    for &(disease, _) in &probabilities {
+                        scores.entry(disease).or_default();
+                    }
+                    if let Some(&(disease, _)) = probabilities.first() {
+                        *scores.entry(disease).or_default() += 1.0;
+                    }
+                }
+            }
+        }
+
+        let mut scores: Vec<(&str, f64)> = scores.into_iter().collect();
+        match self.aggregation {
+            Aggregation::MeanLogPosterior => {
+                // Normalize in log space so that small products don't underflow:
+                let n = self.models.len() as f64;
+                let max_score = scores
+                    .iter()
+                    .fold(f64::NEG_INFINITY, |acc, &(_, score)| acc.max(score / n));
+                let total: f64 = scores
+                    .iter()
+                    .map(|(_, score)| (score / n - max_score).exp())
+                    .sum();
+                for (_, score) in &mut scores {
+                    *score = (*score / n - max_score).exp() / total;
+                }
+            }
+            Aggregation::MajorityVote => {
+                for (_, votes) in &mut scores {
+                    *votes /= self.models.len() as f64;
+                }
+            }
+        }
+
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        scores.truncate(k);
+        scores
+    }
+
+    /// Predicts every record, returning the predictions in the same order. Each gets the disease
+    /// with the lowest expected cost given a cost matrix, and records falling below the
+    /// configured confidence or margin get the unknown label:
+    pub fn predict_batch(&self, records: &[Features]) -> Vec<BatchPrediction> {
+        let config = self.config();
+        records
+            .iter()
+            .enumerate()
+            .map(|(i, symptoms)| {
+                let top = match &config.costs {
+                    Some(_) => self.predict_top_k(symptoms, usize::MAX),
+                    None => self.predict_top_k(symptoms, 2),
+                };
+                let probability = top.first().map_or(0.0, |&(_, p)| p);
+                let runner_up = top.get(1).map_or(0.0, |&(_, p)| p);
+                // The disease with the lowest expected cost rather than the most likely one:
+                let best = match &config.costs {
+                    Some(costs) => costs.decide(&top),
+                    None => top.first().copied(),
+                };
+                let (disease, probability) = if probability < config.min_confidence
+                    || probability - runner_up < config.min_margin
+                {
+                    (config.unknown_label.as_str(), probability)
+                } else {
+                    best.unwrap_or(("", 0.0))
+                };
+
+                let mut prediction = BatchPrediction {
+                    id: (i + 1).to_string(),
+                    id_from_input: false,
+                    disease: Symbol::new(disease),
+                    score: probability,
+                    probability: None,
+                    alternatives: Vec::new(),
+                };
+                prediction.describe(&config.output, || self.predict_top_k(symptoms, usize::MAX));
+                prediction
+            })
+            .collect()
+    }
+
+    /// Predicts the records of a test file like `NaiveBayesClassifier::predict`:
+    pub fn predict<P: AsRef<Path>>(
+        &self,
+        inpath: P,
+        outpath: P,
+        format: Option<Format>,
+    ) -> Result<ParseReport> {
+        format::predict_file(inpath, outpath, format, self.config(), |records| {
+            self.predict_batch(records)
+        })
+    }
+}
@@ -0,0 +1,77 @@
+use std::io;
+use thiserror::Error;
+
+/// Everything that can go wrong while training, predicting, or reading and writing files.
+#[derive(Debug, Error)]
+pub enum BayesError {
+    /// Reading or writing a file (or stream) failed.
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    /// A csv file couldn't be read, e.g. its rows have different lengths.
+    #[error(transparent)]
+    Csv(#[from] csv::Error),
+    /// A value in an input file couldn't be parsed. The row is the (1 based) line of the file.
+    #[error("malformed row {row}{}: {message}", column.as_ref().map(|c| format!(", column {}", c)).unwrap_or_default())]
+    Parse {
+        row: u64,
+        column: Option<String>,
+        message: String,
+    },
+    /// An Arrow record batch couldn't be read.
+    #[cfg(feature = "arrow")]
+    #[error(transparent)]
+    Arrow(#[from] arrow::error::ArrowError),
+    /// A Parquet file couldn't be read.
+    #[cfg(feature = "arrow")]
+    #[error(transparent)]
+    Parquet(#[from] parquet::errors::ParquetError),
+    /// linfa rejected a dataset.
+    #[cfg(feature = "linfa")]
+    #[error(transparent)]
+    Linfa(#[from] linfa::Error),
+    /// A workbook (or one of its sheets) couldn't be read.
+    #[cfg(feature = "xlsx")]
+    #[error(transparent)]
+    Spreadsheet(#[from] calamine::Error),
+    /// A line of a jsonl file (or a request) isn't valid json.
+    #[error(transparent)]
+    Json(#[from] serde_json::Error),
+    /// A column the config refers to isn't in a file's header.
+    #[error("missing column: {0}")]
+    MissingColumn(String),
+    /// There's nothing to train on: no records, or only records with zero weight.
+    #[error("empty training set")]
+    EmptyTrainingSet,
+    /// None of the training records have any (kept) features to learn from.
+    #[error("training records have no features")]
+    NoFeatures,
+    /// There's nothing to calibrate on.
+    #[error("empty holdout set")]
+    EmptyHoldoutSet,
+    /// An option (or one of its values) is invalid.
+    #[error("invalid config: {0}")]
+    InvalidConfig(String),
+    /// Training would need more memory than the configured limit.
+    #[error("training needs more than the memory limit of {0} bytes")]
+    MemoryLimit(usize),
+    /// The model can't do what's asked of it, e.g. export a Bernoulli model to PMML.
+    #[error("unsupported: {0}")]
+    Unsupported(String),
+}
+
+pub type Result<T, E = BayesError> = std::result::Result<T, E>;
+
+impl BayesError {
+    /// A parse error of the record at the given csv position:
+    pub(crate) fn parse<S: Into<String>>(
+        position: Option<&csv::Position>,
+        column: Option<&str>,
+        message: S,
+    ) -> Self {
+        BayesError::Parse {
+            row: position.map_or(0, |position| position.line()),
+            column: column.map(|column| column.trim().to_string()),
+            message: message.into(),
+        }
+    }
+}
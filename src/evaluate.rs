@@ -0,0 +1,287 @@
+use crate::{bayes::NaiveBayesClassifier, compress, error::Result, features::Features};
+use std::{fmt, path::Path};
+
+/// One bin of a reliability diagram: the predictions whose confidence fell in [lower, upper).
+#[derive(Clone, Debug)]
+pub struct ReliabilityBin {
+    pub lower: f64,
+    pub upper: f64,
+    pub count: usize,
+    pub mean_confidence: f64,
+    pub accuracy: f64,
+}
+
+/// A point of a class's ROC and precision-recall curves: the rates when every record whose
+/// probability of the class is at least the threshold is predicted as it.
+#[derive(Clone, Debug)]
+pub struct CurvePoint {
+    pub threshold: f64,
+    pub false_positive_rate: f64,
+    /// Also the recall.
+    pub true_positive_rate: f64,
+    pub precision: f64,
+}
+
+/// The one-vs-rest ROC and precision-recall curves of a class.
+#[derive(Clone, Debug)]
+pub struct ClassCurves {
+    pub class: String,
+    /// The number of records of the class.
+    pub positives: usize,
+    /// The area under the ROC curve, which is undefined unless there are records both of and not
+    /// of the class.
+    pub roc_auc: Option<f64>,
+    /// The area under the precision-recall curve (the average precision), which is undefined
+    /// unless there are records of the class.
+    pub pr_auc: Option<f64>,
+    /// From the highest threshold (where nothing is predicted as the class) down.
+    pub points: Vec<CurvePoint>,
+}
+
+/// How well the classifier did on a labeled data set.
+#[derive(Clone, Debug)]
+pub struct Evaluation {
+    pub num_records: usize,
+    pub accuracy: f64,
+    pub reliability: Vec<ReliabilityBin>,
+    /// Expected calibration error: the count weighted gap between confidence and accuracy.
+    pub calibration_error: f64,
+    /// The curves of every class the classifier knows, in order.
+    pub classes: Vec<ClassCurves>,
+}
+
+/// Predicts every (disease, features) record and compares against its disease. The confidence of
+/// each prediction (probability of the top disease) is bucketed into `num_bins` equal width bins
+/// to make a reliability diagram:
+pub fn evaluate(
+    classifier: &NaiveBayesClassifier,
+    records: &[(String, Features)],
+    num_bins: usize,
+) -> Evaluation {
+    let num_bins = num_bins.max(1);
+
+    // (count, total confidence, total correct) of each bin:
+    let mut bins = vec![(0, 0.0, 0.0); num_bins];
+    let mut num_correct = 0;
+    // (probability of the class, whether it's the record's disease) for every record of every
+    // class:
+    let classes = classifier.classes();
+    let mut scores: Vec<Vec<(f64, bool)>> = vec![Vec::with_capacity(records.len()); classes.len()];
+    for (disease, features) in records {
+        let probabilities = classifier.predict_top_k(features, usize::MAX);
+        for &(class, p) in &probabilities {
+            if let Ok(i) = classes.binary_search(&class) {
+                scores[i].push((p, class == disease));
+            }
+        }
+
+        let (predicted, confidence) = match probabilities.first() {
+            Some(&(predicted, confidence)) => (predicted, confidence),
+            None => continue,
+        };
+        let correct = predicted == disease;
+        if correct {
+            num_correct += 1;
+        }
+
+        let bin = ((confidence * num_bins as f64) as usize).min(num_bins - 1);
+        bins[bin].0 += 1;
+        bins[bin].1 += confidence;
+        bins[bin].2 += if correct { 1.0 } else { 0.0 };
+    }
+
+    let num_records = records.len();
+    let reliability: Vec<ReliabilityBin> = bins
+        .iter()
+        .enumerate()
+        .map(|(i, &(count, confidence, correct))| ReliabilityBin {
+            lower: i as f64 / num_bins as f64,
+            upper: (i + 1) as f64 / num_bins as f64,
+            count,
+            mean_confidence: if count > 0 {
+                confidence / count as f64
+            } else {
+                0.0
+            },
+            accuracy: if count > 0 {
+                correct / count as f64
+            } else {
+                0.0
+            },
+        })
+        .collect();
+
+    let calibration_error = if num_records > 0 {
+        reliability
+            .iter()
+            .map(|bin| bin.count as f64 * (bin.mean_confidence - bin.accuracy).abs())
+            .sum::<f64>()
+            / num_records as f64
+    } else {
+        0.0
+    };
+
+    Evaluation {
+        num_records,
+        accuracy: if num_records > 0 {
+            num_correct as f64 / num_records as f64
+        } else {
+            0.0
+        },
+        reliability,
+        calibration_error,
+        classes: classes
+            .into_iter()
+            .zip(scores)
+            .map(|(class, scores)| curves(class, scores))
+            .collect(),
+    }
+}
+
+/// Sweeps the threshold down through the (class probability, is the class) scores of the records,
+/// adding up the area under the ROC curve with the trapezoidal rule and the average precision as
+/// the precision weighted increase in recall. Records with the same score are passed together:
+fn curves(class: &str, mut scores: Vec<(f64, bool)>) -> ClassCurves {
+    scores.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let positives = scores.iter().filter(|&&(_, positive)| positive).count();
+    let negatives = scores.len() - positives;
+    let rate = |count: usize, total: usize| {
+        if total > 0 {
+            count as f64 / total as f64
+        } else {
+            0.0
+        }
+    };
+
+    let mut points = vec![CurvePoint {
+        threshold: f64::INFINITY,
+        false_positive_rate: 0.0,
+        true_positive_rate: 0.0,
+        precision: 1.0,
+    }];
+    let (mut true_positives, mut false_positives) = (0, 0);
+    let (mut roc_auc, mut pr_auc) = (0.0, 0.0);
+    for group in scores.chunk_by(|a, b| a.0 == b.0) {
+        for &(_, positive) in group {
+            if positive {
+                true_positives += 1;
+            } else {
+                false_positives += 1;
+            }
+        }
+
+        let point = CurvePoint {
+            threshold: group[0].0,
+            false_positive_rate: rate(false_positives, negatives),
+            true_positive_rate: rate(true_positives, positives),
+            precision: rate(true_positives, true_positives + false_positives),
+        };
+        let last = &points[points.len() - 1];
+        roc_auc += (point.false_positive_rate - last.false_positive_rate)
+            * (point.true_positive_rate + last.true_positive_rate)
+            / 2.0;
+        pr_auc += (point.true_positive_rate - last.true_positive_rate) * point.precision;
+        points.push(point);
+    }
+
+    ClassCurves {
+        class: class.to_string(),
+        positives,
+        roc_auc: (positives > 0 && negatives > 0).then_some(roc_auc),
+        pr_auc: (positives > 0).then_some(pr_auc),
+        points,
+    }
+}
+
+impl Evaluation {
+    /// The unweighted mean ROC AUC of the classes it is defined for, so rare classes count as
+    /// much as common ones:
+    pub fn macro_roc_auc(&self) -> Option<f64> {
+        mean(self.classes.iter().filter_map(|class| class.roc_auc))
+    }
+
+    /// The unweighted mean PR AUC of the classes it is defined for:
+    pub fn macro_pr_auc(&self) -> Option<f64> {
+        mean(self.classes.iter().filter_map(|class| class.pr_auc))
+    }
+
+    /// Writes the points of every class's curves as csv, for plotting:
+    pub fn write_curves<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = csv::Writer::from_writer(compress::create(path)?);
+        writer.write_record([
+            "class",
+            "threshold",
+            "false_positive_rate",
+            "true_positive_rate",
+            "precision",
+        ])?;
+        for class in &self.classes {
+            for point in &class.points {
+                writer.write_record([
+                    class.class.clone(),
+                    point.threshold.to_string(),
+                    point.false_positive_rate.to_string(),
+                    point.true_positive_rate.to_string(),
+                    point.precision.to_string(),
+                ])?;
+            }
+        }
+        writer.into_inner().map_err(|e| e.into_error())?.finish()
+    }
+}
+
+fn mean<I: Iterator<Item = f64>>(values: I) -> Option<f64> {
+    let (count, total) = values.fold((0, 0.0), |(count, total), x| (count + 1, total + x));
+    (count > 0).then(|| total / count as f64)
+}
+
+impl fmt::Display for Evaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "records:  {}", self.num_records)?;
+        writeln!(f, "accuracy: {:.4}", self.accuracy)?;
+        writeln!(f, "calibration error: {:.4}", self.calibration_error)?;
+        writeln!(f)?;
+        writeln!(f, "reliability diagram:")?;
+        writeln!(f, "  confidence     count  mean conf  accuracy")?;
+        for bin in &self.reliability {
+            writeln!(
+                f,
+                "  [{:.2}, {:.2})  {:>7}  {:>9.4}  {:>8.4}  {}",
+                bin.lower,
+                bin.upper,
+                bin.count,
+                bin.mean_confidence,
+                bin.accuracy,
+                "#".repeat((bin.accuracy * 20.0).round() as usize)
+            )?;
+        }
+
+        // Undefined areas are shown as a dash:
+        let area = |auc: Option<f64>| auc.map_or("-".to_string(), |auc| format!("{:.4}", auc));
+        writeln!(f)?;
+        writeln!(f, "one-vs-rest curves:")?;
+        writeln!(
+            f,
+            "  {:>9}  {:>7}  {:>7}  class",
+            "positives", "roc auc", "pr auc"
+        )?;
+        for class in &self.classes {
+            writeln!(
+                f,
+                "  {:>9}  {:>7}  {:>7}  {}",
+                class.positives,
+                area(class.roc_auc),
+                area(class.pr_auc),
+                class.class
+            )?;
+        }
+        writeln!(
+            f,
+            "  {:>9}  {:>7}  {:>7}  (macro average)",
+            "",
+            area(self.macro_roc_auc()),
+            area(self.macro_pr_auc())
+        )?;
+        Ok(())
+    }
+}
@@ -0,0 +1,66 @@
+use std::fmt;
+
+/// How much a single feature (or the prior) adds to the log score of the predicted disease and
+/// of the runner up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Contribution {
+    /// A symptom, a `column=value` pair of a categorical or numeric column, or `prior`.
+    pub feature: String,
+    pub winner: f64,
+    pub runner_up: f64,
+}
+
+impl Contribution {
+    /// How much the feature favors the predicted disease over the runner up:
+    pub fn difference(&self) -> f64 {
+        self.winner - self.runner_up
+    }
+}
+
+/// Why a record was predicted as a disease rather than the runner up.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Explanation {
+    pub winner: String,
+    /// The second most likely disease, if there is more than one.
+    pub runner_up: Option<String>,
+    /// The log priors of the two diseases.
+    pub prior: Contribution,
+    /// The log likelihood contributions of the record's features, the most decisive first.
+    pub features: Vec<Contribution>,
+}
+
+impl Explanation {
+    /// The log of how many times more likely the winner is than the runner up:
+    pub fn log_odds(&self) -> f64 {
+        self.prior.difference() + self.features.iter().map(|c| c.difference()).sum::<f64>()
+    }
+}
+
+impl fmt::Display for Explanation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let runner_up = self.runner_up.as_deref().unwrap_or("-");
+        writeln!(
+            f,
+            "{} over {} (log odds {:.4}):",
+            self.winner,
+            runner_up,
+            self.log_odds()
+        )?;
+        writeln!(
+            f,
+            "  {:>10}  {:>10}  {:>10}  feature",
+            "winner", "runner up", "difference"
+        )?;
+        for contribution in std::iter::once(&self.prior).chain(&self.features) {
+            writeln!(
+                f,
+                "  {:>10.4}  {:>10.4}  {:>10.4}  {}",
+                contribution.winner,
+                contribution.runner_up,
+                contribution.difference(),
+                contribution.feature
+            )?;
+        }
+        Ok(())
+    }
+}
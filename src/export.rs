@@ -0,0 +1,147 @@
+use crate::{
+    bayes::NaiveBayesClassifier,
+    config::ModelKind,
+    error::{BayesError, Result},
+};
+use std::{
+    fs::File,
+    io::{BufWriter, Write},
+    path::Path,
+};
+
+/// Name of the target field in exported models.
+const TARGET_FIELD: &str = "Disease";
+
+/// Writes the classifier as a PMML `NaiveBayesModel` to a file:
+pub fn export_pmml<P: AsRef<Path>>(classifier: &NaiveBayesClassifier, path: P) -> Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    write_pmml(classifier, &mut writer)?;
+    writer.flush()?;
+    Ok(())
+}
+
+/// Writes the classifier as a PMML `NaiveBayesModel`.
+///
+/// Every symptom becomes a categorical field whose only value is "1" (present). An absent symptom
+/// should be passed as a missing value, which PMML skips, just like the classifier does. PMML
+/// works with counts rather than probabilities, so the pi values are written as the class counts
+/// and pi * beta as the count of a symptom for a class, which gives back the same probabilities.
+pub fn write_pmml<W: Write>(classifier: &NaiveBayesClassifier, mut w: W) -> Result<()> {
+    if classifier.config().model_kind != ModelKind::Multinomial {
+        return Err(BayesError::Unsupported(
+            "only multinomial models can be exported to PMML.".to_string(),
+        ));
+    }
+    // A PMML consumer wouldn't know to fill in the fields of the pairs:
+    if classifier.feature_pairs().is_some() {
+        return Err(BayesError::Unsupported(
+            "models with symptom pairs can't be exported to PMML.".to_string(),
+        ));
+    }
+
+    let classes = classifier.classes();
+    let features = classifier.features();
+
+    writeln!(w, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        w,
+        r#"<PMML xmlns="http://www.dmg.org/PMML-4_4" version="4.4">"#
+    )?;
+    writeln!(
+        w,
+        r#"  <Header description="Naive Bayes disease classifier"/>"#
+    )?;
+
+    // Describe all of the fields:
+    writeln!(
+        w,
+        r#"  <DataDictionary numberOfFields="{}">"#,
+        features.len() + 1
+    )?;
+    writeln!(
+        w,
+        r#"    <DataField name="{}" optype="categorical" dataType="string">"#,
+        TARGET_FIELD
+    )?;
+    for class in &classes {
+        writeln!(w, r#"      <Value value="{}"/>"#, escape(class))?;
+    }
+    writeln!(w, "    </DataField>")?;
+    for feature in &features {
+        writeln!(
+            w,
+            r#"    <DataField name="{}" optype="categorical" dataType="string">"#,
+            escape(feature)
+        )?;
+        writeln!(w, r#"      <Value value="1"/>"#)?;
+        writeln!(w, "    </DataField>")?;
+    }
+    writeln!(w, "  </DataDictionary>")?;
+
+    // The model itself, a zero count should never come up but PMML requires a threshold:
+    writeln!(
+        w,
+        r#"  <NaiveBayesModel modelName="NaiveBayesClassifier" functionName="classification" threshold="1e-12">"#
+    )?;
+    writeln!(w, "    <MiningSchema>")?;
+    writeln!(
+        w,
+        r#"      <MiningField name="{}" usageType="target"/>"#,
+        TARGET_FIELD
+    )?;
+    for feature in &features {
+        writeln!(
+            w,
+            r#"      <MiningField name="{}" missingValueTreatment="asIs"/>"#,
+            escape(feature)
+        )?;
+    }
+    writeln!(w, "    </MiningSchema>")?;
+
+    writeln!(w, "    <BayesInputs>")?;
+    for feature in &features {
+        writeln!(w, r#"      <BayesInput fieldName="{}">"#, escape(feature))?;
+        writeln!(w, r#"        <PairCounts value="1">"#)?;
+        writeln!(w, "          <TargetValueCounts>")?;
+        for class in &classes {
+            let log_count = classifier.class_log_prior(class).unwrap()
+                + classifier.feature_log_prob(class, feature).unwrap();
+            writeln!(
+                w,
+                r#"            <TargetValueCount value="{}" count="{}"/>"#,
+                escape(class),
+                log_count.exp()
+            )?;
+        }
+        writeln!(w, "          </TargetValueCounts>")?;
+        writeln!(w, "        </PairCounts>")?;
+        writeln!(w, "      </BayesInput>")?;
+    }
+    writeln!(w, "    </BayesInputs>")?;
+
+    writeln!(w, r#"    <BayesOutput fieldName="{}">"#, TARGET_FIELD)?;
+    writeln!(w, "      <TargetValueCounts>")?;
+    for class in &classes {
+        writeln!(
+            w,
+            r#"        <TargetValueCount value="{}" count="{}"/>"#,
+            escape(class),
+            classifier.class_log_prior(class).unwrap().exp()
+        )?;
+    }
+    writeln!(w, "      </TargetValueCounts>")?;
+    writeln!(w, "    </BayesOutput>")?;
+
+    writeln!(w, "  </NaiveBayesModel>")?;
+    writeln!(w, "</PMML>")?;
+
+    Ok(())
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
@@ -0,0 +1,77 @@
+use crate::{config::Config, schema::ColumnType, stats::ENTRY_BYTES, symbols::Symbol};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// The features of a single record. They are kept sorted so that sums over them (and so scores)
+/// come out exactly the same every run. Every name (and category value) is an interned `Symbol`,
+/// shared with every other record and model that has it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Features {
+    /// Symptoms (or tokens) mapped to how many times they occur. A symptom is either present (1)
+    /// or missing from the map.
+    pub counts: BTreeMap<Symbol, f64>,
+    /// The value of every categorical column the record has a value for.
+    pub categories: BTreeMap<Symbol, Symbol>,
+    /// The value of every numeric column the record has a (valid) value for.
+    pub numbers: BTreeMap<Symbol, f64>,
+}
+
+impl Features {
+    /// Builds the features of a record from its (non label) fields as (column header, value)
+    /// pairs. The schema decides how each column is interpreted, and symptom columns are
+    /// tokenized as text in text mode:
+    pub fn from_fields<'a, I>(fields: I, config: &Config) -> Features
+    where
+        I: IntoIterator<Item = (&'a str, &'a str)>,
+    {
+        let mut features = Features::default();
+        for (column, value) in fields {
+            match config.schema.column_type(column) {
+                ColumnType::Categorical => {
+                    let value = value.trim();
+                    if value.is_empty() {
+                        continue;
+                    }
+                    features
+                        .categories
+                        .insert(Symbol::new(column.trim()), Symbol::new(value));
+                }
+                ColumnType::Numeric => {
+                    if let Ok(x) = value.trim().parse::<f64>() {
+                        if x.is_finite() {
+                            features.numbers.insert(Symbol::new(column.trim()), x);
+                        }
+                    }
+                }
+                ColumnType::Symptom => match &config.tokenizer {
+                    Some(tokenizer) => {
+                        for token in tokenizer.tokenize(value) {
+                            let token = match &config.normalizer {
+                                Some(normalizer) => normalizer.normalize(&token),
+                                None => token,
+                            };
+                            *features.counts.entry(Symbol::from(token)).or_default() += 1.0;
+                        }
+                    }
+                    None => {
+                        let symptom = match &config.normalizer {
+                            Some(normalizer) => Symbol::from(normalizer.normalize(value)),
+                            None => Symbol::new(value.trim()),
+                        };
+                        if symptom.is_empty() {
+                            continue;
+                        }
+                        features.counts.insert(symptom, 1.0);
+                    }
+                },
+            }
+        }
+        features
+    }
+
+    /// Roughly how many bytes the record takes up in memory. The names are shared, so they
+    /// aren't counted:
+    pub fn estimated_bytes(&self) -> usize {
+        ENTRY_BYTES * (self.counts.len() + self.categories.len() + self.numbers.len())
+    }
+}
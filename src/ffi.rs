@@ -0,0 +1,158 @@
+//! A C interface to the classifier, see `include/naive_bayes.h` (generated by cbindgen when
+//! building with the `ffi` feature).
+//!
+//! Functions that fail return null, and `nb_last_error` then describes what went wrong.
+
+use crate::{bayes::NaiveBayesClassifier, error::BayesError, format::JsonRecord};
+use serde::Serialize;
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CStr, CString},
+    path::Path,
+    ptr,
+};
+
+/// A trained classifier, only ever handled through a pointer.
+pub struct NbClassifier(NaiveBayesClassifier);
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+#[derive(Serialize)]
+struct PredictResponse<'a> {
+    predictions: Vec<ClassProbability<'a>>,
+}
+
+#[derive(Serialize)]
+struct ClassProbability<'a> {
+    disease: &'a str,
+    probability: f64,
+}
+
+/// Trains a classifier on a training csv file. Returns null on failure.
+///
+/// # Safety
+///
+/// `path` must be a valid, nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn nb_train(path: *const c_char) -> *mut NbClassifier {
+    let classifier = to_str(path).and_then(|path| NaiveBayesClassifier::new(Path::new(path)));
+    into_raw(classifier.map(NbClassifier))
+}
+
+/// Loads a classifier saved with `NaiveBayesClassifier::save`. Returns null on failure.
+///
+/// # Safety
+///
+/// `path` must be a valid, nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn nb_load(path: *const c_char) -> *mut NbClassifier {
+    let classifier = to_str(path).and_then(|path| NaiveBayesClassifier::load(Path::new(path)));
+    into_raw(classifier.map(NbClassifier))
+}
+
+/// Predicts the `k` most likely diseases of a record given as json like the records of jsonl
+/// files, e.g. `{"symptoms": ["fever", "cough"]}`. Returns json like
+/// `{"predictions": [{"disease": "Flu", "probability": 0.9}]}` that must be freed with
+/// `nb_free_string`, or null on failure.
+///
+/// # Safety
+///
+/// `classifier` must come from `nb_train` or `nb_load` and not have been freed, and `record` must
+/// be a valid, nul terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn nb_predict_json(
+    classifier: *const NbClassifier,
+    record: *const c_char,
+    k: usize,
+) -> *mut c_char {
+    let Some(NbClassifier(classifier)) = classifier.as_ref() else {
+        set_last_error("classifier is null.".to_string());
+        return ptr::null_mut();
+    };
+
+    let response = to_str(record).and_then(|record| {
+        let record: JsonRecord = serde_json::from_str(record)?;
+        let features = record.into_features(classifier.config());
+        let predictions = classifier
+            .predict_top_k(&features, k)
+            .into_iter()
+            .map(|(disease, probability)| ClassProbability {
+                disease,
+                probability,
+            })
+            .collect();
+        Ok(serde_json::to_string(&PredictResponse { predictions })?)
+    });
+
+    // Json strings escape nul bytes, so the response never has any:
+    match response {
+        Ok(response) => CString::new(response).unwrap_or_default().into_raw(),
+        Err(e) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Frees a classifier, null is ignored.
+///
+/// # Safety
+///
+/// `classifier` must come from `nb_train` or `nb_load` and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn nb_free(classifier: *mut NbClassifier) {
+    if !classifier.is_null() {
+        drop(Box::from_raw(classifier));
+    }
+}
+
+/// Frees a string returned by this library, null is ignored.
+///
+/// # Safety
+///
+/// `s` must come from `nb_predict_json` and not have been freed already.
+#[no_mangle]
+pub unsafe extern "C" fn nb_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+/// Describes the last failure on this thread, or null if nothing failed yet. The string is owned
+/// by the library and only valid until the next call on this thread.
+#[no_mangle]
+pub extern "C" fn nb_last_error() -> *const c_char {
+    LAST_ERROR.with(|error| {
+        error
+            .borrow()
+            .as_ref()
+            .map_or(ptr::null(), |error| error.as_ptr())
+    })
+}
+
+fn set_last_error(message: String) {
+    // Messages don't have nul bytes in them, but if they do the error is still reported:
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|error| *error.borrow_mut() = Some(message));
+}
+
+unsafe fn to_str<'a>(s: *const c_char) -> Result<&'a str, BayesError> {
+    if s.is_null() {
+        return Err(BayesError::InvalidConfig("string is null.".to_string()));
+    }
+    CStr::from_ptr(s)
+        .to_str()
+        .map_err(|e| BayesError::InvalidConfig(e.to_string()))
+}
+
+fn into_raw(classifier: Result<NbClassifier, BayesError>) -> *mut NbClassifier {
+    match classifier {
+        Ok(classifier) => Box::into_raw(Box::new(classifier)),
+        Err(e) => {
+            set_last_error(e.to_string());
+            ptr::null_mut()
+        }
+    }
+}
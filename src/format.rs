@@ -0,0 +1,669 @@
+use crate::{
+    bayes::BatchPrediction,
+    compress::{self, Compression},
+    config::{Config, IdColumn, ParseMode},
+    error::{BayesError, Result},
+    features::Features,
+    progress::{Stage, Tracker},
+    schema::ColumnType,
+    spreadsheet,
+    symbols::Symbol,
+};
+use csv::StringRecord;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt,
+    io::{BufRead, BufReader, Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+/// The file formats records and predictions can be read from or written to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+    Csv,
+    Jsonl,
+}
+
+impl Format {
+    /// Picks a format based on the extension of the path, ignoring any compression extension
+    /// (defaulting to csv):
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let path = match Compression::from_path(path) {
+            Compression::None => path,
+            _ => Path::new(path.file_stem().unwrap_or_default()),
+        };
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("jsonl") | Some("ndjson") | Some("json") => Format::Jsonl,
+            _ => Format::Csv,
+        }
+    }
+}
+
+impl FromStr for Format {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "csv" => Ok(Format::Csv),
+            "jsonl" | "ndjson" | "json" => Ok(Format::Jsonl),
+            _ => Err(BayesError::InvalidConfig(format!("unknown format: {}", s))),
+        }
+    }
+}
+
+/// A record in a jsonl file (or http request).
+#[derive(Deserialize)]
+pub(crate) struct JsonRecord {
+    #[serde(default)]
+    symptoms: Vec<String>,
+    /// Raw text, only used in text mode.
+    #[serde(default)]
+    text: Option<String>,
+    /// The values of categorical columns.
+    #[serde(default)]
+    categories: BTreeMap<String, String>,
+    /// The values of numeric columns.
+    #[serde(default)]
+    numbers: BTreeMap<String, f64>,
+    /// Any other fields, e.g. the record's ID.
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_json::Value>,
+}
+
+#[derive(Serialize)]
+struct JsonPrediction<'a> {
+    id: serde_json::Value,
+    disease: &'a Symbol,
+    score: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    probability: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    alternatives: Vec<JsonAlternative<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    features: Option<&'a Features>,
+}
+
+#[derive(Serialize)]
+struct JsonAlternative<'a> {
+    disease: &'a Symbol,
+    probability: f64,
+}
+
+/// What's written along with the ID and disease (and the score in jsonl) of every prediction.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PredictOutputConfig {
+    /// Whether the probability of the predicted disease is written.
+    pub probability: bool,
+    /// How many of the next most likely diseases are written, with their probabilities.
+    pub alternatives: usize,
+    /// The field delimiter of csv output, e.g. `b'\t'` for TSV.
+    pub delimiter: u8,
+    /// Whether the features of every record are written (in csv as a single field of semicolon
+    /// separated symptoms and `column=value` pairs).
+    pub echo_features: bool,
+}
+
+impl Default for PredictOutputConfig {
+    fn default() -> Self {
+        PredictOutputConfig {
+            probability: false,
+            alternatives: 0,
+            delimiter: b',',
+            echo_features: false,
+        }
+    }
+}
+
+impl PredictOutputConfig {
+    /// Whether predictions need the probabilities of the diseases:
+    pub fn wants_probabilities(&self) -> bool {
+        self.probability || self.alternatives > 0
+    }
+}
+
+/// The ID (if there is an ID column) and features of a record.
+pub type IdentifiedRecord = (Option<String>, Features);
+
+/// The disease, features, and weight of a training record.
+pub type TrainingRecord = (String, Features, f64);
+
+/// A malformed row that was skipped in lenient mode.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SkippedRow {
+    /// The (1 based) line of the file.
+    pub row: u64,
+    pub reason: String,
+}
+
+/// The malformed rows skipped while reading a file in lenient mode.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ParseReport {
+    pub skipped: Vec<SkippedRow>,
+}
+
+impl ParseReport {
+    pub fn is_empty(&self) -> bool {
+        self.skipped.is_empty()
+    }
+
+    // Strict mode fails on the first malformed row, lenient mode notes it down and moves on. IO
+    // errors always fail since there's no moving on from them:
+    fn skip_or_fail(&mut self, config: &Config, error: BayesError) -> Result<()> {
+        let row = match &error {
+            _ if config.parse_mode == ParseMode::Strict => return Err(error),
+            BayesError::Parse { row, .. } => *row,
+            BayesError::Csv(e) if !e.is_io_error() => e.position().map_or(0, |p| p.line()),
+            _ => return Err(error),
+        };
+
+        self.skipped.push(SkippedRow {
+            row,
+            reason: error.to_string(),
+        });
+        Ok(())
+    }
+}
+
+impl fmt::Display for ParseReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Skipped {} malformed rows:", self.skipped.len())?;
+        for skipped in &self.skipped {
+            writeln!(f, "  row {}: {}", skipped.row, skipped.reason)?;
+        }
+        Ok(())
+    }
+}
+
+/// Reads the ID (if there is an ID column) and features of every record in the file (in order),
+/// as described by the config:
+pub fn read_records<P: AsRef<Path>>(
+    path: P,
+    format: Format,
+    config: &Config,
+) -> Result<(Vec<IdentifiedRecord>, ParseReport)> {
+    let mut records = Vec::new();
+    let report = for_each_record(
+        spreadsheet::open(path, config)?,
+        format,
+        config,
+        |id, features| {
+            records.push((id, features));
+            Ok(())
+        },
+    )?;
+
+    Ok((records, report))
+}
+
+/// Reads the disease (first column), features, and weight (from the configured weight column, 1
+/// otherwise) of every record in a training csv file:
+pub fn read_training_records<P: AsRef<Path>>(
+    path: P,
+    config: &Config,
+) -> Result<(Vec<TrainingRecord>, ParseReport)> {
+    read_training_records_from(spreadsheet::open(path, config)?, config)
+}
+
+/// Reads training records from csv that isn't in a file:
+pub fn read_training_records_from<R: Read>(
+    reader: R,
+    config: &Config,
+) -> Result<(Vec<TrainingRecord>, ParseReport)> {
+    let mut records = Vec::new();
+    let report = for_each_training_record(reader, config, |record| {
+        records.push(record);
+        Ok(())
+    })?;
+    Ok((records, report))
+}
+
+/// Calls `f` with every training record as soon as it is read, so a file doesn't need to fit in
+/// memory. Returns the malformed rows that were skipped:
+pub fn for_each_training_record<R, F>(reader: R, config: &Config, mut f: F) -> Result<ParseReport>
+where
+    R: Read,
+    F: FnMut(TrainingRecord) -> Result<()>,
+{
+    for_each_training_record_after(reader, config, 0, |record, _| f(record))
+}
+
+/// Like `for_each_training_record`, but skips the first `skip` rows (e.g. the ones a checkpoint
+/// already counted), and also passes `f` how many rows have been read with the record's, malformed
+/// ones included:
+pub fn for_each_training_record_after<R, F>(
+    reader: R,
+    config: &Config,
+    skip: u64,
+    mut f: F,
+) -> Result<ParseReport>
+where
+    R: Read,
+    F: FnMut(TrainingRecord, u64) -> Result<()>,
+{
+    let mut reader = config.dialect.reader(reader)?;
+    let headers = reader.headers()?.clone();
+
+    // Find the column holding the sample weights if there is one:
+    let weight_index = match &config.weight_column {
+        Some(column) => Some(
+            headers
+                .iter()
+                .position(|header| header.trim() == column)
+                .ok_or_else(|| BayesError::MissingColumn(column.clone()))?,
+        ),
+        None => None,
+    };
+
+    let mut tracker = Tracker::start(config, Stage::Training);
+    let mut report = ParseReport::default();
+    for (i, record) in reader.records().enumerate().skip(skip as usize) {
+        let parsed = record.map_err(BayesError::from).and_then(|record| {
+            let disease = disease_field(&record)?;
+
+            let weight = match weight_index {
+                Some(index) => {
+                    let column = &headers[index];
+                    let weight = record.get(index).ok_or_else(|| {
+                        BayesError::parse(record.position(), Some(column), "missing weight entry.")
+                    })?;
+                    weight.trim().parse().map_err(|_| {
+                        let message = format!("invalid weight: {}", weight);
+                        BayesError::parse(record.position(), Some(column), message)
+                    })?
+                }
+                None => 1.0,
+            };
+
+            let features = csv_features(&headers, &record, config, weight_index)?;
+            Ok((disease.to_string(), features, weight))
+        });
+
+        match parsed {
+            Ok(parsed) => {
+                f(parsed, i as u64 + 1)?;
+                tracker.record();
+            }
+            Err(e) => report.skip_or_fail(config, e)?,
+        }
+    }
+
+    log::info!("read {} training records", tracker.finish());
+    Ok(report)
+}
+
+/// The disease of a labeled record (its first column), trimmed like every other field:
+fn disease_field(record: &StringRecord) -> Result<&str> {
+    record
+        .get(0)
+        .map(str::trim)
+        .ok_or_else(|| BayesError::parse(record.position(), None, "missing disease entry."))
+}
+
+/// Reads the disease (first column) and features of every record in a labeled csv file, e.g. a
+/// holdout set:
+pub fn read_labeled_records<P: AsRef<Path>>(
+    path: P,
+    config: &Config,
+) -> Result<(Vec<(String, Features)>, ParseReport)> {
+    let mut reader = config.dialect.reader(spreadsheet::open(path, config)?)?;
+    let headers = reader.headers()?.clone();
+
+    let mut records = Vec::new();
+    let mut report = ParseReport::default();
+    for record in reader.records() {
+        let parsed = record.map_err(BayesError::from).and_then(|record| {
+            let disease = disease_field(&record)?;
+            let features = csv_features(&headers, &record, config, None)?;
+            Ok((disease.to_string(), features))
+        });
+
+        match parsed {
+            Ok(parsed) => records.push(parsed),
+            Err(e) => report.skip_or_fail(config, e)?,
+        }
+    }
+
+    Ok((records, report))
+}
+
+/// Calls `f` with the ID (from the configured ID column) and features of each record as soon as
+/// it is read, this way records can be streamed (e.g. from stdin). Returns the malformed rows
+/// that were skipped:
+pub fn for_each_record<R, F>(
+    reader: R,
+    format: Format,
+    config: &Config,
+    mut f: F,
+) -> Result<ParseReport>
+where
+    R: Read,
+    F: FnMut(Option<String>, Features) -> Result<()>,
+{
+    let mut report = ParseReport::default();
+    match format {
+        Format::Csv => {
+            let mut reader = config.dialect.reader(reader)?;
+            let headers = reader.headers()?.clone();
+            let id_index = match &config.id_column {
+                Some(IdColumn::Name(column)) => Some(
+                    headers
+                        .iter()
+                        .position(|header| header.trim() == column)
+                        .ok_or_else(|| BayesError::MissingColumn(column.clone()))?,
+                ),
+                Some(IdColumn::Index(index)) if *index >= headers.len() => {
+                    return Err(BayesError::MissingColumn(index.to_string()));
+                }
+                Some(IdColumn::Index(index)) => Some(*index),
+                None => None,
+            };
+
+            for record in reader.records() {
+                let parsed = record.map_err(BayesError::from).and_then(|record| {
+                    let id = id_index
+                        .map(|index| {
+                            record
+                                .get(index)
+                                .map(|id| id.trim().to_string())
+                                .ok_or_else(|| {
+                                    BayesError::parse(record.position(), None, "missing id entry.")
+                                })
+                        })
+                        .transpose()?;
+                    Ok((id, csv_features(&headers, &record, config, id_index)?))
+                });
+
+                match parsed {
+                    Ok((id, features)) => f(id, features)?,
+                    Err(e) => report.skip_or_fail(config, e)?,
+                }
+            }
+        }
+        Format::Jsonl => {
+            let reader = BufReader::new(reader);
+            for (i, line) in reader.lines().enumerate() {
+                let line = line?;
+                if line.trim().is_empty() {
+                    continue;
+                }
+
+                let row = i as u64 + 1;
+                let record: JsonRecord = match serde_json::from_str(&line) {
+                    Ok(record) => record,
+                    Err(e) => {
+                        let error = BayesError::Parse {
+                            row,
+                            column: None,
+                            message: e.to_string(),
+                        };
+                        report.skip_or_fail(config, error)?;
+                        continue;
+                    }
+                };
+                let id = match &config.id_column {
+                    Some(IdColumn::Name(field)) => match record.other.get(field) {
+                        Some(serde_json::Value::String(id)) => Some(id.clone()),
+                        Some(id) => Some(id.to_string()),
+                        None => {
+                            let error = BayesError::Parse {
+                                row,
+                                column: Some(field.clone()),
+                                message: "missing id field.".to_string(),
+                            };
+                            report.skip_or_fail(config, error)?;
+                            continue;
+                        }
+                    },
+                    Some(IdColumn::Index(_)) => {
+                        return Err(BayesError::InvalidConfig(
+                            "jsonl id columns must be named.".to_string(),
+                        ))
+                    }
+                    None => None,
+                };
+                f(id, record.into_features(config))?;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+// The first column is the label (or ID) so it's skipped, as is the ID (or weight) column if there
+// is one. Numeric values that aren't blank have to be numbers:
+fn csv_features(
+    headers: &StringRecord,
+    record: &StringRecord,
+    config: &Config,
+    skip_index: Option<usize>,
+) -> Result<Features> {
+    let fields: Vec<_> = headers
+        .iter()
+        .zip(record.iter())
+        .enumerate()
+        .skip(1)
+        .filter(|&(i, _)| Some(i) != skip_index)
+        .map(|(_, field)| field)
+        .collect();
+
+    for &(column, value) in &fields {
+        let value = value.trim();
+        if config.schema.column_type(column) == ColumnType::Numeric
+            && !value.is_empty()
+            && value.parse::<f64>().is_err()
+        {
+            let message = format!("invalid number: {}", value);
+            return Err(BayesError::parse(record.position(), Some(column), message));
+        }
+    }
+
+    Ok(Features::from_fields(fields, config))
+}
+
+impl JsonRecord {
+    pub(crate) fn into_features(self, config: &Config) -> Features {
+        let fields = self.symptoms.iter().chain(self.text.iter());
+        let mut features = Features::from_fields(fields.map(|field| ("", field.as_str())), config);
+        for (column, value) in self.categories {
+            if !value.trim().is_empty() {
+                features
+                    .categories
+                    .insert(Symbol::new(column.trim()), Symbol::new(value.trim()));
+            }
+        }
+        for (column, x) in self.numbers {
+            if x.is_finite() {
+                features.numbers.insert(Symbol::new(column.trim()), x);
+            }
+        }
+        features
+    }
+}
+
+/// Writes predictions one at a time in the given format:
+pub struct PredictionWriter<W: Write> {
+    sink: Sink<W>,
+    output: PredictOutputConfig,
+}
+
+enum Sink<W: Write> {
+    Csv(Box<csv::Writer<W>>),
+    Jsonl(W),
+}
+
+impl<W: Write> PredictionWriter<W> {
+    pub fn new(writer: W, format: Format, output: &PredictOutputConfig) -> Result<Self> {
+        let sink = match format {
+            Format::Csv => {
+                let mut writer = csv::WriterBuilder::new()
+                    .delimiter(output.delimiter)
+                    .from_writer(writer);
+                let mut headers = vec!["ID".to_string(), "Disease".to_string()];
+                if output.probability {
+                    headers.push("Probability".to_string());
+                }
+                for i in 1..=output.alternatives {
+                    headers.push(format!("Alternative{}", i));
+                    headers.push(format!("Alternative{}Probability", i));
+                }
+                if output.echo_features {
+                    headers.push("Features".to_string());
+                }
+                writer.write_record(&headers)?;
+                Sink::Csv(Box::new(writer))
+            }
+            Format::Jsonl => Sink::Jsonl(writer),
+        };
+        Ok(PredictionWriter {
+            sink,
+            output: output.clone(),
+        })
+    }
+
+    /// Writes the prediction of a record, along with whatever the output asks for:
+    pub fn write(&mut self, prediction: &BatchPrediction, features: &Features) -> Result<()> {
+        let output = &self.output;
+        match &mut self.sink {
+            Sink::Csv(writer) => {
+                // Diseases are written straight from their symbols, without copying them:
+                writer.write_field(&prediction.id)?;
+                writer.write_field(prediction.disease.as_str())?;
+                if output.probability {
+                    let probability = prediction.probability.map(|p| p.to_string());
+                    writer.write_field(probability.unwrap_or_default())?;
+                }
+                for i in 0..output.alternatives {
+                    match prediction.alternatives.get(i) {
+                        Some((disease, p)) => {
+                            writer.write_field(disease.as_str())?;
+                            writer.write_field(p.to_string())?;
+                        }
+                        None => {
+                            writer.write_field("")?;
+                            writer.write_field("")?;
+                        }
+                    }
+                }
+                if output.echo_features {
+                    writer.write_field(echo(features))?;
+                }
+                writer.write_record(None::<&[u8]>)?;
+            }
+            Sink::Jsonl(writer) => {
+                // IDs read from the input are written as they were (so "007" stays "007"),
+                // positions as numbers:
+                let id = match prediction.id.parse::<u64>() {
+                    Ok(id) if !prediction.id_from_input => id.into(),
+                    _ => prediction.id.as_str().into(),
+                };
+                let json = JsonPrediction {
+                    id,
+                    disease: &prediction.disease,
+                    score: prediction.score,
+                    probability: prediction.probability.filter(|_| output.probability),
+                    alternatives: prediction
+                        .alternatives
+                        .iter()
+                        .take(output.alternatives)
+                        .map(|(disease, probability)| JsonAlternative {
+                            disease,
+                            probability: *probability,
+                        })
+                        .collect(),
+                    features: Some(features).filter(|_| output.echo_features),
+                };
+                serde_json::to_writer(&mut *writer, &json)?;
+                writeln!(writer)?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<()> {
+        match &mut self.sink {
+            Sink::Csv(writer) => writer.flush()?,
+            Sink::Jsonl(writer) => writer.flush()?,
+        }
+        Ok(())
+    }
+
+    /// Flushes what's been written and returns the underlying writer, e.g. to finish a
+    /// compressed file:
+    pub fn into_inner(self) -> Result<W> {
+        match self.sink {
+            Sink::Csv(writer) => Ok(writer.into_inner().map_err(|e| e.into_error())?),
+            Sink::Jsonl(mut writer) => {
+                writer.flush()?;
+                Ok(writer)
+            }
+        }
+    }
+}
+
+/// The symptoms of a record followed by the `column=value` pairs of its other columns:
+fn echo(features: &Features) -> String {
+    let symptoms = features.counts.keys().map(|symptom| symptom.to_string());
+    let categories = features
+        .categories
+        .iter()
+        .map(|(column, value)| format!("{}={}", column, value));
+    let numbers = features
+        .numbers
+        .iter()
+        .map(|(column, x)| format!("{}={}", column, x));
+    symptoms
+        .chain(categories)
+        .chain(numbers)
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+/// Writes the predictions of the records to the file:
+pub fn write_predictions<P: AsRef<Path>>(
+    path: P,
+    format: Format,
+    results: &[BatchPrediction],
+    records: &[Features],
+    output: &PredictOutputConfig,
+) -> Result<()> {
+    let mut writer = PredictionWriter::new(compress::create(path)?, format, output)?;
+    for (result, features) in results.iter().zip(records) {
+        writer.write(result, features)?;
+    }
+    writer.into_inner()?.finish()
+}
+
+/// Predicts the records of the input file with `predict_batch` and writes the predictions to the
+/// output file, keeping the IDs of the input records if they have any. The format of each file is
+/// picked from its extension unless one is given. Returns the malformed rows that were skipped:
+pub fn predict_file<P, F>(
+    inpath: P,
+    outpath: P,
+    format: Option<Format>,
+    config: &Config,
+    predict_batch: F,
+) -> Result<ParseReport>
+where
+    P: AsRef<Path>,
+    F: FnOnce(&[Features]) -> Vec<BatchPrediction>,
+{
+    let informat = format.unwrap_or_else(|| Format::from_path(&inpath));
+    let outformat = format.unwrap_or_else(|| Format::from_path(&outpath));
+
+    let (records, report) = read_records(inpath, informat, config)?;
+    let (ids, records): (Vec<_>, Vec<_>) = records.into_iter().unzip();
+    let mut results = predict_batch(&records);
+    for (result, id) in results.iter_mut().zip(ids) {
+        if let Some(id) = id {
+            result.id = id;
+            result.id_from_input = true;
+        }
+    }
+
+    write_predictions(outpath, outformat, &results, &records, &config.output)?;
+    Ok(report)
+}
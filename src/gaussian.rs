@@ -0,0 +1,113 @@
+use crate::symbols::Symbol;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap},
+    f64::consts::PI,
+};
+
+/// Per disease normal distributions of every numeric column.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GaussianModel {
+    // column -> disease -> (mean, variance)
+    params: HashMap<Symbol, HashMap<Symbol, (f64, f64)>>,
+}
+
+// A fraction of the largest variance added to every variance, so that a column that is constant
+// within a disease doesn't produce infinite likelihoods:
+const VARIANCE_SMOOTHING: f64 = 1e-9;
+
+/// The weighted mean and spread of a numeric column's values, updated one value at a time so
+/// they never have to be kept around.
+#[derive(Clone, Copy, Debug, Default, Serialize, Deserialize)]
+pub struct Moments {
+    pub weight: f64,
+    pub mean: f64,
+    /// The weighted sum of squared differences from the mean.
+    pub m2: f64,
+}
+
+impl Moments {
+    pub fn add(&mut self, x: f64, weight: f64) {
+        let total = self.weight + weight;
+        if total <= 0.0 {
+            return;
+        }
+        let delta = x - self.mean;
+        self.mean += delta * weight / total;
+        self.m2 += weight * delta * (x - self.mean);
+        self.weight = total;
+    }
+
+    /// Combines the moments of two sets of values:
+    pub fn merge(&mut self, other: &Moments) {
+        let total = self.weight + other.weight;
+        if total <= 0.0 {
+            return;
+        }
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.weight / total;
+        self.m2 += other.m2 + delta * delta * self.weight * other.weight / total;
+        self.weight = total;
+    }
+
+    pub fn variance(&self) -> f64 {
+        self.m2 / self.weight
+    }
+}
+
+impl GaussianModel {
+    /// Fits the (weighted) mean and variance of each numeric column for each disease from the
+    /// moments of the training records. Records without a value for a column don't count
+    /// towards that column:
+    pub fn fit(numbers: &BTreeMap<Symbol, BTreeMap<Symbol, Moments>>) -> Self {
+        let mut params = HashMap::new();
+        for (column, moments) in numbers {
+            let mut disease_params = HashMap::new();
+            let mut max_variance: f64 = 0.0;
+            for (disease, moments) in moments {
+                if moments.weight <= 0.0 {
+                    continue;
+                }
+
+                let variance = moments.variance();
+                max_variance = max_variance.max(variance);
+                disease_params.insert(disease.clone(), (moments.mean, variance));
+            }
+
+            let epsilon = (VARIANCE_SMOOTHING * max_variance).max(VARIANCE_SMOOTHING);
+            for (_, variance) in disease_params.values_mut() {
+                *variance += epsilon;
+            }
+            params.insert(column.clone(), disease_params);
+        }
+
+        GaussianModel { params }
+    }
+
+    /// The sum of the log densities of a record's numeric values for the disease. Columns the
+    /// disease never had a value for are skipped:
+    pub fn log_likelihood(&self, disease: &str, numbers: &BTreeMap<Symbol, f64>) -> f64 {
+        numbers
+            .iter()
+            .filter_map(|(column, &x)| self.log_density(disease, column, x))
+            .sum()
+    }
+
+    /// The log density of a numeric column taking on the value for the disease:
+    pub fn log_density(&self, disease: &str, column: &str, x: f64) -> Option<f64> {
+        let &(mean, variance) = self.params.get(column)?.get(disease)?;
+        Some(-0.5 * ((2.0 * PI * variance).ln() + (x - mean).powi(2) / variance))
+    }
+
+    /// The mean and variance of a numeric column for the disease:
+    pub fn params(&self, disease: &str, column: &str) -> Option<(f64, f64)> {
+        self.params.get(column)?.get(disease).copied()
+    }
+
+    /// All of the numeric columns seen during training (sorted):
+    pub fn columns(&self) -> Vec<&str> {
+        let mut columns: Vec<&str> = self.params.keys().map(|c| c.as_str()).collect();
+        columns.sort();
+        columns
+    }
+}
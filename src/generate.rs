@@ -0,0 +1,144 @@
+use crate::{
+    compress,
+    error::{BayesError, Result},
+    features::Features,
+    rng::Rng,
+    symbols::Symbol,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+/// A generative model of labeled records: a record's disease is drawn from the priors, and then
+/// each of the disease's symptoms is independently present with its probability.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct GenerativeModel {
+    /// The relative weight of every disease.
+    pub priors: BTreeMap<String, f64>,
+    /// disease -> symptom -> probability of the symptom being present.
+    pub symptoms: BTreeMap<String, BTreeMap<String, f64>>,
+    /// The probability of a record's label being swapped for a uniformly random disease after its
+    /// symptoms were drawn.
+    #[serde(default)]
+    pub noise: f64,
+}
+
+impl GenerativeModel {
+    /// Reads a model from a json file like
+    /// `{"priors": {"Flu": 2, "Cold": 1}, "symptoms": {"Flu": {"fever": 0.9}, ...}, "noise": 0.1}`:
+    pub fn from_json<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let model: GenerativeModel = serde_json::from_reader(compress::open(path)?)?;
+        model.validate()?;
+        Ok(model)
+    }
+
+    /// A random model where every disease has `symptoms_per_class` characteristic symptoms
+    /// (picked from a shared vocabulary, so diseases overlap) that show up 30% to 90% of the time:
+    pub fn random(
+        classes: usize,
+        vocabulary: usize,
+        symptoms_per_class: usize,
+        noise: f64,
+        seed: u64,
+    ) -> Self {
+        let mut rng = Rng::seeded(seed);
+        let mut model = GenerativeModel {
+            noise,
+            ..Default::default()
+        };
+        for class in 0..classes {
+            let disease = format!("disease{}", class);
+            model.priors.insert(disease.clone(), 0.5 + rng.next_f64());
+
+            let mut symptoms = BTreeMap::new();
+            for _ in 0..symptoms_per_class.min(vocabulary) {
+                let symptom = format!("symptom{}", rng.below(vocabulary));
+                symptoms.insert(symptom, 0.3 + 0.6 * rng.next_f64());
+            }
+            model.symptoms.insert(disease, symptoms);
+        }
+        model
+    }
+
+    fn validate(&self) -> Result<()> {
+        let total: f64 = self.priors.values().sum();
+        if self.priors.values().any(|p| p.is_nan() || *p < 0.0) || total <= 0.0 {
+            return Err(BayesError::InvalidConfig(
+                "priors must be non-negative and not all zero.".to_string(),
+            ));
+        }
+        let probabilities = self
+            .symptoms
+            .values()
+            .flat_map(|symptoms| symptoms.values());
+        if probabilities
+            .chain([&self.noise])
+            .any(|p| p.is_nan() || !(0.0..=1.0).contains(p))
+        {
+            return Err(BayesError::InvalidConfig(
+                "symptom probabilities and noise must be between 0 and 1.".to_string(),
+            ));
+        }
+        Ok(())
+    }
+
+    /// Draws (disease, features) records:
+    pub fn generate(&self, records: usize, seed: u64) -> Result<Vec<(String, Features)>> {
+        self.validate()?;
+
+        let diseases: Vec<(&String, f64)> = self.priors.iter().map(|(d, &p)| (d, p)).collect();
+        let total: f64 = diseases.iter().map(|(_, p)| p).sum();
+        let no_symptoms = BTreeMap::new();
+
+        let mut rng = Rng::seeded(seed);
+        let mut generated = Vec::with_capacity(records);
+        for _ in 0..records {
+            // Walk the priors until the drawn weight is used up:
+            let mut draw = rng.next_f64() * total;
+            let mut disease = diseases[diseases.len() - 1].0;
+            for &(candidate, p) in &diseases {
+                if draw < p {
+                    disease = candidate;
+                    break;
+                }
+                draw -= p;
+            }
+
+            let mut features = Features::default();
+            for (symptom, &p) in self.symptoms.get(disease).unwrap_or(&no_symptoms) {
+                if rng.next_f64() < p {
+                    features.counts.insert(Symbol::new(symptom), 1.0);
+                }
+            }
+
+            if rng.next_f64() < self.noise {
+                disease = diseases[rng.below(diseases.len())].0;
+            }
+            generated.push((disease.clone(), features));
+        }
+        Ok(generated)
+    }
+
+    /// Writes generated records as a training csv file with a disease column followed by symptom
+    /// columns:
+    pub fn write_csv<P: AsRef<Path>>(&self, path: P, records: usize, seed: u64) -> Result<()> {
+        let records = self.generate(records, seed)?;
+        let width = records
+            .iter()
+            .map(|(_, features)| features.counts.len())
+            .max()
+            .unwrap_or(0)
+            .max(1);
+
+        let mut writer = csv::Writer::from_writer(compress::create(path)?);
+        let mut header = vec!["Disease".to_string()];
+        header.extend((1..=width).map(|i| format!("S{}", i)));
+        writer.write_record(&header)?;
+        for (disease, features) in &records {
+            let mut row: Vec<&str> = vec![disease];
+            row.extend(features.counts.keys().map(|s| s.as_str()));
+            row.resize(width + 1, "");
+            writer.write_record(&row)?;
+        }
+        writer.into_inner().map_err(|e| e.into_error())?.finish()
+    }
+}
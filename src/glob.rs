@@ -0,0 +1,114 @@
+use crate::error::{BayesError, Result};
+use std::{
+    fs,
+    path::{Component, Path, PathBuf},
+};
+
+/// The files matching a pattern whose components may hold `*` (any run of characters) and `?`
+/// (any one character) wildcards, e.g. `shards/2024-*/*.csv`, sorted. Like a shell, wildcards
+/// don't match names starting with a dot unless the pattern's component does, and only files
+/// (not directories) are returned. A pattern without wildcards is taken as the path it is,
+/// whether or not it exists:
+pub fn glob<P: AsRef<Path>>(pattern: P) -> Result<Vec<PathBuf>> {
+    let pattern = pattern.as_ref();
+    if !has_wildcards(&pattern.to_string_lossy()) {
+        return Ok(vec![pattern.to_path_buf()]);
+    }
+
+    let mut paths = vec![PathBuf::new()];
+    for component in pattern.components() {
+        let Component::Normal(os_name) = component else {
+            // Roots, prefixes, `.`, and `..` are kept as they are:
+            paths.iter_mut().for_each(|path| path.push(component));
+            continue;
+        };
+        // Names are only matched as (lossy) strings, paths are built from the names themselves:
+        let name = os_name.to_string_lossy();
+        if !has_wildcards(&name) {
+            paths.iter_mut().for_each(|path| path.push(os_name));
+            continue;
+        }
+
+        let mut matches = Vec::new();
+        for path in &paths {
+            let dir = if path.as_os_str().is_empty() {
+                Path::new(".")
+            } else {
+                path.as_path()
+            };
+            // Paths that aren't directories simply don't match anything:
+            let Ok(entries) = fs::read_dir(dir) else {
+                continue;
+            };
+            for entry in entries {
+                let file_name = entry?.file_name();
+                let lossy_name = file_name.to_string_lossy();
+                if lossy_name.starts_with('.') && !name.starts_with('.') {
+                    continue;
+                }
+                if matches_pattern(&name, &lossy_name) {
+                    matches.push(path.join(&file_name));
+                }
+            }
+        }
+        paths = matches;
+    }
+
+    // Directories matching the last component aren't files to read:
+    paths.retain(|path| path.is_file());
+    if paths.is_empty() {
+        return Err(BayesError::InvalidConfig(format!(
+            "no files match {}",
+            pattern.display()
+        )));
+    }
+    paths.sort();
+    Ok(paths)
+}
+
+/// The files matching every pattern, in the order of the patterns:
+pub fn glob_all<I, P>(patterns: I) -> Result<Vec<PathBuf>>
+where
+    I: IntoIterator<Item = P>,
+    P: AsRef<Path>,
+{
+    let mut paths = Vec::new();
+    for pattern in patterns {
+        paths.extend(glob(pattern)?);
+    }
+    Ok(paths)
+}
+
+fn has_wildcards(s: &str) -> bool {
+    s.contains(['*', '?'])
+}
+
+/// Whether a name matches a pattern, backtracking to the last `*` on a mismatch:
+fn matches_pattern(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut star: Option<(usize, usize)> = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                // Let the star swallow one more character:
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
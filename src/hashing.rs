@@ -0,0 +1,51 @@
+use crate::{features::Features, symbols::Symbol};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+/// What the names of hashed features start with, followed by the bucket number.
+pub const BUCKET_PREFIX: &str = "#";
+
+/// The hashing trick: maps symptom (and token) names into a fixed number of buckets, so a model
+/// never has more features than that however large the vocabulary is. Symptoms whose names land
+/// in the same bucket are counted as one. Only buckets that symptoms landed in during training
+/// are part of the vocabulary, symptoms landing in any other bucket are unknown as usual.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeatureHasher {
+    pub buckets: usize,
+}
+
+impl FeatureHasher {
+    pub fn new(buckets: usize) -> Self {
+        FeatureHasher { buckets }
+    }
+
+    /// The bucket of a name. The hash is the same on every platform and run, so models can be
+    /// saved and used elsewhere:
+    pub fn bucket(&self, name: &str) -> usize {
+        (fnv1a(name.as_bytes()) % self.buckets as u64) as usize
+    }
+
+    /// The name of the feature a name is hashed to:
+    pub fn hash_name(&self, name: &str) -> String {
+        format!("{}{}", BUCKET_PREFIX, self.bucket(name))
+    }
+
+    /// Hashes the symptoms of a record, adding up the counts of symptoms that land in the same
+    /// bucket. Classifiers trained with a hasher do this to every record they're given:
+    pub fn transform(&self, features: &mut Features) {
+        let mut counts = BTreeMap::new();
+        for (symptom, count) in &features.counts {
+            *counts
+                .entry(Symbol::from(self.hash_name(symptom)))
+                .or_default() += count;
+        }
+        features.counts = counts;
+    }
+}
+
+/// 64 bit FNV-1a:
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
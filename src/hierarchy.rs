@@ -0,0 +1,333 @@
+use crate::{
+    bayes::{BatchPrediction, NaiveBayesClassifier},
+    config::{Config, Priors},
+    error::{BayesError, Result},
+    features::Features,
+    format::{self, Format, ParseReport},
+    progress::{Stage, Tracker},
+    symbols::Symbol,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt,
+    path::Path,
+};
+
+/// The category every disease belongs to, for diseases with a two level hierarchy.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Hierarchy {
+    categories: BTreeMap<String, String>,
+}
+
+impl Hierarchy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reads a csv file of disease,category rows (after a header row):
+    pub fn from_csv<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut reader = csv::Reader::from_path(path)?;
+
+        let mut hierarchy = Hierarchy::new();
+        for record in reader.records() {
+            let record = record?;
+            let disease = record.get(0).ok_or_else(|| {
+                BayesError::parse(record.position(), None, "missing disease entry.")
+            })?;
+            let category = record.get(1).ok_or_else(|| {
+                BayesError::parse(record.position(), None, "missing category entry.")
+            })?;
+            hierarchy.insert(disease.trim(), category.trim());
+        }
+        Ok(hierarchy)
+    }
+
+    pub fn insert<S: Into<String>>(&mut self, disease: S, category: S) {
+        self.categories.insert(disease.into(), category.into());
+    }
+
+    /// The category of a disease:
+    pub fn category(&self, disease: &str) -> Option<&str> {
+        self.categories
+            .get(disease)
+            .map(|category| category.as_str())
+    }
+
+    fn category_or_err(&self, disease: &str) -> Result<&str> {
+        self.category(disease).ok_or_else(|| {
+            BayesError::InvalidConfig(format!("disease {} has no category.", disease))
+        })
+    }
+}
+
+/// A prediction of both levels of the hierarchy.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HierarchicalPrediction<'a> {
+    pub category: &'a str,
+    pub category_probability: f64,
+    pub disease: &'a str,
+    /// The probability of the category times that of the disease within it.
+    pub probability: f64,
+}
+
+/// Hierarchical naive Bayes: predicts the category of a record first, and then the disease
+/// within that category with a classifier trained on only the records of the category.
+pub struct HierarchicalClassifier {
+    hierarchy: Hierarchy,
+    categories: NaiveBayesClassifier,
+    // The classifier of every category with more than one disease:
+    diseases: BTreeMap<String, NaiveBayesClassifier>,
+    // The disease of every category with only one:
+    only_disease: BTreeMap<String, String>,
+    training_report: ParseReport,
+}
+
+impl HierarchicalClassifier {
+    /// Trains on a csv file:
+    pub fn with_config<P: AsRef<Path>>(
+        path: P,
+        config: Config,
+        hierarchy: Hierarchy,
+    ) -> Result<Self> {
+        let (records, report) = format::read_training_records(path, &config)?;
+        let mut classifier = Self::fit_weighted(records, config, hierarchy)?;
+        classifier.training_report = report;
+        Ok(classifier)
+    }
+
+    /// Trains on (disease, features, weight) records, every disease needs a category:
+    pub fn fit_weighted<I, S>(records: I, config: Config, hierarchy: Hierarchy) -> Result<Self>
+    where
+        I: IntoIterator<Item = (S, Features, f64)>,
+        S: Into<String>,
+    {
+        let mut by_category: BTreeMap<String, Vec<(String, Features, f64)>> = BTreeMap::new();
+        for (disease, features, weight) in records {
+            let disease = disease.into();
+            let category = hierarchy.category_or_err(&disease)?.to_string();
+            by_category
+                .entry(category)
+                .or_default()
+                .push((disease, features, weight));
+        }
+
+        // The weight of a category is that of all of its diseases:
+        let category_priors = match &config.priors {
+            Priors::Custom(weights) => {
+                let mut category_weights = HashMap::new();
+                for (disease, weight) in weights {
+                    if let Some(category) = hierarchy.category(disease) {
+                        *category_weights.entry(category.to_string()).or_default() += weight;
+                    }
+                }
+                Priors::Custom(category_weights)
+            }
+            priors => priors.clone(),
+        };
+        let categories = NaiveBayesClassifier::fit_weighted(
+            by_category.iter().flat_map(|(category, records)| {
+                records.iter().map(move |(_, features, weight)| {
+                    (category.as_str(), features.clone(), *weight)
+                })
+            }),
+            Config {
+                priors: category_priors,
+                ..config.clone()
+            },
+        )?;
+
+        let mut diseases = BTreeMap::new();
+        let mut only_disease = BTreeMap::new();
+        for (category, records) in by_category {
+            let first = records[0].0.clone();
+            if records.iter().all(|(disease, _, _)| *disease == first) {
+                only_disease.insert(category, first);
+            } else {
+                let classifier = NaiveBayesClassifier::fit_weighted(records, config.clone())?;
+                diseases.insert(category, classifier);
+            }
+        }
+
+        Ok(HierarchicalClassifier {
+            hierarchy,
+            categories,
+            diseases,
+            only_disease,
+            training_report: ParseReport::default(),
+        })
+    }
+
+    /// The malformed rows skipped while reading the training file in lenient mode:
+    pub fn training_report(&self) -> &ParseReport {
+        &self.training_report
+    }
+
+    pub fn config(&self) -> &Config {
+        self.categories.config()
+    }
+
+    pub fn hierarchy(&self) -> &Hierarchy {
+        &self.hierarchy
+    }
+
+    /// The classifier of the categories:
+    pub fn categories(&self) -> &NaiveBayesClassifier {
+        &self.categories
+    }
+
+    /// Predicts the most likely category, and then the most likely disease within it:
+    pub fn predict_levels(&self, psymptoms: &Features) -> HierarchicalPrediction<'_> {
+        let (category, category_probability) = self
+            .categories
+            .predict_top_k(psymptoms, 1)
+            .first()
+            .copied()
+            .unwrap_or(("", 0.0));
+
+        let (disease, within) = match self.diseases.get(category) {
+            Some(classifier) => classifier
+                .predict_top_k(psymptoms, 1)
+                .first()
+                .copied()
+                .unwrap_or(("", 0.0)),
+            None => match self.only_disease.get(category) {
+                Some(disease) => (disease.as_str(), 1.0),
+                None => ("", 0.0),
+            },
+        };
+
+        HierarchicalPrediction {
+            category,
+            category_probability,
+            disease,
+            probability: category_probability * within,
+        }
+    }
+
+    /// The probability of every disease, the probability of its category times that of the
+    /// disease within it, most likely first:
+    pub fn probabilities(&self, psymptoms: &Features) -> Vec<(&str, f64)> {
+        let mut probabilities = Vec::new();
+        for (category, p) in self.categories.predict_top_k(psymptoms, usize::MAX) {
+            match self.diseases.get(category) {
+                Some(classifier) => probabilities.extend(
+                    classifier
+                        .predict_top_k(psymptoms, usize::MAX)
+                        .into_iter()
+                        .map(|(disease, within)| (disease, p * within)),
+                ),
+                None => probabilities.extend(
+                    self.only_disease
+                        .get(category)
+                        .map(|disease| (disease.as_str(), p)),
+                ),
+            }
+        }
+        probabilities.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        probabilities
+    }
+
+    /// Predicts every record, returning the predictions in the same order, picking the disease
+    /// with the lowest expected cost given a cost matrix. The classifier abstains when the
+    /// probability of the most likely disease is below the minimum confidence, or when the top
+    /// category doesn't beat the runner up by the minimum margin:
+    pub fn predict_batch(&self, records: &[Features]) -> Vec<BatchPrediction> {
+        let config = self.config();
+        let mut tracker = Tracker::start(config, Stage::Predicting);
+        let results = records
+            .iter()
+            .enumerate()
+            .map(|(i, symptoms)| {
+                tracker.record();
+                let prediction = self.predict_levels(symptoms);
+                let runner_up = self
+                    .categories
+                    .predict_top_k(symptoms, 2)
+                    .get(1)
+                    .map_or(0.0, |&(_, p)| p);
+                // The disease with the lowest expected cost rather than the most likely one:
+                let (disease, probability) = match &config.costs {
+                    Some(costs) => costs
+                        .decide(&self.probabilities(symptoms))
+                        .unwrap_or(("", 0.0)),
+                    None => (prediction.disease, prediction.probability),
+                };
+                let disease = if prediction.probability < config.min_confidence
+                    || prediction.category_probability - runner_up < config.min_margin
+                {
+                    &config.unknown_label
+                } else {
+                    disease
+                };
+
+                let mut batch_prediction = BatchPrediction {
+                    id: (i + 1).to_string(),
+                    id_from_input: false,
+                    disease: Symbol::new(disease),
+                    score: probability,
+                    probability: None,
+                    alternatives: Vec::new(),
+                };
+                batch_prediction.describe(&config.output, || self.probabilities(symptoms));
+                batch_prediction
+            })
+            .collect();
+        log::info!("predicted {} records", tracker.finish());
+        results
+    }
+
+    /// Predicts the records of a test file like `NaiveBayesClassifier::predict`:
+    pub fn predict<P: AsRef<Path>>(
+        &self,
+        inpath: P,
+        outpath: P,
+        format: Option<Format>,
+    ) -> Result<ParseReport> {
+        format::predict_file(inpath, outpath, format, self.config(), |records| {
+            self.predict_batch(records)
+        })
+    }
+
+    /// Predicts every (disease, features) record and compares against its category and disease:
+    pub fn evaluate(&self, records: &[(String, Features)]) -> HierarchyEvaluation {
+        let (mut categories_correct, mut diseases_correct) = (0, 0);
+        for (disease, features) in records {
+            let prediction = self.predict_levels(features);
+            if self.hierarchy.category(disease) == Some(prediction.category) {
+                categories_correct += 1;
+            }
+            if prediction.disease == disease {
+                diseases_correct += 1;
+            }
+        }
+
+        let accuracy = |correct: usize| {
+            if records.is_empty() {
+                0.0
+            } else {
+                correct as f64 / records.len() as f64
+            }
+        };
+        HierarchyEvaluation {
+            num_records: records.len(),
+            category_accuracy: accuracy(categories_correct),
+            accuracy: accuracy(diseases_correct),
+        }
+    }
+}
+
+/// How well a hierarchical classifier did on a labeled data set, at both levels.
+#[derive(Clone, Debug, PartialEq)]
+pub struct HierarchyEvaluation {
+    pub num_records: usize,
+    pub category_accuracy: f64,
+    pub accuracy: f64,
+}
+
+impl fmt::Display for HierarchyEvaluation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "records:           {}", self.num_records)?;
+        writeln!(f, "category accuracy: {:.4}", self.category_accuracy)?;
+        writeln!(f, "disease accuracy:  {:.4}", self.accuracy)
+    }
+}
@@ -0,0 +1,132 @@
+use crate::{bayes::DiseaseRecords, features::Features, symbols::Symbol};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Joins the two symptoms of a pair into the name of the feature it's added as.
+pub const PAIR_SEPARATOR: &str = " & ";
+
+/// Pairs of symptoms that are added as features of their own (present when both symptoms are),
+/// relaxing the assumption that symptoms are independent given the disease for the pairs where
+/// it's furthest off.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct FeaturePairs {
+    /// Every pair's symptoms in order, the most dependent pair first.
+    pairs: Vec<(String, String)>,
+}
+
+impl FeaturePairs {
+    /// Picks the (up to) `max_pairs` pairs of symptoms that show up together in some record with
+    /// the highest conditional mutual information given the disease, I(A; B | disease), over the
+    /// (weighted) records. Ties are broken by name so the pairs are always the same:
+    pub fn fit(diseases_map: &DiseaseRecords, max_pairs: usize) -> Self {
+        let mut num_records = 0.0;
+        let mut scores: HashMap<(&str, &str), f64> = HashMap::new();
+        let mut disease_counts = Vec::with_capacity(diseases_map.len());
+        for records in diseases_map.values() {
+            // Weight of the disease's records with every symptom and with every pair:
+            let mut total = 0.0;
+            let mut symptoms: HashMap<&str, f64> = HashMap::new();
+            let mut pairs: HashMap<(&str, &str), f64> = HashMap::new();
+            for (features, weight) in records {
+                total += weight;
+                let present: Vec<&str> = features
+                    .counts
+                    .iter()
+                    .filter(|&(_, &count)| count > 0.0)
+                    .map(|(symptom, _)| symptom.as_str())
+                    .collect();
+                for (i, &a) in present.iter().enumerate() {
+                    *symptoms.entry(a).or_default() += weight;
+                    // Symptoms are sorted, so every pair is only counted in one order:
+                    for &b in &present[i + 1..] {
+                        *pairs.entry((a, b)).or_default() += weight;
+                    }
+                }
+            }
+            num_records += total;
+            for &pair in pairs.keys() {
+                scores.insert(pair, 0.0);
+            }
+            disease_counts.push((total, symptoms, pairs));
+        }
+
+        // Add up every disease's term of the information, including those of the diseases a
+        // pair never shows up together in:
+        for ((a, b), score) in scores.iter_mut() {
+            for (total, symptoms, pairs) in &disease_counts {
+                let with_a = symptoms.get(a).copied().unwrap_or(0.0);
+                let with_b = symptoms.get(b).copied().unwrap_or(0.0);
+                let with_both = pairs.get(&(*a, *b)).copied().unwrap_or(0.0);
+                *score += information(*total, with_a, with_b, with_both) / num_records;
+            }
+        }
+
+        let mut ranking: Vec<((&str, &str), f64)> = scores.into_iter().collect();
+        ranking.sort_by(|(pa, a), (pb, b)| b.total_cmp(a).then_with(|| pa.cmp(pb)));
+        let pairs: Vec<(String, String)> = ranking
+            .into_iter()
+            .take(max_pairs)
+            .map(|((a, b), _)| (a.to_string(), b.to_string()))
+            .collect();
+        log::info!("added {} symptom pairs as features", pairs.len());
+        FeaturePairs { pairs }
+    }
+
+    pub fn pairs(&self) -> &[(String, String)] {
+        &self.pairs
+    }
+
+    /// The names of the features the pairs are added as:
+    pub fn names(&self) -> impl Iterator<Item = Symbol> + '_ {
+        self.pairs
+            .iter()
+            .map(|(a, b)| Symbol::from(pair_name(a, b)))
+    }
+
+    /// Adds the feature of every pair whose symptoms are both present, counted as often as the
+    /// rarer of the two:
+    pub fn transform(&self, features: &mut Features) {
+        let mut added = BTreeMap::new();
+        for (a, b) in &self.pairs {
+            if let (Some(&count_a), Some(&count_b)) = (
+                features.counts.get(a.as_str()),
+                features.counts.get(b.as_str()),
+            ) {
+                let count = count_a.min(count_b);
+                if count > 0.0 {
+                    added.insert(Symbol::from(pair_name(a, b)), count);
+                }
+            }
+        }
+        features.counts.extend(added);
+    }
+}
+
+pub fn pair_name(a: &str, b: &str) -> String {
+    format!("{}{}{}", a, PAIR_SEPARATOR, b)
+}
+
+/// A disease's (weighted) term of the conditional mutual information of two symptoms being
+/// present, from the weight of its records, of those with either symptom, and of those with both.
+/// It's scaled by the disease's weight but not yet divided by the total weight:
+fn information(total: f64, with_a: f64, with_b: f64, with_both: f64) -> f64 {
+    if total <= 0.0 {
+        return 0.0;
+    }
+    // (weight of the cell, weight with its value of a, weight with its value of b):
+    let cells = [
+        (with_both, with_a, with_b),
+        (with_a - with_both, with_a, total - with_b),
+        (with_b - with_both, total - with_a, with_b),
+        (
+            total - with_a - with_b + with_both,
+            total - with_a,
+            total - with_b,
+        ),
+    ];
+    cells
+        .into_iter()
+        .filter(|&(observed, a, b)| observed > 0.0 && a > 0.0 && b > 0.0)
+        .map(|(observed, a, b)| observed * (observed * total / (a * b)).ln())
+        .sum()
+}
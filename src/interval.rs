@@ -0,0 +1,283 @@
+use crate::{
+    bayes::NaiveBayesClassifier,
+    config::{ModelKind, Priors},
+    error::{BayesError, Result},
+    features::Features,
+    rng::Rng,
+};
+use std::{
+    collections::{BTreeMap, HashSet},
+    fmt,
+};
+
+/// Options controlling how the posterior of a classifier is sampled.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct IntervalConfig {
+    /// How many classifiers are drawn from the posterior.
+    pub samples: usize,
+    /// The probability mass inside every interval, e.g. 0.95.
+    pub level: f64,
+    /// Seeds the posterior samples, unless the classifier's config has a seed.
+    pub seed: u64,
+}
+
+impl Default for IntervalConfig {
+    fn default() -> Self {
+        IntervalConfig {
+            samples: 200,
+            level: 0.95,
+            seed: 0,
+        }
+    }
+}
+
+/// The probability of a disease for a record, along with a credible interval of it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CredibleInterval {
+    pub class: String,
+    /// The (uncalibrated) probability the classifier itself predicts.
+    pub probability: f64,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl fmt::Display for CredibleInterval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} {:.4} [{:.4}, {:.4}]",
+            self.class, self.probability, self.lower, self.upper
+        )
+    }
+}
+
+/// Draws classifiers from the posterior of the counts a classifier was trained on, to tell how
+/// much its probabilities could change given more training records. Every disease's betas are
+/// Dirichlet (multinomial) or Beta (bernoulli) distributed given its counts and the smoothing,
+/// and so are the values of its categorical columns. Empirical priors are Dirichlet distributed
+/// given the number of records of every disease, while other priors and the numeric columns are
+/// taken as they are.
+pub struct PosteriorSampler<'a> {
+    classifier: &'a NaiveBayesClassifier,
+    config: IntervalConfig,
+    rng: Rng,
+    // Sorted, so bernoulli betas are drawn in the same order every time:
+    vocabulary: Vec<&'a str>,
+    known: HashSet<&'a str>,
+    // The sum of the (smoothed) symptom counts of every disease over the vocabulary:
+    totals: BTreeMap<&'a str, f64>,
+}
+
+impl<'a> PosteriorSampler<'a> {
+    pub fn new(classifier: &'a NaiveBayesClassifier, config: IntervalConfig) -> Result<Self> {
+        if config.samples == 0 {
+            return Err(BayesError::InvalidConfig(
+                "there must be at least one posterior sample.".to_string(),
+            ));
+        }
+        if config.level.is_nan() || config.level <= 0.0 || config.level >= 1.0 {
+            return Err(BayesError::InvalidConfig(format!(
+                "credible level must be between 0 and 1: {}",
+                config.level
+            )));
+        }
+
+        let vocabulary = classifier.features();
+        let known: HashSet<&str> = vocabulary.iter().copied().collect();
+        let alpha = classifier.config().smoothing;
+        let stats = classifier.stats();
+        let totals = classifier
+            .classes()
+            .into_iter()
+            .map(|class| {
+                let counts: f64 = stats.symptom_counts.get(class).map_or(0.0, |counts| {
+                    counts
+                        .iter()
+                        .filter(|(symptom, _)| known.contains(symptom.as_str()))
+                        .map(|(_, count)| count)
+                        .sum()
+                });
+                (class, counts + alpha * vocabulary.len() as f64)
+            })
+            .collect();
+
+        Ok(PosteriorSampler {
+            classifier,
+            config,
+            rng: Rng::seeded(classifier.config().seed.unwrap_or(config.seed)),
+            vocabulary,
+            known,
+            totals,
+        })
+    }
+
+    /// The credible interval of the probability of every disease for the record, most likely
+    /// disease first:
+    pub fn intervals(&mut self, psymptoms: &Features) -> Vec<CredibleInterval> {
+        let classifier = self.classifier;
+        let prepared = classifier.prepare(psymptoms);
+        let classes = classifier.classes();
+
+        let mut samples: Vec<Vec<f64>> =
+            vec![Vec::with_capacity(self.config.samples); classes.len()];
+        for _ in 0..self.config.samples {
+            let log_priors = self.sample_log_priors(&classes);
+            let mut scores: Vec<f64> = classes
+                .iter()
+                .zip(log_priors)
+                .map(|(&class, log_prior)| log_prior + self.sample_log_likelihood(class, &prepared))
+                .collect();
+
+            // Normalize in log space so that small products don't underflow:
+            let max_score = scores.iter().fold(f64::NEG_INFINITY, |acc, &s| acc.max(s));
+            let total: f64 = scores.iter().map(|s| (s - max_score).exp()).sum();
+            for (score, class_samples) in scores.iter_mut().zip(&mut samples) {
+                class_samples.push((*score - max_score).exp() / total);
+            }
+        }
+
+        let probabilities: BTreeMap<&str, f64> =
+            classifier.probabilities(psymptoms).into_iter().collect();
+        let tail = (1.0 - self.config.level) / 2.0;
+        let mut intervals: Vec<CredibleInterval> = classes
+            .iter()
+            .zip(samples)
+            .map(|(&class, mut class_samples)| {
+                class_samples.sort_by(|a, b| a.total_cmp(b));
+                CredibleInterval {
+                    class: class.to_string(),
+                    probability: probabilities.get(class).copied().unwrap_or(0.0),
+                    lower: quantile(&class_samples, tail),
+                    upper: quantile(&class_samples, 1.0 - tail),
+                }
+            })
+            .collect();
+        intervals.sort_by(|a, b| {
+            b.probability
+                .total_cmp(&a.probability)
+                .then_with(|| a.class.cmp(&b.class))
+        });
+        intervals
+    }
+
+    fn sample_log_priors(&mut self, classes: &[&str]) -> Vec<f64> {
+        let classifier = self.classifier;
+        if !matches!(classifier.config().priors, Priors::Empirical) {
+            return classes
+                .iter()
+                .map(|&class| classifier.class_log_prior(class).unwrap())
+                .collect();
+        }
+
+        // Dirichlet distributed given the records of every disease (and a uniform prior):
+        let records = &classifier.stats().records;
+        let draws: Vec<f64> = classes
+            .iter()
+            .map(|&class| {
+                self.rng
+                    .gamma(records.get(class).copied().unwrap_or(0.0) + 1.0)
+            })
+            .collect();
+        let total: f64 = draws.iter().sum();
+        draws.iter().map(|draw| (draw / total).ln()).collect()
+    }
+
+    fn sample_log_likelihood(&mut self, class: &str, psymptoms: &Features) -> f64 {
+        let classifier = self.classifier;
+        let config = classifier.config();
+        let stats = classifier.stats();
+        let alpha = config.smoothing;
+
+        let mut log_likelihood = match config.model_kind {
+            // Only the betas of the record's symptoms matter, and the rest of a Dirichlet sample
+            // adds up to a single Gamma draw:
+            ModelKind::Multinomial => {
+                let counts = stats.symptom_counts.get(class);
+                let mut rest = self.totals[class];
+                let mut draws = Vec::with_capacity(psymptoms.counts.len());
+                for (symptom, &count) in &psymptoms.counts {
+                    if !self.known.contains(symptom.as_str()) {
+                        continue;
+                    }
+                    let concentration = counts
+                        .and_then(|c| c.get(symptom.as_str()))
+                        .copied()
+                        .unwrap_or(0.0)
+                        + alpha;
+                    rest -= concentration;
+                    draws.push((count, self.rng.gamma(concentration)));
+                }
+                let total = draws.iter().map(|&(_, draw)| draw).sum::<f64>()
+                    + self.rng.gamma(rest.max(0.0));
+                draws
+                    .iter()
+                    .map(|&(count, draw)| count * (draw / total).ln())
+                    .sum()
+            }
+            // Every beta counts, for present and absent symptoms alike:
+            ModelKind::Bernoulli => {
+                let num_records = stats.records.get(class).copied().unwrap_or(0.0);
+                let with_symptom = stats.symptom_records.get(class);
+                let mut log_likelihood = 0.0;
+                for &symptom in &self.vocabulary {
+                    let present = with_symptom
+                        .and_then(|c| c.get(symptom))
+                        .copied()
+                        .unwrap_or(0.0);
+                    let a = self.rng.gamma(present + alpha);
+                    let b = self.rng.gamma(num_records - present + alpha);
+                    let beta = a / (a + b);
+                    let count = psymptoms.counts.get(symptom).copied().unwrap_or(0.0);
+                    log_likelihood += if count > 0.0 {
+                        beta.ln()
+                    } else {
+                        (1.0 - beta).ln()
+                    };
+                }
+                log_likelihood
+            }
+        };
+
+        // A value's probability is Beta distributed (it against all of the other values):
+        for (column, value) in &psymptoms.categories {
+            let Some(diseases) = stats.categories.get(column.as_str()) else {
+                continue;
+            };
+            let num_values = diseases
+                .values()
+                .flat_map(|counts| counts.keys())
+                .collect::<HashSet<_>>()
+                .len() as f64;
+            let counts = diseases.get(class);
+            let total: f64 = counts.map_or(0.0, |counts| counts.values().sum());
+            if !diseases
+                .values()
+                .any(|counts| counts.contains_key(value.as_str()))
+            {
+                continue;
+            }
+            let count = counts
+                .and_then(|c| c.get(value.as_str()))
+                .copied()
+                .unwrap_or(0.0);
+            let a = self.rng.gamma(count + alpha);
+            let b = self.rng.gamma(total - count + alpha * (num_values - 1.0));
+            log_likelihood += (a / (a + b)).ln();
+        }
+
+        for (column, &x) in &psymptoms.numbers {
+            log_likelihood += classifier
+                .numeric_log_density(class, column, x)
+                .unwrap_or(0.0);
+        }
+        log_likelihood
+    }
+}
+
+/// The (linearly interpolated) quantile of sorted samples:
+fn quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = q * (sorted.len() - 1) as f64;
+    let (below, above) = (position.floor() as usize, position.ceil() as usize);
+    let fraction = position - below as f64;
+    sorted[below] * (1.0 - fraction) + sorted[above] * fraction
+}
@@ -0,0 +1,52 @@
+pub mod bayes;
+pub mod builder;
+pub mod cache;
+pub mod calibration;
+pub mod categorical;
+pub mod checkpoint;
+#[cfg(feature = "arrow")]
+pub mod columnar;
+pub mod compress;
+pub mod config;
+pub mod cost;
+pub mod diagnostics;
+pub mod dialect;
+pub mod diff;
+pub mod drift;
+pub mod ensemble;
+pub mod error;
+pub mod evaluate;
+pub mod explain;
+pub mod export;
+pub mod features;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+pub mod format;
+pub mod gaussian;
+pub mod generate;
+pub mod glob;
+pub mod hashing;
+pub mod hierarchy;
+pub mod interaction;
+pub mod interval;
+#[cfg(feature = "linfa")]
+pub mod linfa_compat;
+pub mod missing;
+pub mod multilabel;
+pub mod normalize;
+pub mod progress;
+pub mod quality;
+pub mod quantize;
+pub mod rng;
+pub mod schema;
+pub mod selection;
+pub mod self_training;
+#[cfg(feature = "server")]
+pub mod server;
+pub mod spreadsheet;
+pub mod stats;
+pub mod symbols;
+pub mod text;
+pub mod tune;
+#[cfg(feature = "wasm")]
+pub mod wasm;
@@ -0,0 +1,117 @@
+//! linfa `Fit` and `Predict` implementations so the classifier can be used in linfa pipelines
+//! (cross validation, benchmarks, ...). Every column of the records is a symptom whose value is
+//! how many times it occurs, 0 when it's absent.
+
+use crate::{
+    bayes::NaiveBayesClassifier,
+    config::Config,
+    error::{BayesError, Result},
+    features::Features,
+    symbols::Symbol,
+};
+use linfa::{
+    dataset::{AsSingleTargets, DatasetBase, Label},
+    traits::{Fit, PredictInplace},
+};
+use ndarray::{Array1, ArrayBase, ArrayView1, Data, Ix2};
+use std::collections::BTreeMap;
+
+/// The parameters a classifier is fitted with.
+#[derive(Clone, Debug, Default)]
+pub struct NaiveBayesParams {
+    pub config: Config,
+}
+
+impl NaiveBayesParams {
+    pub fn new(config: Config) -> Self {
+        NaiveBayesParams { config }
+    }
+}
+
+/// A classifier fitted on a linfa dataset, predicting the dataset's labels.
+pub struct FittedNaiveBayes<L> {
+    classifier: NaiveBayesClassifier,
+    labels: BTreeMap<String, L>,
+    feature_names: Vec<String>,
+}
+
+impl<L> FittedNaiveBayes<L> {
+    pub fn classifier(&self) -> &NaiveBayesClassifier {
+        &self.classifier
+    }
+}
+
+impl<D, T, L> Fit<ArrayBase<D, Ix2>, T, BayesError> for NaiveBayesParams
+where
+    D: Data<Elem = f64>,
+    T: AsSingleTargets<Elem = L>,
+    L: Label + ToString,
+{
+    type Object = FittedNaiveBayes<L>;
+
+    fn fit(&self, dataset: &DatasetBase<ArrayBase<D, Ix2>, T>) -> Result<Self::Object> {
+        // Datasets without feature names get x0, x1, ... instead:
+        let records = dataset.records();
+        let feature_names = if dataset.feature_names().len() == records.ncols() {
+            dataset.feature_names().to_vec()
+        } else {
+            (0..records.ncols()).map(|i| format!("x{}", i)).collect()
+        };
+
+        // The classifier only knows diseases by name:
+        let mut labels = BTreeMap::new();
+        let mut training = Vec::with_capacity(records.nrows());
+        for (row, label) in records.rows().into_iter().zip(dataset.as_single_targets()) {
+            labels
+                .entry(label.to_string())
+                .or_insert_with(|| label.clone());
+            training.push((label.to_string(), row_features(row, &feature_names)));
+        }
+
+        Ok(FittedNaiveBayes {
+            classifier: NaiveBayesClassifier::fit(training, self.config.clone())?,
+            labels,
+            feature_names,
+        })
+    }
+}
+
+impl<D, L> PredictInplace<ArrayBase<D, Ix2>, Array1<L>> for FittedNaiveBayes<L>
+where
+    D: Data<Elem = f64>,
+    L: Label,
+{
+    fn predict_inplace(&self, x: &ArrayBase<D, Ix2>, y: &mut Array1<L>) {
+        assert_eq!(
+            x.nrows(),
+            y.len(),
+            "The number of data points must match the number of output targets."
+        );
+        assert_eq!(
+            x.ncols(),
+            self.feature_names.len(),
+            "The number of features must match the number the classifier was fitted on."
+        );
+
+        for (row, target) in x.rows().into_iter().zip(y.iter_mut()) {
+            let features = row_features(row, &self.feature_names);
+            if let Some(&(disease, _)) = self.classifier.predict_top_k(&features, 1).first() {
+                *target = self.labels[disease].clone();
+            }
+        }
+    }
+
+    fn default_target(&self, x: &ArrayBase<D, Ix2>) -> Array1<L> {
+        Array1::default(x.nrows())
+    }
+}
+
+fn row_features(row: ArrayView1<f64>, feature_names: &[String]) -> Features {
+    let mut features = Features::default();
+    for (name, &count) in feature_names.iter().zip(row) {
+        if count != 0.0 && count.is_finite() {
+            features.counts.insert(Symbol::new(name), count);
+        }
+    }
+    features
+}
@@ -1,10 +1,979 @@
-mod bayes;
-
 use anyhow::Result;
+use clap::{Args, Parser, Subcommand};
+use cs145_bayes::{
+    bayes::NaiveBayesClassifier,
+    calibration::CalibrationMethod,
+    checkpoint::CheckpointConfig,
+    config::{Config, IdColumn, ModelKind, ParseMode, Priors},
+    cost::CostMatrix,
+    dialect::{CsvDialect, Delimiter, Encoding},
+    diff,
+    ensemble::{Aggregation, BaggingConfig, EnsembleClassifier},
+    evaluate, export,
+    format::{self, Format, ParseReport, PredictOutputConfig},
+    generate::GenerativeModel,
+    glob,
+    hashing::FeatureHasher,
+    hierarchy::{HierarchicalClassifier, Hierarchy},
+    interval::{IntervalConfig, PosteriorSampler},
+    multilabel::MultiLabelClassifier,
+    normalize::Normalizer,
+    progress::{Progress, ProgressCallback, Stage},
+    quality::DataQualityReport,
+    quantize::{Precision, QuantizationReport, QuantizedClassifier},
+    schema::CsvSchema,
+    selection::{FeatureScore, FeatureSelection},
+    self_training::SelfTraining,
+    server,
+    spreadsheet::SheetConfig,
+    text::Tokenizer,
+    tune::{self, Grid},
+};
+use indicatif::{ProgressBar, ProgressStyle};
+use regex::Regex;
+use std::{collections::HashSet, io, iter, path::PathBuf, sync::Mutex};
+
+#[derive(Parser)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+    /// Log what's going on (record counts, classes, vocabulary size) to stderr.
+    #[arg(long, short, global = true)]
+    verbose: bool,
+}
+
+/// Draws a spinner with the number of records processed on stderr (when it's a terminal).
+struct ProgressBars(Mutex<ProgressBar>);
+
+impl ProgressCallback for ProgressBars {
+    fn start(&self, stage: Stage) {
+        let bar = ProgressBar::new_spinner().with_style(
+            ProgressStyle::with_template("{spinner} {msg}: {pos} records ({per_sec})").unwrap(),
+        );
+        bar.set_message(match stage {
+            Stage::Training => "training",
+            Stage::Predicting => "predicting",
+        });
+        *self.0.lock().unwrap() = bar;
+    }
+
+    fn record(&self, _stage: Stage, count: usize) {
+        self.0.lock().unwrap().set_position(count as u64);
+    }
+
+    fn finish(&self, _stage: Stage, _count: usize) {
+        self.0.lock().unwrap().finish_and_clear();
+    }
+}
+
+/// Options shared by every command that trains a classifier.
+#[derive(Args)]
+struct TrainArgs {
+    /// The csv file to train on, or a glob pattern (e.g. "shards/*.csv") of files to train on as
+    /// if they were one.
+    train: PathBuf,
+    /// Another csv file (or glob pattern) to train on along with the first, can be repeated.
+    #[arg(long = "train-file")]
+    train_files: Vec<PathBuf>,
+    /// How symptoms are modeled (multinomial or bernoulli).
+    #[arg(long, default_value = "multinomial")]
+    model: ModelKind,
+    /// Additive smoothing added to every symptom and category count.
+    #[arg(long, default_value_t = Config::default().smoothing)]
+    smoothing: f64,
+    /// Drop symptoms seen fewer than this many times during training.
+    #[arg(long, default_value_t = Config::default().min_feature_count)]
+    min_feature_count: usize,
+    /// Only keep this many of the most frequent symptoms.
+    #[arg(long)]
+    max_features: Option<usize>,
+    /// Pool dropped and unknown symptoms into a single other symptom.
+    #[arg(long)]
+    pool_rare: bool,
+    /// Disease priors: "empirical", "uniform", or a csv file of disease,weight rows.
+    #[arg(long, default_value = "empirical")]
+    priors: String,
+    /// Name of a training csv column holding per-record sample weights.
+    #[arg(long)]
+    weight_column: Option<String>,
+    /// A csv file of column,type[,missing] rows declaring categorical and numeric columns, and
+    /// how their missing values are handled (skip, impute, or category).
+    #[arg(long)]
+    schema: Option<PathBuf>,
+    /// Only train on this many of the most informative symptoms.
+    #[arg(long)]
+    select_features: Option<usize>,
+    /// How symptoms are scored for selection (mi or chi2).
+    #[arg(long, default_value = "mi")]
+    selection_score: FeatureScore,
+    /// Treat every field as raw text that is split into tokens instead of as a symptom.
+    #[arg(long)]
+    text: bool,
+    /// Regex whose matches are the tokens in text mode (default: split on whitespace).
+    #[arg(long, requires = "text")]
+    token_pattern: Option<String>,
+    /// Keep the case of text in text mode.
+    #[arg(long, requires = "text")]
+    keep_case: bool,
+    /// Drop common english words in text mode.
+    #[arg(long, requires = "text")]
+    stop_words: bool,
+    /// Also use n-grams of tokens up to this length in text mode.
+    #[arg(long, requires = "text", default_value_t = 1)]
+    ngrams: usize,
+    /// Normalize symptom names (trim, lowercase, and unicode NFKC) before training and predicting.
+    #[arg(long)]
+    normalize: bool,
+    /// A csv file of alias,name rows mapping symptom synonyms onto one name (implies --normalize).
+    #[arg(long)]
+    synonyms: Option<PathBuf>,
+    /// Hash symptom (and token) names into this many buckets, which bounds the number of
+    /// features however many symptoms there are.
+    #[arg(long)]
+    hash_buckets: Option<usize>,
+    /// The sheet that records are read from in workbook (e.g. xlsx) files, the first otherwise.
+    #[arg(long)]
+    sheet: Option<String>,
+    /// The (1 based) row of the column headers in workbook files.
+    #[arg(long, default_value_t = SheetConfig::default().header_row)]
+    header_row: usize,
+    /// The encoding of csv input files (utf8 or windows-1252, a.k.a. latin1). A byte order mark
+    /// overrides it.
+    #[arg(long, default_value = "utf8")]
+    encoding: Encoding,
+    /// Field delimiter of csv input files: a single character, "tab", "semicolon", or "auto" to
+    /// sniff it from each file's first rows.
+    #[arg(long, default_value = ",")]
+    input_delimiter: Delimiter,
+    /// Skip malformed rows of the input files (reporting them) instead of failing on the first.
+    #[arg(long)]
+    lenient: bool,
+    /// Add up to this many pairs of symptoms that depend on each other the most (given the
+    /// disease) as features of their own.
+    #[arg(long)]
+    feature_pairs: Option<usize>,
+    /// Re-weight feature counts by TF-IDF.
+    #[arg(long)]
+    tf_idf: bool,
+    /// Skip looking up symptoms whose log likelihood differs by at most this much between the
+    /// diseases for every disease when predicting. 0 only skips those that can't tell the
+    /// diseases apart, which doesn't change predictions.
+    #[arg(long)]
+    prune_uninformative: Option<f64>,
+    /// Seed of everything random: shuffled cross validation folds, bootstrap samples of
+    /// --bagging, self-training pools, and posterior samples of --intervals.
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Fail rather than use more than about this much memory while training, e.g. 512M or 4G.
+    #[arg(long, value_parser = parse_bytes)]
+    memory_limit: Option<usize>,
+    /// A file of unlabeled records (csv or jsonl) to self-train on: the records the classifier is
+    /// confident about are labeled with its predictions and trained on, round after round.
+    #[arg(long)]
+    unlabeled: Option<PathBuf>,
+    /// The most rounds of self-training.
+    #[arg(long, requires = "unlabeled", default_value_t = SelfTraining::default().rounds)]
+    self_training_rounds: usize,
+    /// The probability a prediction needs to become a pseudo-label.
+    #[arg(long, requires = "unlabeled", default_value_t = SelfTraining::default().threshold)]
+    pseudo_threshold: f64,
+    /// What every pseudo-labeled record counts as, compared to a labeled record.
+    #[arg(long, requires = "unlabeled", default_value_t = SelfTraining::default().weight)]
+    pseudo_weight: f64,
+    /// Only consider a random sample of this many unlabeled records every self-training round.
+    #[arg(long, requires = "unlabeled")]
+    pseudo_pool_size: Option<usize>,
+    /// Before training, report duplicate records, records with the same features but different
+    /// diseases, and rare diseases.
+    #[arg(long)]
+    check_quality: bool,
+    /// Diseases with fewer training records than this are reported as rare.
+    #[arg(long, requires = "check_quality", default_value_t = 5)]
+    min_class_records: usize,
+    /// Save the counts so far to this file every so often while training, so that training can
+    /// be resumed if it's interrupted.
+    #[arg(long, conflicts_with = "unlabeled")]
+    checkpoint: Option<PathBuf>,
+    /// Number of training rows read between checkpoints.
+    #[arg(long, requires = "checkpoint", default_value_t = CheckpointConfig::default().every)]
+    checkpoint_every: u64,
+    /// Continue from the checkpoint (if there is one) instead of starting over.
+    #[arg(long, requires = "checkpoint")]
+    resume: bool,
+}
+
+/// Parses a number of bytes with an optional K, M, or G (binary) suffix:
+fn parse_bytes(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let upper = s.to_ascii_uppercase();
+    let digits = upper.trim_end_matches("IB").trim_end_matches('B');
+    let (digits, unit) = match digits.chars().last() {
+        Some('K') => (&digits[..digits.len() - 1], 1 << 10),
+        Some('M') => (&digits[..digits.len() - 1], 1 << 20),
+        Some('G') => (&digits[..digits.len() - 1], 1 << 30),
+        _ => (digits, 1),
+    };
+    digits
+        .trim()
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(unit))
+        .ok_or_else(|| format!("invalid number of bytes: {}", s))
+}
+
+impl TrainArgs {
+    /// Every training file the arguments name:
+    fn paths(&self) -> Result<Vec<PathBuf>> {
+        Ok(glob::glob_all(
+            iter::once(&self.train).chain(&self.train_files),
+        )?)
+    }
+
+    /// The training file of what only trains on a single file:
+    fn path(&self) -> Result<PathBuf> {
+        match self.paths()?.as_slice() {
+            [path] => Ok(path.clone()),
+            _ => anyhow::bail!("only plain training reads several training files"),
+        }
+    }
+
+    fn config(&self) -> Result<Config> {
+        let priors = match self.priors.as_str() {
+            "empirical" => Priors::Empirical,
+            "uniform" => Priors::Uniform,
+            path => Priors::from_csv(path)?,
+        };
+
+        let tokenizer = if self.text {
+            Some(Tokenizer {
+                pattern: self.token_pattern.as_deref().map(Regex::new).transpose()?,
+                lowercase: !self.keep_case,
+                stop_words: if self.stop_words {
+                    Tokenizer::english_stop_words()
+                } else {
+                    HashSet::new()
+                },
+                ngrams: self.ngrams,
+            })
+        } else {
+            None
+        };
+
+        let schema = match &self.schema {
+            Some(path) => CsvSchema::from_csv(path)?,
+            None => CsvSchema::new(),
+        };
+
+        let normalizer = match &self.synonyms {
+            Some(path) => Some(Normalizer::default().with_synonyms_csv(path)?),
+            None if self.normalize => Some(Normalizer::default()),
+            None => None,
+        };
+
+        Ok(Config {
+            model_kind: self.model,
+            smoothing: self.smoothing,
+            min_feature_count: self.min_feature_count,
+            max_features: self.max_features,
+            pool_rare_features: self.pool_rare,
+            priors,
+            schema,
+            tokenizer,
+            normalizer,
+            feature_pairs: self.feature_pairs,
+            tf_idf: self.tf_idf,
+            prune_uninformative: self.prune_uninformative,
+            feature_hasher: self.hash_buckets.map(FeatureHasher::new),
+            memory_limit: self.memory_limit,
+            seed: self.seed,
+            sheet: SheetConfig {
+                name: self.sheet.clone(),
+                header_row: self.header_row,
+            },
+            dialect: CsvDialect {
+                encoding: self.encoding,
+                delimiter: self.input_delimiter,
+            },
+            parse_mode: if self.lenient {
+                ParseMode::Lenient
+            } else {
+                ParseMode::Strict
+            },
+            weight_column: self.weight_column.clone(),
+            feature_selection: self.select_features.map(|keep| FeatureSelection {
+                score: self.selection_score,
+                keep,
+            }),
+            progress: Some(Progress::new(ProgressBars(Mutex::new(
+                ProgressBar::hidden(),
+            )))),
+            ..Config::default()
+        })
+    }
+}
+
+/// Options controlling how predictions are made.
+#[derive(Args)]
+struct PredictArgs {
+    /// Memoize predictions of records with identical symptoms.
+    #[arg(long)]
+    cache: bool,
+    /// Maximum number of cached predictions.
+    #[arg(long, default_value_t = Config::default().cache_capacity)]
+    cache_capacity: usize,
+    /// Abstain when the top disease's probability is below this.
+    #[arg(long, default_value_t = Config::default().min_confidence)]
+    min_confidence: f64,
+    /// Abstain when the top disease's probability beats the runner up by less than this.
+    #[arg(long, default_value_t = Config::default().min_margin)]
+    min_margin: f64,
+    /// Label written for records the classifier abstains on.
+    #[arg(long, default_value_t = Config::default().unknown_label)]
+    unknown_label: String,
+    /// Also write the probability of every predicted disease.
+    #[arg(long)]
+    probability: bool,
+    /// Also write this many of the next most likely diseases, with their probabilities.
+    #[arg(long, default_value_t = 0)]
+    alternatives: usize,
+    /// Field delimiter of csv predictions: a single character, "tab", or "semicolon".
+    #[arg(long, default_value = ",", value_parser = parse_delimiter)]
+    delimiter: u8,
+    /// Also write the features of every record.
+    #[arg(long)]
+    echo_features: bool,
+    /// A csv matrix of misclassification costs (actual diseases down, predicted diseases
+    /// across): predict the disease with the lowest expected cost.
+    #[arg(long)]
+    costs: Option<PathBuf>,
+    /// Name (or 0 based csv index) of the input column whose IDs are written with the
+    /// predictions, records are numbered from 1 otherwise.
+    #[arg(long)]
+    id_column: Option<IdColumn>,
+}
+
+impl PredictArgs {
+    fn apply(&self, config: Config) -> Result<Config> {
+        let costs = match &self.costs {
+            Some(path) => Some(CostMatrix::from_csv(path)?),
+            None => None,
+        };
+        Ok(Config {
+            cache_predictions: self.cache,
+            cache_capacity: self.cache_capacity,
+            min_confidence: self.min_confidence,
+            min_margin: self.min_margin,
+            unknown_label: self.unknown_label.clone(),
+            id_column: self.id_column.clone(),
+            output: PredictOutputConfig {
+                probability: self.probability,
+                alternatives: self.alternatives,
+                delimiter: self.delimiter,
+                echo_features: self.echo_features,
+            },
+            costs,
+            ..config
+        })
+    }
+}
+
+/// Parses a csv delimiter, which has to be a single byte:
+fn parse_delimiter(s: &str) -> Result<u8, String> {
+    match s.to_ascii_lowercase().as_str() {
+        "tab" | "\\t" | "tsv" => Ok(b'\t'),
+        "semicolon" => Ok(b';'),
+        "comma" => Ok(b','),
+        _ if s.len() == 1 => Ok(s.as_bytes()[0]),
+        _ => Err(format!("invalid delimiter: {}", s)),
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Trains on a csv file and predicts the diseases of the records in another file.
+    Predict {
+        #[command(flatten)]
+        training: TrainArgs,
+        #[arg(required_unless_present = "stdin")]
+        input: Option<PathBuf>,
+        #[arg(required_unless_present = "stdin")]
+        output: Option<PathBuf>,
+        /// Format of the input and output (csv or jsonl), defaults to the file extension.
+        #[arg(long)]
+        format: Option<Format>,
+        /// Read records from stdin and write predictions to stdout as they arrive.
+        #[arg(long, conflicts_with_all = ["input", "output"])]
+        stdin: bool,
+        /// Predict with an ensemble of this many models trained on bootstrap samples.
+        #[arg(long, conflicts_with_all = ["stdin", "unlabeled", "checkpoint"])]
+        bagging: Option<usize>,
+        /// How the ensemble's predictions are combined (mean of log posteriors, or vote).
+        #[arg(long, requires = "bagging", default_value = "mean")]
+        aggregation: Aggregation,
+        /// Train a classifier per disease on semicolon separated labels, and predict every
+        /// disease that's likely enough.
+        #[arg(long, conflicts_with_all = ["stdin", "bagging", "unlabeled", "checkpoint"])]
+        multi_label: bool,
+        /// The probability a disease needs to be predicted in multi-label mode.
+        #[arg(long, requires = "multi_label", default_value_t = 0.5)]
+        label_threshold: f64,
+        /// Print how much every feature of each record favors the predicted disease over the
+        /// runner up.
+        #[arg(long, conflicts_with_all = ["stdin", "bagging", "multi_label"])]
+        explain: bool,
+        /// Print credible intervals of the probabilities of the most likely diseases of each
+        /// record, from this many classifiers drawn from the posterior of the training counts.
+        #[arg(long, conflicts_with_all = ["stdin", "bagging", "multi_label"])]
+        intervals: Option<usize>,
+        /// Write the log scores of the top two diseases of each record, their margin, and the
+        /// numbers of unseen and used features to this csv file.
+        #[arg(long, conflicts_with_all = ["stdin", "bagging", "multi_label"])]
+        diagnostics: Option<PathBuf>,
+        /// A csv file of disease,category rows: predict the category of every record first, and
+        /// then the disease within it.
+        #[arg(long, conflicts_with_all = ["stdin", "bagging", "multi_label", "explain", "intervals", "diagnostics", "unlabeled", "checkpoint"])]
+        hierarchy: Option<PathBuf>,
+        /// The probability mass inside every credible interval.
+        #[arg(long, requires = "intervals", default_value_t = IntervalConfig::default().level)]
+        level: f64,
+        #[command(flatten)]
+        prediction: PredictArgs,
+    },
+    /// Trains on a csv file and saves the model as json (compressed according to its extension).
+    Train {
+        #[command(flatten)]
+        training: TrainArgs,
+        output: PathBuf,
+    },
+    /// Quantizes the log probabilities of a saved model to 16 bits and saves it in a compact
+    /// binary format.
+    Quantize {
+        model: PathBuf,
+        output: PathBuf,
+        /// How the log probabilities are stored (f16, or i16 with a shared scale).
+        #[arg(long, default_value = "i16")]
+        precision: Precision,
+        /// A labeled csv file to compare the predictions of the quantized and full precision
+        /// models on.
+        #[arg(long)]
+        compare: Option<PathBuf>,
+    },
+    /// Compares two saved models: added and removed classes and symptoms, changed priors, and
+    /// symptom likelihoods that moved.
+    Diff {
+        old: PathBuf,
+        new: PathBuf,
+        /// Report likelihoods whose log probability moved by more than this.
+        #[arg(long, default_value_t = diff::LIKELIHOOD_THRESHOLD)]
+        threshold: f64,
+    },
+    /// Trains a saved model further on the records of another csv file and saves it again.
+    Update {
+        model: PathBuf,
+        train: PathBuf,
+        /// Where the updated model is saved, it's overwritten otherwise.
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Loads a saved model (or trains one on a csv file) and serves its predictions over http.
+    Serve {
+        /// The model to serve, as saved by the train command.
+        #[arg(long, required_unless_present = "train", conflicts_with = "train")]
+        model: Option<PathBuf>,
+        /// A csv file to train a model on with the default options instead of loading one.
+        #[arg(long)]
+        train: Option<PathBuf>,
+        /// Address to listen on.
+        #[arg(long, default_value = "127.0.0.1:8080")]
+        addr: String,
+        /// Number of diseases returned when a request doesn't specify `k`.
+        #[arg(long, default_value_t = 3)]
+        top_k: usize,
+    },
+    /// Trains on a csv file and prints the most likely symptoms of every disease.
+    Inspect {
+        #[command(flatten)]
+        training: TrainArgs,
+        /// Number of symptoms listed per disease.
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+    /// Trains on a csv file and reports how well it predicts another labeled csv file.
+    Evaluate {
+        #[command(flatten)]
+        training: TrainArgs,
+        test: PathBuf,
+        /// A labeled holdout csv file to calibrate the probabilities on first.
+        #[arg(long)]
+        calibrate: Option<PathBuf>,
+        /// How the probabilities are calibrated (platt or isotonic).
+        #[arg(long, default_value = "platt")]
+        calibration: CalibrationMethod,
+        /// Number of bins in the reliability diagram.
+        #[arg(long, default_value_t = 10)]
+        bins: usize,
+        /// Write the points of every class's ROC and precision-recall curves to this csv file.
+        #[arg(long, conflicts_with = "hierarchy")]
+        curves: Option<PathBuf>,
+        /// A csv file of disease,category rows: train a hierarchical classifier and report its
+        /// accuracy at both levels.
+        #[arg(long, conflicts_with_all = ["calibrate", "unlabeled", "checkpoint"])]
+        hierarchy: Option<PathBuf>,
+    },
+    /// Trains on a csv file and reports how much the features and classes of the records in
+    /// another file have shifted since.
+    Drift {
+        #[command(flatten)]
+        training: TrainArgs,
+        input: PathBuf,
+        /// Format of the input (csv or jsonl), defaults to the file extension.
+        #[arg(long)]
+        format: Option<Format>,
+    },
+    /// Cross validates every combination of the given hyperparameters and reports the best.
+    /// Hyperparameters without a list of values keep the training option's value.
+    Tune {
+        #[command(flatten)]
+        training: TrainArgs,
+        /// Comma separated smoothing values to try.
+        #[arg(long, value_delimiter = ',')]
+        alphas: Vec<f64>,
+        /// Comma separated models to try (multinomial, bernoulli).
+        #[arg(long, value_delimiter = ',')]
+        models: Vec<ModelKind>,
+        /// Comma separated minimum feature counts to try.
+        #[arg(long, value_delimiter = ',')]
+        min_feature_counts: Vec<usize>,
+        /// Number of cross validation folds.
+        #[arg(long, default_value_t = 5)]
+        folds: usize,
+    },
+    /// Writes a synthetic training csv file drawn from a generative model.
+    Generate {
+        output: PathBuf,
+        /// A json file with the "priors" of every disease, the probability of each of their
+        /// "symptoms", and the label "noise". A random model is used otherwise.
+        #[arg(long)]
+        model: Option<PathBuf>,
+        /// Number of diseases of a random model.
+        #[arg(long, default_value_t = 5, conflicts_with = "model")]
+        classes: usize,
+        /// Number of distinct symptoms of a random model.
+        #[arg(long, default_value_t = 50, conflicts_with = "model")]
+        vocabulary: usize,
+        /// Number of characteristic symptoms of every disease of a random model.
+        #[arg(long, default_value_t = 5, conflicts_with = "model")]
+        symptoms_per_class: usize,
+        /// Probability of a record's label being swapped for a random disease (overrides the
+        /// model's).
+        #[arg(long)]
+        noise: Option<f64>,
+        /// Number of records.
+        #[arg(long, default_value_t = 1000)]
+        records: usize,
+        #[arg(long, default_value_t = 0)]
+        seed: u64,
+    },
+    /// Trains on a csv file and exports the model as PMML.
+    Export {
+        #[command(flatten)]
+        training: TrainArgs,
+        output: PathBuf,
+    },
+}
+
+/// Trains on a csv file (self-training on the unlabeled records if there are any), reporting any
+/// rows that were skipped:
+fn train(training: &TrainArgs, config: Config) -> Result<NaiveBayesClassifier> {
+    check_quality(training, &config)?;
+    if let Some(path) = &training.checkpoint {
+        let checkpoints = CheckpointConfig {
+            path: path.clone(),
+            every: training.checkpoint_every,
+            resume: training.resume,
+        };
+        let classifier =
+            NaiveBayesClassifier::with_checkpoints(training.path()?, config, &checkpoints)?;
+        report_skipped(classifier.training_report());
+        return Ok(classifier);
+    }
+    let Some(unlabeled) = &training.unlabeled else {
+        let classifier = NaiveBayesClassifier::with_config_files(&training.paths()?, config)?;
+        report_skipped(classifier.training_report());
+        return Ok(classifier);
+    };
+
+    let mut labeled = Vec::new();
+    for path in training.paths()? {
+        let (records, report) = format::read_training_records(path, &config)?;
+        report_skipped(&report);
+        labeled.extend(records);
+    }
+    let (unlabeled, report) =
+        format::read_records(unlabeled, Format::from_path(unlabeled), &config)?;
+    report_skipped(&report);
+
+    let unlabeled: Vec<_> = unlabeled
+        .into_iter()
+        .map(|(_, features)| features)
+        .collect();
+    let self_training = SelfTraining {
+        rounds: training.self_training_rounds,
+        threshold: training.pseudo_threshold,
+        weight: training.pseudo_weight,
+        pool_size: training.pseudo_pool_size,
+    };
+    Ok(self_training.fit(labeled, &unlabeled, config)?)
+}
+
+/// Reports the quality of the training records if asked to, on stderr like skipped rows:
+fn check_quality(training: &TrainArgs, config: &Config) -> Result<()> {
+    if !training.check_quality {
+        return Ok(());
+    }
+    // Malformed rows are reported by training itself:
+    let mut records = Vec::new();
+    for path in training.paths()? {
+        records.extend(format::read_training_records(path, config)?.0);
+    }
+    eprint!(
+        "{}",
+        DataQualityReport::compute(&records, training.min_class_records)
+    );
+    Ok(())
+}
+
+// On stderr, since stdout may hold predictions:
+fn report_skipped(report: &ParseReport) {
+    if !report.is_empty() {
+        eprint!("{}", report);
+    }
+}
 
 fn main() -> Result<()> {
-    let classifier = bayes::NaiveBayesClassifier::new("D:/Dev/cs145/train.csv")?;
-    classifier.predict("D:/Dev/cs145/test.csv", "D:/Dev/cs145/result4.csv")?;
+    let cli = Cli::parse();
+    env_logger::Builder::new()
+        .filter_level(if cli.verbose {
+            log::LevelFilter::Info
+        } else {
+            log::LevelFilter::Warn
+        })
+        .parse_default_env()
+        .init();
+
+    match cli.command {
+        Command::Predict {
+            training,
+            input,
+            output,
+            format,
+            stdin,
+            bagging,
+            aggregation,
+            multi_label,
+            label_threshold,
+            explain,
+            intervals,
+            diagnostics,
+            hierarchy,
+            level,
+            prediction,
+        } => {
+            let config = prediction.apply(training.config()?)?;
+            if multi_label {
+                check_quality(&training, &config)?;
+                let classifier =
+                    MultiLabelClassifier::with_config(training.path()?, config, label_threshold)?;
+                report_skipped(classifier.training_report());
+
+                // Both are present when not reading from stdin:
+                let (input, output) = (input.unwrap(), output.unwrap());
+                report_skipped(&classifier.predict(input, output, format)?);
+                println!("Done");
+                return Ok(());
+            }
+            if let Some(path) = hierarchy {
+                let hierarchy = Hierarchy::from_csv(path)?;
+                check_quality(&training, &config)?;
+                let classifier =
+                    HierarchicalClassifier::with_config(training.path()?, config, hierarchy)?;
+                report_skipped(classifier.training_report());
+
+                // Both are present when not reading from stdin:
+                let (input, output) = (input.unwrap(), output.unwrap());
+                report_skipped(&classifier.predict(input, output, format)?);
+                println!("Done");
+                return Ok(());
+            }
+            if let Some(num_models) = bagging {
+                let bagging = BaggingConfig {
+                    num_models,
+                    aggregation,
+                    // The config's seed takes the place of its own:
+                    ..BaggingConfig::default()
+                };
+                check_quality(&training, &config)?;
+                let ensemble = EnsembleClassifier::with_config(training.path()?, config, &bagging)?;
+                report_skipped(ensemble.training_report());
+
+                // Both are present when not reading from stdin:
+                let (input, output) = (input.unwrap(), output.unwrap());
+                report_skipped(&ensemble.predict(input, output, format)?);
+                println!("Done");
+                return Ok(());
+            }
+
+            let classifier = train(&training, config)?;
+            if stdin {
+                // Nothing else may be written to stdout here:
+                let format = format.unwrap_or(Format::Jsonl);
+                let report =
+                    classifier.predict_stream(io::stdin().lock(), io::stdout().lock(), format)?;
+                report_skipped(&report);
+                return Ok(());
+            }
+
+            // Both are present when not reading from stdin:
+            let (input, output) = (input.unwrap(), output.unwrap());
+            if explain || intervals.is_some() {
+                let informat = format.unwrap_or_else(|| Format::from_path(&input));
+                let (records, _) = format::read_records(&input, informat, classifier.config())?;
+                let mut sampler = match intervals {
+                    Some(samples) => Some(PosteriorSampler::new(
+                        &classifier,
+                        IntervalConfig {
+                            samples,
+                            level,
+                            ..IntervalConfig::default()
+                        },
+                    )?),
+                    None => None,
+                };
+                for (i, (id, features)) in records.iter().enumerate() {
+                    let id = id.clone().unwrap_or_else(|| (i + 1).to_string());
+                    if explain {
+                        println!("Record {}: {}", id, classifier.explain(features));
+                    }
+                    if let Some(sampler) = &mut sampler {
+                        let intervals: Vec<String> = sampler
+                            .intervals(features)
+                            .iter()
+                            .take(3)
+                            .map(|interval| interval.to_string())
+                            .collect();
+                        println!("Record {}: {}", id, intervals.join(", "));
+                    }
+                }
+            }
+            report_skipped(&classifier.predict(&input, &output, format)?);
+            if let Some(path) = diagnostics {
+                // Malformed rows were already reported when predicting:
+                classifier.write_diagnostics(&input, &path, format)?;
+            }
+        }
+        Command::Train { training, output } => {
+            let classifier = train(&training, training.config()?)?;
+            classifier.save(output)?;
+        }
+        Command::Quantize {
+            model,
+            output,
+            precision,
+            compare,
+        } => {
+            let classifier = NaiveBayesClassifier::load(&model)?;
+            QuantizedClassifier::quantize(&classifier, precision)?.save(&output)?;
+
+            if let Some(path) = compare {
+                // Compare with what was actually written:
+                let quantized = QuantizedClassifier::load(&output)?;
+                let (records, report) = format::read_labeled_records(path, classifier.config())?;
+                report_skipped(&report);
+                print!(
+                    "{}",
+                    QuantizationReport::compare(&classifier, &quantized, &records)
+                );
+            }
+        }
+        Command::Diff {
+            old,
+            new,
+            threshold,
+        } => {
+            let old = NaiveBayesClassifier::load(&old)?;
+            let new = NaiveBayesClassifier::load(&new)?;
+            print!("{}", old.diff(&new, threshold));
+            return Ok(());
+        }
+        Command::Update {
+            model,
+            train,
+            output,
+        } => {
+            let mut classifier = NaiveBayesClassifier::load(&model)?;
+            report_skipped(&classifier.update_with_csv(train)?);
+            classifier.save(output.unwrap_or(model))?;
+        }
+        Command::Serve {
+            model,
+            train,
+            addr,
+            top_k,
+        } => {
+            let classifier = match (model, train) {
+                (Some(model), _) => NaiveBayesClassifier::load(model)?,
+                (None, Some(train)) => {
+                    let classifier = NaiveBayesClassifier::new(train)?;
+                    report_skipped(classifier.training_report());
+                    classifier
+                }
+                (None, None) => unreachable!("clap requires --model or --train"),
+            };
+            server::serve(&classifier, &addr, top_k)?;
+        }
+        Command::Inspect { training, top } => {
+            let classifier = train(&training, training.config()?)?;
+
+            println!(
+                "{} diseases, {} symptoms",
+                classifier.classes().len(),
+                classifier.features().len()
+            );
+            for class in classifier.classes() {
+                let prior = classifier.class_log_prior(class).unwrap();
+                println!("\n{} (log prior {:.4}):", class, prior);
+                for (feature, log_prob) in classifier.top_features(class, top) {
+                    println!("  {:>10.4}  {}", log_prob, feature);
+                }
+            }
+
+            if let Some(ranking) = classifier.feature_ranking() {
+                println!("\nMost informative symptoms:");
+                for (feature, score) in ranking.iter().take(top) {
+                    println!("  {:>10.4}  {}", score, feature);
+                }
+            }
+            return Ok(());
+        }
+        Command::Evaluate {
+            training,
+            test,
+            calibrate,
+            calibration,
+            bins,
+            curves,
+            hierarchy,
+        } => {
+            if let Some(path) = hierarchy {
+                let hierarchy = Hierarchy::from_csv(path)?;
+                let config = training.config()?;
+                check_quality(&training, &config)?;
+                let classifier =
+                    HierarchicalClassifier::with_config(training.path()?, config, hierarchy)?;
+                report_skipped(classifier.training_report());
+
+                let (records, report) = format::read_labeled_records(test, classifier.config())?;
+                report_skipped(&report);
+                print!("{}", classifier.evaluate(&records));
+                return Ok(());
+            }
+
+            let mut classifier = train(&training, training.config()?)?;
+            if let Some(holdout) = calibrate {
+                let (holdout, report) = format::read_labeled_records(holdout, classifier.config())?;
+                report_skipped(&report);
+                classifier.calibrate(&holdout, calibration)?;
+            }
+
+            let (records, report) = format::read_labeled_records(test, classifier.config())?;
+            report_skipped(&report);
+            let evaluation = evaluate::evaluate(&classifier, &records, bins);
+            print!("{}", evaluation);
+            if let Some(path) = curves {
+                evaluation.write_curves(path)?;
+            }
+            return Ok(());
+        }
+        Command::Drift {
+            training,
+            input,
+            format,
+        } => {
+            let classifier = train(&training, training.config()?)?;
+            let format = format.unwrap_or_else(|| Format::from_path(&input));
+            let (records, report) = format::read_records(&input, format, classifier.config())?;
+            report_skipped(&report);
+
+            let records: Vec<_> = records.into_iter().map(|(_, features)| features).collect();
+            print!("{}", classifier.drift_report(&records));
+            return Ok(());
+        }
+        Command::Tune {
+            training,
+            alphas,
+            models,
+            min_feature_counts,
+            folds,
+        } => {
+            if training.unlabeled.is_some() || training.checkpoint.is_some() {
+                anyhow::bail!("tune doesn't self-train or checkpoint");
+            }
+            // Progress bars from many threads would garble each other:
+            let config = Config {
+                progress: None,
+                ..training.config()?
+            };
+            let mut records = Vec::new();
+            for path in training.paths()? {
+                let (shard, report) = format::read_training_records(path, &config)?;
+                report_skipped(&report);
+                records.extend(shard);
+            }
+
+            let grid = Grid {
+                smoothing: alphas,
+                model_kinds: models,
+                min_feature_counts,
+            };
+            let results = tune::grid_search(&records, &config, &grid, folds)?;
+
+            println!("smoothing   model         min feature count  accuracy");
+            for result in &results {
+                println!("{}", result);
+            }
+            let best = &results[0];
+            println!(
+                "\nbest: --smoothing {} --model {} --min-feature-count {}",
+                best.smoothing,
+                format!("{:?}", best.model_kind).to_lowercase(),
+                best.min_feature_count
+            );
+            return Ok(());
+        }
+        Command::Generate {
+            output,
+            model,
+            classes,
+            vocabulary,
+            symptoms_per_class,
+            noise,
+            records,
+            seed,
+        } => {
+            let mut model = match model {
+                Some(path) => GenerativeModel::from_json(path)?,
+                None => GenerativeModel::random(classes, vocabulary, symptoms_per_class, 0.0, seed),
+            };
+            if let Some(noise) = noise {
+                model.noise = noise;
+            }
+            model.write_csv(output, records, seed)?;
+        }
+        Command::Export { training, output } => {
+            let classifier = train(&training, training.config()?)?;
+            export::export_pmml(&classifier, output)?;
+        }
+    }
 
     println!("Done");
 
@@ -0,0 +1,93 @@
+use crate::{
+    bayes::DiseaseRecords,
+    features::Features,
+    schema::{ColumnType, CsvSchema, MissingValue},
+    symbols::Symbol,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The category given to missing values of columns that treat missing as a category.
+pub const MISSING_CATEGORY: &str = "<missing>";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+enum Fill {
+    Category(Symbol),
+    Number(f64),
+}
+
+/// The values filled in for missing categorical and numeric columns, per the schema's policies.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct MissingValues {
+    fills: HashMap<Symbol, Fill>,
+}
+
+impl MissingValues {
+    /// Works out the fill value of every column that doesn't skip missing values, imputed columns
+    /// use the (weighted) most frequent category or mean of the training records:
+    pub fn fit(schema: &CsvSchema, diseases_map: &DiseaseRecords) -> Self {
+        let records = || diseases_map.values().flatten();
+
+        let mut fills = HashMap::new();
+        for (column, column_type, missing) in schema.columns() {
+            let fill = match (column_type, missing) {
+                (_, MissingValue::Skip) | (ColumnType::Symptom, _) => continue,
+                (_, MissingValue::Category) => Fill::Category(Symbol::new(MISSING_CATEGORY)),
+                (ColumnType::Categorical, MissingValue::Impute) => {
+                    let mut counts: HashMap<&str, f64> = HashMap::new();
+                    for (features, weight) in records() {
+                        if let Some(value) = features.categories.get(column) {
+                            *counts.entry(value.as_str()).or_default() += weight;
+                        }
+                    }
+                    // Break ties by name so it's the same every time:
+                    let most_frequent = counts
+                        .into_iter()
+                        .max_by(|(va, a), (vb, b)| a.total_cmp(b).then_with(|| vb.cmp(va)));
+                    match most_frequent {
+                        Some((value, _)) => Fill::Category(Symbol::new(value)),
+                        None => continue,
+                    }
+                }
+                (ColumnType::Numeric, MissingValue::Impute) => {
+                    let (mut sum, mut total) = (0.0, 0.0);
+                    for (features, weight) in records() {
+                        if let Some(x) = features.numbers.get(column) {
+                            sum += weight * x;
+                            total += weight;
+                        }
+                    }
+                    if total <= 0.0 {
+                        continue;
+                    }
+                    Fill::Number(sum / total)
+                }
+            };
+            fills.insert(Symbol::new(column), fill);
+        }
+
+        MissingValues { fills }
+    }
+
+    /// Fills in the columns the record has no value for:
+    pub fn fill(&self, features: &mut Features) {
+        for (column, fill) in &self.fills {
+            match fill {
+                Fill::Category(value) => {
+                    if !features.categories.contains_key(column) {
+                        features.categories.insert(column.clone(), value.clone());
+                    }
+                }
+                Fill::Number(x) => {
+                    if !features.numbers.contains_key(column) {
+                        features.numbers.insert(column.clone(), *x);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.fills.is_empty()
+    }
+}
@@ -0,0 +1,174 @@
+use crate::{
+    bayes::{BatchPrediction, NaiveBayesClassifier},
+    config::Config,
+    error::{BayesError, Result},
+    features::Features,
+    format::{self, Format, ParseReport},
+    progress::{Stage, Tracker},
+    symbols::Symbol,
+};
+use std::{collections::BTreeMap, path::Path};
+
+/// Separates the labels of a record that has more than one.
+pub const LABEL_SEPARATOR: char = ';';
+
+/// The label of the records that don't have a class in its one-vs-rest classifier.
+pub const REST_LABEL: &str = "<rest>";
+
+/// Predicts any number of diseases per record (e.g. comorbid ones) with an independent binary
+/// (one-vs-rest) classifier per disease.
+pub struct MultiLabelClassifier {
+    models: BTreeMap<String, NaiveBayesClassifier>,
+    threshold: f64,
+    training_report: ParseReport,
+}
+
+impl MultiLabelClassifier {
+    /// Trains on a csv file whose first column holds the labels of every record, separated by
+    /// semicolons. Records are predicted as every disease whose probability reaches `threshold`:
+    pub fn with_config<P: AsRef<Path>>(path: P, config: Config, threshold: f64) -> Result<Self> {
+        let (records, report) = format::read_training_records(path, &config)?;
+        let records = records.into_iter().map(|(labels, features, weight)| {
+            let labels = labels
+                .split(LABEL_SEPARATOR)
+                .map(|label| label.trim().to_string())
+                .filter(|label| !label.is_empty())
+                .collect();
+            (labels, features, weight)
+        });
+
+        let mut classifier = Self::fit_weighted(records, config, threshold)?;
+        classifier.training_report = report;
+        Ok(classifier)
+    }
+
+    /// Trains on (diseases, features, weight) records:
+    pub fn fit_weighted<I>(records: I, config: Config, threshold: f64) -> Result<Self>
+    where
+        I: IntoIterator<Item = (Vec<String>, Features, f64)>,
+    {
+        if threshold.is_nan() || !(0.0..=1.0).contains(&threshold) {
+            return Err(BayesError::InvalidConfig(format!(
+                "label threshold must be between 0 and 1: {}",
+                threshold
+            )));
+        }
+
+        let records: Vec<_> = records.into_iter().collect();
+        let mut classes: Vec<&str> = records
+            .iter()
+            .flat_map(|(labels, _, _)| labels.iter().map(|label| label.as_str()))
+            .collect();
+        classes.sort();
+        classes.dedup();
+        if classes.is_empty() {
+            return Err(BayesError::EmptyTrainingSet);
+        }
+
+        // Every class is told apart from all of the other records:
+        let mut models = BTreeMap::new();
+        for &class in &classes {
+            let binary = records.iter().map(|(labels, features, weight)| {
+                let label = if labels.iter().any(|label| label == class) {
+                    class
+                } else {
+                    REST_LABEL
+                };
+                (label, features.clone(), *weight)
+            });
+            let model = NaiveBayesClassifier::fit_weighted(binary, config.clone())?;
+            models.insert(class.to_string(), model);
+        }
+
+        Ok(MultiLabelClassifier {
+            models,
+            threshold,
+            training_report: ParseReport::default(),
+        })
+    }
+
+    /// The malformed rows skipped while reading the training file in lenient mode:
+    pub fn training_report(&self) -> &ParseReport {
+        &self.training_report
+    }
+
+    pub fn config(&self) -> &Config {
+        self.models.values().next().unwrap().config()
+    }
+
+    /// All of the diseases the classifier knows about (sorted):
+    pub fn classes(&self) -> Vec<&str> {
+        self.models.keys().map(|class| class.as_str()).collect()
+    }
+
+    /// The probability of every disease on its own, most likely first:
+    pub fn probabilities(&self, psymptoms: &Features) -> Vec<(&str, f64)> {
+        let mut probabilities: Vec<(&str, f64)> = self
+            .models
+            .iter()
+            .map(|(class, model)| {
+                let p = model
+                    .predict_top_k(psymptoms, usize::MAX)
+                    .into_iter()
+                    .find(|&(label, _)| label == class)
+                    .map_or(0.0, |(_, p)| p);
+                (class.as_str(), p)
+            })
+            .collect();
+        probabilities.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        probabilities
+    }
+
+    /// Every disease whose probability reaches the threshold, most likely first:
+    pub fn predict_labels(&self, psymptoms: &Features) -> Vec<(&str, f64)> {
+        let mut labels = self.probabilities(psymptoms);
+        labels.retain(|&(_, p)| p >= self.threshold);
+        labels
+    }
+
+    /// Predicts every record, returning the predictions in the same order. The diseases of a
+    /// prediction are joined by semicolons (the unknown label if there are none), and its score
+    /// is the probability of the most likely one:
+    pub fn predict_batch(&self, records: &[Features]) -> Vec<BatchPrediction> {
+        let config = self.config();
+        let mut tracker = Tracker::start(config, Stage::Predicting);
+        let results = records
+            .iter()
+            .enumerate()
+            .map(|(i, symptoms)| {
+                tracker.record();
+                let labels = self.predict_labels(symptoms);
+                let disease = if labels.is_empty() {
+                    Symbol::new(&config.unknown_label)
+                } else {
+                    let labels: Vec<&str> = labels.iter().map(|&(label, _)| label).collect();
+                    Symbol::new(&labels.join(&LABEL_SEPARATOR.to_string()))
+                };
+
+                BatchPrediction {
+                    id: (i + 1).to_string(),
+                    id_from_input: false,
+                    disease,
+                    score: labels.first().map_or(0.0, |&(_, p)| p),
+                    // There's no single disease to list alternatives to:
+                    probability: None,
+                    alternatives: Vec::new(),
+                }
+            })
+            .collect();
+        log::info!("predicted {} records", tracker.finish());
+        results
+    }
+
+    /// Predicts the records of a test file like `NaiveBayesClassifier::predict`:
+    pub fn predict<P: AsRef<Path>>(
+        &self,
+        inpath: P,
+        outpath: P,
+        format: Option<Format>,
+    ) -> Result<ParseReport> {
+        format::predict_file(inpath, outpath, format, self.config(), |records| {
+            self.predict_batch(records)
+        })
+    }
+}
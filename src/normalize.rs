@@ -0,0 +1,70 @@
+use crate::error::{BayesError, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path};
+use unicode_normalization::UnicodeNormalization;
+
+/// Normalizes symptom (and token) names so that e.g. "Fever", "fever " and "fevers" end up as
+/// the same feature. It's applied the same way when training and predicting.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Normalizer {
+    /// Whether names are lowercased.
+    pub lowercase: bool,
+    /// Whether names are put in unicode NFKC form (so e.g. full width letters match).
+    pub nfkc: bool,
+    /// Names (after the other steps) mapped to the name they are an alias of.
+    pub synonyms: HashMap<String, String>,
+}
+
+impl Default for Normalizer {
+    fn default() -> Self {
+        Normalizer {
+            lowercase: true,
+            nfkc: true,
+            synonyms: HashMap::new(),
+        }
+    }
+}
+
+impl Normalizer {
+    /// Reads synonyms from a csv file of alias,name rows. Both sides are normalized the same way
+    /// names are, so the file doesn't have to worry about case:
+    pub fn with_synonyms_csv<P: AsRef<Path>>(mut self, path: P) -> Result<Self> {
+        let mut reader = csv::Reader::from_path(path)?;
+
+        let mut synonyms = HashMap::new();
+        for record in reader.records() {
+            let record = record?;
+            let alias = record.get(0).ok_or_else(|| {
+                BayesError::parse(record.position(), None, "missing alias entry.")
+            })?;
+            let name = record
+                .get(1)
+                .ok_or_else(|| BayesError::parse(record.position(), None, "missing name entry."))?;
+            synonyms.insert(self.clean(alias), self.clean(name));
+        }
+
+        self.synonyms.extend(synonyms);
+        Ok(self)
+    }
+
+    /// Trims, folds, and resolves the alias of a name:
+    pub fn normalize(&self, name: &str) -> String {
+        let name = self.clean(name);
+        match self.synonyms.get(&name) {
+            Some(canonical) => canonical.clone(),
+            None => name,
+        }
+    }
+
+    fn clean(&self, name: &str) -> String {
+        let mut name = if self.nfkc {
+            name.nfkc().collect()
+        } else {
+            name.to_string()
+        };
+        if self.lowercase {
+            name = name.to_lowercase();
+        }
+        name.trim().to_string()
+    }
+}
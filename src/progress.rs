@@ -0,0 +1,79 @@
+use crate::config::Config;
+use std::{fmt, sync::Arc};
+
+/// The long running stages that report progress.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Stage {
+    /// Reading the training records.
+    Training,
+    /// Predicting records.
+    Predicting,
+}
+
+/// Receives progress reports as records are processed, e.g. to draw a progress bar. Closures
+/// taking the stage and the number of records processed so far are callbacks too.
+pub trait ProgressCallback: Send + Sync {
+    /// The stage started:
+    fn start(&self, _stage: Stage) {}
+    /// Another record was processed, `count` records have been processed in the stage so far:
+    fn record(&self, stage: Stage, count: usize);
+    /// The stage is done after processing `count` records:
+    fn finish(&self, _stage: Stage, _count: usize) {}
+}
+
+impl<F: Fn(Stage, usize) + Send + Sync> ProgressCallback for F {
+    fn record(&self, stage: Stage, count: usize) {
+        self(stage, count)
+    }
+}
+
+/// A shared progress callback that can be stored in the config.
+#[derive(Clone)]
+pub struct Progress(pub Arc<dyn ProgressCallback>);
+
+impl Progress {
+    pub fn new<C: ProgressCallback + 'static>(callback: C) -> Self {
+        Progress(Arc::new(callback))
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Progress")
+    }
+}
+
+/// Counts the records of a stage, reporting them to the configured callback (if any):
+pub(crate) struct Tracker<'a> {
+    progress: Option<&'a Progress>,
+    stage: Stage,
+    count: usize,
+}
+
+impl<'a> Tracker<'a> {
+    pub(crate) fn start(config: &'a Config, stage: Stage) -> Self {
+        let progress = config.progress.as_ref();
+        if let Some(progress) = progress {
+            progress.0.start(stage);
+        }
+        Tracker {
+            progress,
+            stage,
+            count: 0,
+        }
+    }
+
+    pub(crate) fn record(&mut self) {
+        self.count += 1;
+        if let Some(progress) = self.progress {
+            progress.0.record(self.stage, self.count);
+        }
+    }
+
+    pub(crate) fn finish(self) -> usize {
+        if let Some(progress) = self.progress {
+            progress.0.finish(self.stage, self.count);
+        }
+        self.count
+    }
+}
@@ -0,0 +1,217 @@
+use crate::{features::Features, format::TrainingRecord};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, HashMap},
+    fmt,
+};
+
+// How many groups of each kind are printed:
+const SHOWN: usize = 10;
+
+/// Training records that are exact copies of each other: the same disease, features, and
+/// weight.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Duplicate {
+    pub disease: String,
+    pub features: Features,
+    /// The (1 based) numbers of the records, counted across every training file in order.
+    pub records: Vec<usize>,
+}
+
+/// Training records with identical features but different diseases.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Conflict {
+    pub features: Features,
+    /// Every disease the features are labeled with and the (1 based) numbers of the records
+    /// labeling them with it.
+    pub diseases: BTreeMap<String, Vec<usize>>,
+}
+
+/// Problems with a training set that hurt the classifier without making training fail.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DataQualityReport {
+    pub num_records: usize,
+    /// Groups of duplicate records, the largest first.
+    pub duplicates: Vec<Duplicate>,
+    /// Groups of conflicting records, the largest first.
+    pub conflicts: Vec<Conflict>,
+    /// The threshold rare classes were picked with.
+    pub min_class_records: usize,
+    /// Diseases with fewer than `min_class_records` records, with their number of records, the
+    /// rarest first.
+    pub rare_classes: Vec<(String, usize)>,
+}
+
+impl DataQualityReport {
+    /// Checks the records for duplicates, conflicts, and diseases with fewer than
+    /// `min_class_records` records:
+    pub fn compute(records: &[TrainingRecord], min_class_records: usize) -> Self {
+        // Features aren't hashable (their counts are floats), their serialization (with sorted
+        // keys) is:
+        let key = |features: &Features| serde_json::to_string(features).unwrap_or_default();
+
+        let mut rows: HashMap<(String, &str, u64), Vec<usize>> = HashMap::new();
+        let mut labels: HashMap<String, BTreeMap<&str, Vec<usize>>> = HashMap::new();
+        let mut first: HashMap<String, &Features> = HashMap::new();
+        let mut classes: BTreeMap<&str, usize> = BTreeMap::new();
+        for (i, (disease, features, weight)) in records.iter().enumerate() {
+            let disease = disease.as_str();
+            let features_key = key(features);
+            rows.entry((features_key.clone(), disease, weight.to_bits()))
+                .or_default()
+                .push(i + 1);
+            labels
+                .entry(features_key.clone())
+                .or_default()
+                .entry(disease)
+                .or_default()
+                .push(i + 1);
+            first.entry(features_key).or_insert(features);
+            *classes.entry(disease).or_default() += 1;
+        }
+
+        let mut duplicates: Vec<Duplicate> = rows
+            .into_iter()
+            .filter(|(_, records)| records.len() > 1)
+            .map(|((features, disease, _), records)| Duplicate {
+                disease: disease.to_string(),
+                features: first[&features].clone(),
+                records,
+            })
+            .collect();
+        duplicates
+            .sort_by_key(|duplicate| (Reverse(duplicate.records.len()), duplicate.records[0]));
+
+        let mut conflicts: Vec<Conflict> = labels
+            .into_iter()
+            .filter(|(_, diseases)| diseases.len() > 1)
+            .map(|(features, diseases)| Conflict {
+                features: first[&features].clone(),
+                diseases: diseases
+                    .into_iter()
+                    .map(|(disease, records)| (disease.to_string(), records))
+                    .collect(),
+            })
+            .collect();
+        conflicts.sort_by_cached_key(|conflict| {
+            let records = conflict.diseases.values().flatten();
+            (Reverse(records.clone().count()), records.min().copied())
+        });
+
+        let mut rare_classes: Vec<(String, usize)> = classes
+            .into_iter()
+            .filter(|&(_, count)| count < min_class_records)
+            .map(|(disease, count)| (disease.to_string(), count))
+            .collect();
+        rare_classes.sort_by(|a, b| a.1.cmp(&b.1).then_with(|| a.0.cmp(&b.0)));
+
+        DataQualityReport {
+            num_records: records.len(),
+            duplicates,
+            conflicts,
+            min_class_records,
+            rare_classes,
+        }
+    }
+
+    /// The number of records that are copies of an earlier one:
+    pub fn num_duplicate_records(&self) -> usize {
+        self.duplicates
+            .iter()
+            .map(|duplicate| duplicate.records.len() - 1)
+            .sum()
+    }
+
+    /// Whether no problems were found:
+    pub fn is_clean(&self) -> bool {
+        self.duplicates.is_empty() && self.conflicts.is_empty() && self.rare_classes.is_empty()
+    }
+}
+
+impl fmt::Display for DataQualityReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "records:    {}", self.num_records)?;
+        writeln!(
+            f,
+            "duplicates: {} records in {} groups",
+            self.num_duplicate_records(),
+            self.duplicates.len()
+        )?;
+        writeln!(f, "conflicts:  {} groups", self.conflicts.len())?;
+        writeln!(
+            f,
+            "rare classes (fewer than {} records): {}",
+            self.min_class_records,
+            self.rare_classes.len()
+        )?;
+
+        if !self.duplicates.is_empty() {
+            writeln!(
+                f,
+                "\nlargest groups of duplicates (records: disease, features):"
+            )?;
+            for duplicate in self.duplicates.iter().take(SHOWN) {
+                writeln!(
+                    f,
+                    "  {}: {}, {}",
+                    join(&duplicate.records),
+                    duplicate.disease,
+                    describe(&duplicate.features)
+                )?;
+            }
+        }
+        if !self.conflicts.is_empty() {
+            writeln!(
+                f,
+                "\nlargest groups of conflicts (features: disease records):"
+            )?;
+            for conflict in self.conflicts.iter().take(SHOWN) {
+                writeln!(f, "  {}:", describe(&conflict.features))?;
+                for (disease, records) in &conflict.diseases {
+                    writeln!(f, "    {}: {}", disease, join(records))?;
+                }
+            }
+        }
+        if !self.rare_classes.is_empty() {
+            writeln!(f, "\nrare classes (records, disease):")?;
+            for (disease, records) in &self.rare_classes {
+                writeln!(f, "  {:>8}  {}", records, disease)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn join(records: &[usize]) -> String {
+    let shown: Vec<String> = records.iter().take(SHOWN).map(|r| r.to_string()).collect();
+    if records.len() > SHOWN {
+        format!("{}, ... ({} in all)", shown.join(", "), records.len())
+    } else {
+        shown.join(", ")
+    }
+}
+
+/// The features of a record on one line, e.g. `fever, cough, age=31, region=north`:
+fn describe(features: &Features) -> String {
+    let symptoms = features.counts.iter().map(|(symptom, &count)| {
+        if count == 1.0 {
+            symptom.to_string()
+        } else {
+            format!("{}x{}", symptom, count)
+        }
+    });
+    let categories = features
+        .categories
+        .iter()
+        .map(|(column, value)| format!("{}={}", column, value));
+    let numbers = features
+        .numbers
+        .iter()
+        .map(|(column, value)| format!("{}={}", column, value));
+    let described: Vec<String> = symptoms.chain(categories).chain(numbers).collect();
+    if described.is_empty() {
+        "(no features)".to_string()
+    } else {
+        described.join(", ")
+    }
+}
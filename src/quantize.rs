@@ -0,0 +1,634 @@
+use crate::{
+    bayes::{BatchPrediction, NaiveBayesClassifier, OTHER_FEATURE},
+    calibration::Calibrator,
+    compress::{self, Compression},
+    config::{Config, ModelKind},
+    error::{BayesError, Result},
+    features::Features,
+    format::{self, Format, ParseReport},
+    progress::{Stage, Tracker},
+    symbols::Symbol,
+};
+use std::{
+    borrow::Cow,
+    fmt, fs, io,
+    io::{Read, Write},
+    path::Path,
+    str::FromStr,
+};
+
+/// The first bytes of every quantized model file (and its format version).
+const MAGIC: &[u8; 4] = b"NBQ2";
+
+/// How many weights are read from a quantized model file at once.
+const READ_CHUNK: usize = 1 << 16;
+
+/// How the weights of a quantized model are stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Precision {
+    /// IEEE half precision floats.
+    F16,
+    /// Fixed point 16 bit integers, multiplied by a scale shared by all of the weights.
+    I16,
+}
+
+impl FromStr for Precision {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "f16" => Ok(Precision::F16),
+            "i16" => Ok(Precision::I16),
+            _ => Err(BayesError::InvalidConfig(format!(
+                "unknown precision: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// A classifier whose log probabilities are quantized to 16 bits, for predicting on devices
+/// without much memory: a model with 50k symptoms and 500 diseases takes up 50MB. It's saved in a
+/// compact binary format rather than as json, and can only predict, not be trained further.
+///
+/// Only symptoms are quantized, models with categorical or numeric columns or TF-IDF weighting
+/// aren't supported.
+pub struct QuantizedClassifier {
+    config: Config,
+    calibrator: Option<Calibrator>,
+    precision: Precision,
+    classes: Vec<Symbol>,
+    // Sorted, so symptoms can be looked up with a binary search rather than a hash map:
+    features: Vec<Symbol>,
+    // The log prior of every class, plus the log probability of none of the symptoms being
+    // present for bernoulli models:
+    base: Vec<f32>,
+    // What fixed point weights are multiplied by:
+    scale: f32,
+    // The weight of every (symptom, class) pair, the weights of a symptom being next to each
+    // other. That's the log beta for multinomial models, and the log odds of the symptom being
+    // present rather than absent for bernoulli ones:
+    weights: Vec<u16>,
+}
+
+impl QuantizedClassifier {
+    pub fn quantize(classifier: &NaiveBayesClassifier, precision: Precision) -> Result<Self> {
+        let config = classifier.config();
+        if config.tf_idf
+            || classifier.feature_pairs().is_some()
+            || !classifier.categorical_columns().is_empty()
+            || !classifier.numeric_columns().is_empty()
+        {
+            return Err(BayesError::Unsupported(
+                "only symptoms (without TF-IDF or symptom pairs) can be quantized.".to_string(),
+            ));
+        }
+
+        let classes = classifier.classes();
+        let features = classifier.features();
+        let mut base: Vec<f64> = classes
+            .iter()
+            .map(|&class| classifier.class_log_prior(class).unwrap())
+            .collect();
+        let mut weights = Vec::with_capacity(features.len() * classes.len());
+        for &feature in &features {
+            for (c, &class) in classes.iter().enumerate() {
+                let log_beta = classifier.feature_log_prob(class, feature).unwrap();
+                weights.push(match config.model_kind {
+                    ModelKind::Multinomial => log_beta,
+                    // Start from every symptom being absent and swap in the present ones:
+                    ModelKind::Bernoulli => {
+                        let log_absent = (-log_beta.exp()).ln_1p();
+                        base[c] += log_absent;
+                        log_beta - log_absent
+                    }
+                });
+            }
+        }
+
+        let scale = match precision {
+            Precision::F16 => 1.0,
+            Precision::I16 => {
+                let max = weights.iter().fold(0.0f64, |max, w| max.max(w.abs()));
+                if max > 0.0 {
+                    (max / i16::MAX as f64) as f32
+                } else {
+                    1.0
+                }
+            }
+        };
+        let weights = weights
+            .into_iter()
+            .map(|w| match precision {
+                Precision::F16 => f32_to_f16(w as f32),
+                Precision::I16 => (w as f32 / scale).round() as i16 as u16,
+            })
+            .collect();
+
+        Ok(QuantizedClassifier {
+            config: config.clone(),
+            calibrator: classifier.calibrator().cloned(),
+            precision,
+            classes: classes.into_iter().map(Symbol::new).collect(),
+            features: features.into_iter().map(Symbol::new).collect(),
+            base: base.into_iter().map(|b| b as f32).collect(),
+            scale,
+            weights,
+        })
+    }
+
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
+    pub fn precision(&self) -> Precision {
+        self.precision
+    }
+
+    /// All of the diseases the classifier knows about (sorted):
+    pub fn classes(&self) -> Vec<&str> {
+        self.classes.iter().map(|class| class.as_str()).collect()
+    }
+
+    /// How many bytes the weights take up:
+    pub fn weights_bytes(&self) -> usize {
+        self.weights.len() * 2
+    }
+
+    /// Writes the classifier in the compact binary format (compressed according to the
+    /// extension):
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let mut writer = compress::create(path)?;
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[match self.precision {
+            Precision::F16 => 0,
+            Precision::I16 => 1,
+        }])?;
+        write_bytes(&mut writer, &serde_json::to_vec(&self.config)?)?;
+        write_bytes(&mut writer, &serde_json::to_vec(&self.calibrator)?)?;
+        for names in [&self.classes, &self.features] {
+            write_len(&mut writer, names.len())?;
+            for name in names {
+                write_bytes(&mut writer, name.as_bytes())?;
+            }
+        }
+        for b in &self.base {
+            writer.write_all(&b.to_le_bytes())?;
+        }
+        writer.write_all(&self.scale.to_le_bytes())?;
+        for chunk in self.weights.chunks(READ_CHUNK) {
+            let bytes: Vec<u8> = chunk.iter().flat_map(|w| w.to_le_bytes()).collect();
+            writer.write_all(&bytes)?;
+        }
+        writer.finish()
+    }
+
+    /// Reads a classifier written by `save`. The weights are kept quantized, so it takes up
+    /// about as much memory as the file:
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        // The size of compressed files isn't known until they're read:
+        let size = match Compression::from_path(path) {
+            Compression::None => Some(fs::metadata(path)?.len()),
+            _ => None,
+        };
+        let mut reader = ModelReader {
+            reader: compress::open(path)?,
+            remaining: size,
+        };
+        let magic = reader.read_array::<4>()?;
+        if &magic != MAGIC {
+            return Err(invalid_data("not a quantized model file."));
+        }
+        let precision = match reader.read_array::<1>()? {
+            [0] => Precision::F16,
+            [1] => Precision::I16,
+            _ => return Err(invalid_data("unknown precision.")),
+        };
+        let config: Config = serde_json::from_slice(&reader.read_bytes()?)?;
+        let calibrator = serde_json::from_slice(&reader.read_bytes()?)?;
+
+        let mut names = || -> Result<Vec<Symbol>> {
+            // Every name takes up at least the 4 bytes of its length:
+            let len = reader.read_len(4)?;
+            let mut names = Vec::with_capacity(len.min(READ_CHUNK));
+            for _ in 0..len {
+                let bytes = reader.read_bytes()?;
+                let name = std::str::from_utf8(&bytes)
+                    .map_err(|_| invalid_data("names must be utf-8."))?;
+                names.push(Symbol::new(name));
+            }
+            Ok(names)
+        };
+        let classes = names()?;
+        let features = names()?;
+
+        reader.check_len(classes.len(), 4)?;
+        let base = (0..classes.len())
+            .map(|_| Ok(f32::from_le_bytes(reader.read_array()?)))
+            .collect::<Result<_>>()?;
+        let scale = f32::from_le_bytes(reader.read_array()?);
+
+        // Read a chunk at a time so the whole file is never in memory twice:
+        let len = classes
+            .len()
+            .checked_mul(features.len())
+            .ok_or_else(|| invalid_data("too many weights."))?;
+        reader.check_len(len, 2)?;
+        let mut weights = Vec::with_capacity(len.min(READ_CHUNK));
+        let mut bytes = vec![0; 2 * READ_CHUNK];
+        while weights.len() < len {
+            let chunk = &mut bytes[..2 * READ_CHUNK.min(len - weights.len())];
+            reader.read_exact(chunk)?;
+            weights.extend(
+                chunk
+                    .chunks_exact(2)
+                    .map(|w| u16::from_le_bytes([w[0], w[1]])),
+            );
+        }
+
+        Ok(QuantizedClassifier {
+            config,
+            calibrator,
+            precision,
+            classes,
+            features,
+            base,
+            scale,
+            weights,
+        })
+    }
+
+    fn weight(&self, w: u16) -> f64 {
+        match self.precision {
+            Precision::F16 => f16_to_f32(w) as f64,
+            Precision::I16 => (w as i16 as f32 * self.scale) as f64,
+        }
+    }
+
+    /// The log score of every class, like `NaiveBayesClassifier` computes them but from the
+    /// quantized weights:
+    fn log_scores(&self, psymptoms: &Features) -> Vec<f64> {
+        let hashed = match &self.config.feature_hasher {
+            Some(hasher) => {
+                let mut features = psymptoms.clone();
+                hasher.transform(&mut features);
+                Cow::Owned(features)
+            }
+            None => Cow::Borrowed(psymptoms),
+        };
+        let psymptoms = &*hashed;
+        let index = |symptom: &str| {
+            self.features
+                .binary_search_by(|feature| feature.as_str().cmp(symptom))
+                .ok()
+        };
+
+        // Unknown symptoms are pooled into the other feature if the model was trained that way:
+        let mut counts = Vec::with_capacity(psymptoms.counts.len());
+        let mut other = 0.0;
+        for (symptom, &count) in &psymptoms.counts {
+            match index(symptom) {
+                Some(i) if symptom != OTHER_FEATURE => counts.push((i, count)),
+                _ if self.config.pool_rare_features => other += count,
+                _ => {}
+            }
+        }
+        if other > 0.0 {
+            if let Some(i) = index(OTHER_FEATURE) {
+                counts.push((i, other));
+            }
+        }
+
+        let num_classes = self.classes.len();
+        let mut scores: Vec<f64> = self.base.iter().map(|&b| b as f64).collect();
+        for (i, count) in counts {
+            let count = match self.config.model_kind {
+                ModelKind::Multinomial => count,
+                ModelKind::Bernoulli if count > 0.0 => 1.0,
+                ModelKind::Bernoulli => continue,
+            };
+            let weights = &self.weights[i * num_classes..(i + 1) * num_classes];
+            for (score, &w) in scores.iter_mut().zip(weights) {
+                *score += count * self.weight(w);
+            }
+        }
+        scores
+    }
+
+    /// The (uncalibrated) posterior probability of every class, in the order of `classes`:
+    pub(crate) fn probabilities(&self, psymptoms: &Features) -> Vec<(&str, f64)> {
+        let scores = self.log_scores(psymptoms);
+        let max_score = scores.iter().fold(f64::NEG_INFINITY, |acc, &s| acc.max(s));
+        let total: f64 = scores.iter().map(|score| (score - max_score).exp()).sum();
+        self.classes
+            .iter()
+            .zip(&scores)
+            .map(|(class, score)| (class.as_str(), (score - max_score).exp() / total))
+            .collect()
+    }
+
+    /// Returns the `k` most likely diseases for the symptoms along with their (normalized, and
+    /// calibrated if the full classifier was) probabilities, most likely first:
+    pub fn predict_top_k(&self, psymptoms: &Features, k: usize) -> Vec<(&str, f64)> {
+        let mut probabilities = self.probabilities(psymptoms);
+
+        // Calibrate each probability and then make sure they sum to one again:
+        if let Some(calibrator) = &self.calibrator {
+            for (_, p) in &mut probabilities {
+                *p = calibrator.apply(*p);
+            }
+            let total: f64 = probabilities.iter().map(|(_, p)| p).sum();
+            if total > 0.0 {
+                probabilities.iter_mut().for_each(|(_, p)| *p /= total);
+            }
+        }
+
+        probabilities.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        probabilities.truncate(k);
+        probabilities
+    }
+
+    /// Predicts the disease of a record along with its score (the exponent of its log score),
+    /// deciding like `NaiveBayesClassifier` does: by the cost matrix if there is one, and with
+    /// the unknown label when the most likely disease falls below the confidence or margin:
+    fn predict_one(&self, psymptoms: &Features) -> (&str, f64) {
+        let scores = self.log_scores(psymptoms);
+        let top = self.predict_top_k(psymptoms, self.classes.len());
+        let best = match &self.config.costs {
+            Some(costs) => costs.decide(&top),
+            None => top.first().copied(),
+        };
+        let Some((disease, _)) = best else {
+            return ("", 0.0);
+        };
+        let score = self
+            .classes
+            .iter()
+            .position(|class| class.as_str() == disease)
+            .map_or(0.0, |i| scores[i].exp());
+
+        let probability = top[0].1;
+        let runner_up = top.get(1).map_or(0.0, |&(_, p)| p);
+        if probability < self.config.min_confidence
+            || probability - runner_up < self.config.min_margin
+        {
+            return (&self.config.unknown_label, score);
+        }
+        (disease, score)
+    }
+
+    /// Predicts every record, returning the predictions in the same order:
+    pub fn predict_batch(&self, records: &[Features]) -> Vec<BatchPrediction> {
+        let mut tracker = Tracker::start(&self.config, Stage::Predicting);
+        let results = records
+            .iter()
+            .enumerate()
+            .map(|(i, symptoms)| {
+                tracker.record();
+                let (disease, score) = self.predict_one(symptoms);
+                let mut prediction = BatchPrediction {
+                    id: (i + 1).to_string(),
+                    id_from_input: false,
+                    disease: Symbol::new(disease),
+                    score,
+                    probability: None,
+                    alternatives: Vec::new(),
+                };
+                prediction.describe(&self.config.output, || {
+                    self.predict_top_k(symptoms, self.classes.len())
+                });
+                prediction
+            })
+            .collect();
+        log::info!("predicted {} records", tracker.finish());
+        results
+    }
+
+    /// Predicts the records of a test file like `NaiveBayesClassifier::predict`:
+    pub fn predict<P: AsRef<Path>>(
+        &self,
+        inpath: P,
+        outpath: P,
+        format: Option<Format>,
+    ) -> Result<ParseReport> {
+        format::predict_file(inpath, outpath, format, &self.config, |records| {
+            self.predict_batch(records)
+        })
+    }
+}
+
+/// How much quantizing a classifier changes its predictions of labeled records.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct QuantizationReport {
+    pub records: usize,
+    /// The fraction of records both classifiers predict the same disease for.
+    pub agreement: f64,
+    pub full_accuracy: f64,
+    pub quantized_accuracy: f64,
+    /// The mean and largest difference between the (uncalibrated) probabilities the classifiers
+    /// give any disease of a record.
+    pub mean_probability_delta: f64,
+    pub max_probability_delta: f64,
+}
+
+impl QuantizationReport {
+    /// Predicts the (disease, features) records with both classifiers:
+    pub fn compare(
+        full: &NaiveBayesClassifier,
+        quantized: &QuantizedClassifier,
+        records: &[(String, Features)],
+    ) -> Self {
+        let mut report = QuantizationReport {
+            records: records.len(),
+            ..Default::default()
+        };
+        if records.is_empty() {
+            return report;
+        }
+
+        let (mut agree, mut full_correct, mut quantized_correct) = (0, 0, 0);
+        let (mut total_delta, mut num_deltas) = (0.0, 0);
+        for (disease, features) in records {
+            let mut full_probabilities = full.probabilities(features);
+            full_probabilities.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            let mut quantized_probabilities = quantized.probabilities(features);
+            quantized_probabilities.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+            let full_top = full_probabilities.first().map_or("", |&(class, _)| class);
+            let quantized_top = quantized_probabilities
+                .first()
+                .map_or("", |&(class, _)| class);
+            agree += (full_top == quantized_top) as usize;
+            full_correct += (full_top == disease) as usize;
+            quantized_correct += (quantized_top == disease) as usize;
+
+            for &(class, p) in &full_probabilities {
+                let q = quantized_probabilities
+                    .iter()
+                    .find(|&&(other, _)| other == class)
+                    .map_or(0.0, |&(_, q)| q);
+                let delta = (p - q).abs();
+                total_delta += delta;
+                num_deltas += 1;
+                report.max_probability_delta = report.max_probability_delta.max(delta);
+            }
+        }
+
+        let n = records.len() as f64;
+        report.agreement = agree as f64 / n;
+        report.full_accuracy = full_correct as f64 / n;
+        report.quantized_accuracy = quantized_correct as f64 / n;
+        report.mean_probability_delta = total_delta / num_deltas.max(1) as f64;
+        report
+    }
+}
+
+impl fmt::Display for QuantizationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "records:            {}", self.records)?;
+        writeln!(f, "agreement:          {:.4}", self.agreement)?;
+        writeln!(f, "full accuracy:      {:.4}", self.full_accuracy)?;
+        writeln!(f, "quantized accuracy: {:.4}", self.quantized_accuracy)?;
+        writeln!(
+            f,
+            "accuracy delta:     {:+.4}",
+            self.quantized_accuracy - self.full_accuracy
+        )?;
+        writeln!(f, "mean prob delta:    {:.6}", self.mean_probability_delta)?;
+        writeln!(f, "max prob delta:     {:.6}", self.max_probability_delta)
+    }
+}
+
+fn invalid_data(message: &str) -> BayesError {
+    io::Error::new(io::ErrorKind::InvalidData, message).into()
+}
+
+fn write_len<W: Write>(writer: &mut W, len: usize) -> Result<()> {
+    let len = u32::try_from(len).map_err(|_| invalid_data("too many entries."))?;
+    writer.write_all(&len.to_le_bytes())?;
+    Ok(())
+}
+
+fn write_bytes<W: Write>(writer: &mut W, bytes: &[u8]) -> Result<()> {
+    write_len(writer, bytes.len())?;
+    writer.write_all(bytes)?;
+    Ok(())
+}
+
+/// Reads a quantized model file, keeping track of how much of it is left (when its size is
+/// known) so lengths read from a corrupt file can't make it allocate more than the file holds.
+/// When the size isn't known, nothing is allocated ahead of the bytes actually read:
+struct ModelReader {
+    reader: Box<dyn Read>,
+    remaining: Option<u64>,
+}
+
+impl ModelReader {
+    fn read_exact(&mut self, bytes: &mut [u8]) -> Result<()> {
+        self.reader.read_exact(bytes)?;
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.saturating_sub(bytes.len() as u64);
+        }
+        Ok(())
+    }
+
+    fn read_array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        let mut bytes = [0; N];
+        self.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Checks that `len` entries of at least `bytes` bytes each fit in the rest of the file:
+    fn check_len(&self, len: usize, bytes: u64) -> Result<()> {
+        match self.remaining {
+            Some(remaining) if (len as u64).saturating_mul(bytes) > remaining => Err(invalid_data(
+                "a length is larger than the rest of the file.",
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reads the number of entries that follow, each taking up at least `bytes` bytes:
+    fn read_len(&mut self, bytes: u64) -> Result<usize> {
+        let len = u32::from_le_bytes(self.read_array()?) as usize;
+        self.check_len(len, bytes)?;
+        Ok(len)
+    }
+
+    fn read_bytes(&mut self) -> Result<Vec<u8>> {
+        let len = self.read_len(1)?;
+        let mut bytes = Vec::with_capacity(len.min(READ_CHUNK));
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() < len {
+            return Err(io::Error::from(io::ErrorKind::UnexpectedEof).into());
+        }
+        if let Some(remaining) = &mut self.remaining {
+            *remaining = remaining.saturating_sub(len as u64);
+        }
+        Ok(bytes)
+    }
+}
+
+/// Rounds a float to the nearest half precision float (ties to even), as its bits:
+pub fn f32_to_f16(x: f32) -> u16 {
+    let bits = x.to_bits();
+    let sign = ((bits >> 16) & 0x8000) as u16;
+    let exponent = ((bits >> 23) & 0xff) as i32;
+    let mantissa = bits & 0x7f_ffff;
+
+    // Infinity and NaN (keeping it a NaN):
+    if exponent == 0xff {
+        return sign | 0x7c00 | if mantissa != 0 { 0x200 } else { 0 };
+    }
+
+    let exponent = exponent - 127 + 15;
+    if exponent >= 0x1f {
+        return sign | 0x7c00;
+    }
+    if exponent <= 0 {
+        // Too small even for a subnormal:
+        if exponent < -10 {
+            return sign;
+        }
+        // A subnormal, counting multiples of 2^-24:
+        let mantissa = mantissa | 0x80_0000;
+        let shift = (14 - exponent) as u32;
+        let half = 1 << (shift - 1);
+        let rest = mantissa & ((1 << shift) - 1);
+        let mut h = (mantissa >> shift) as u16;
+        if rest > half || (rest == half && h & 1 == 1) {
+            h += 1;
+        }
+        return sign | h;
+    }
+
+    // Rounding up may carry into the exponent, which is still the right result:
+    let mut h = sign | ((exponent as u16) << 10) | (mantissa >> 13) as u16;
+    let rest = mantissa & 0x1fff;
+    if rest > 0x1000 || (rest == 0x1000 && h & 1 == 1) {
+        h += 1;
+    }
+    h
+}
+
+/// Widens a half precision float, given as its bits:
+pub fn f16_to_f32(h: u16) -> f32 {
+    let sign = ((h & 0x8000) as u32) << 16;
+    let exponent = ((h >> 10) & 0x1f) as u32;
+    let mantissa = (h & 0x3ff) as u32;
+    match exponent {
+        0 => {
+            let magnitude = mantissa as f32 * 2f32.powi(-24);
+            if sign != 0 {
+                -magnitude
+            } else {
+                magnitude
+            }
+        }
+        0x1f => f32::from_bits(sign | 0x7f80_0000 | (mantissa << 13)),
+        _ => f32::from_bits(sign | ((exponent + 112) << 23) | (mantissa << 13)),
+    }
+}
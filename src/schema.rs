@@ -0,0 +1,144 @@
+use crate::error::{BayesError, Result};
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, path::Path, str::FromStr};
+
+/// How the values of a csv column are interpreted.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ColumnType {
+    /// The value is the name of a present symptom (or raw text in text mode).
+    #[default]
+    Symptom,
+    /// The value is one of several categories of the column, e.g. none/mild/high. A binary
+    /// (Bernoulli) column is a categorical column with two values.
+    Categorical,
+    /// The value is a number, modeled by a normal distribution per disease.
+    Numeric,
+}
+
+impl FromStr for ColumnType {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "symptom" => Ok(ColumnType::Symptom),
+            "categorical" => Ok(ColumnType::Categorical),
+            "numeric" => Ok(ColumnType::Numeric),
+            _ => Err(BayesError::InvalidConfig(format!(
+                "unknown column type: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// What happens when a categorical or numeric column has no value in a record.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum MissingValue {
+    /// The column is left out of the record's likelihood.
+    #[default]
+    Skip,
+    /// The column takes on its most frequent training value (the mean for numeric columns).
+    Impute,
+    /// Missing is a category of its own. Only valid for categorical columns.
+    Category,
+}
+
+impl FromStr for MissingValue {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim().to_ascii_lowercase().as_str() {
+            "skip" => Ok(MissingValue::Skip),
+            "impute" => Ok(MissingValue::Impute),
+            "category" => Ok(MissingValue::Category),
+            _ => Err(BayesError::InvalidConfig(format!(
+                "unknown missing value policy: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Declares the type of csv columns by their header. Columns that aren't declared are symptoms.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct CsvSchema {
+    columns: HashMap<String, ColumnType>,
+    missing: HashMap<String, MissingValue>,
+}
+
+impl CsvSchema {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_column<S: Into<String>>(mut self, column: S, column_type: ColumnType) -> Self {
+        self.columns.insert(column.into(), column_type);
+        self
+    }
+
+    /// Sets how missing values of a (categorical or numeric) column are handled:
+    pub fn with_missing<S: Into<String>>(mut self, column: S, missing: MissingValue) -> Self {
+        self.missing.insert(column.into(), missing);
+        self
+    }
+
+    /// Reads a schema from a csv file with a column name, a column type, and optionally a missing
+    /// value policy on every row:
+    pub fn from_csv<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut reader = csv::Reader::from_path(path)?;
+
+        let mut schema = Self::new();
+        for record in reader.records() {
+            let record = record?;
+            let column = record.get(0).ok_or_else(|| {
+                BayesError::parse(record.position(), None, "missing column entry.")
+            })?;
+            let column_type = record.get(1).ok_or_else(|| {
+                BayesError::parse(record.position(), Some(column), "missing type entry.")
+            })?;
+            let column_type: ColumnType = column_type.parse()?;
+            schema = schema.with_column(column.trim(), column_type);
+
+            if let Some(missing) = record.get(2).filter(|missing| !missing.trim().is_empty()) {
+                let missing: MissingValue = missing.parse()?;
+                if missing != MissingValue::Skip && column_type == ColumnType::Symptom {
+                    return Err(BayesError::InvalidConfig(format!(
+                        "symptom column {} can't have missing values.",
+                        column
+                    )));
+                }
+                if missing == MissingValue::Category && column_type == ColumnType::Numeric {
+                    return Err(BayesError::InvalidConfig(format!(
+                        "numeric column {} can't treat missing as a category.",
+                        column
+                    )));
+                }
+                schema = schema.with_missing(column.trim(), missing);
+            }
+        }
+
+        Ok(schema)
+    }
+
+    pub fn column_type(&self, column: &str) -> ColumnType {
+        self.columns.get(column.trim()).copied().unwrap_or_default()
+    }
+
+    pub fn missing_value(&self, column: &str) -> MissingValue {
+        self.missing.get(column.trim()).copied().unwrap_or_default()
+    }
+
+    /// Every declared column with its type and missing value policy:
+    pub fn columns(&self) -> impl Iterator<Item = (&str, ColumnType, MissingValue)> {
+        self.columns.iter().map(|(column, &column_type)| {
+            (column.as_str(), column_type, self.missing_value(column))
+        })
+    }
+
+    /// Whether any column's missing values are imputed from the training records:
+    pub fn imputes(&self) -> bool {
+        self.columns().any(|(_, column_type, missing)| {
+            column_type != ColumnType::Symptom && missing == MissingValue::Impute
+        })
+    }
+}
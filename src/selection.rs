@@ -0,0 +1,106 @@
+use crate::{
+    bayes::DiseaseRecords,
+    error::{BayesError, Result},
+    symbols::Symbol,
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    str::FromStr,
+};
+
+/// How strongly a symptom's presence is tied to the disease label.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum FeatureScore {
+    MutualInformation,
+    ChiSquared,
+}
+
+impl FromStr for FeatureScore {
+    type Err = BayesError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "mi" | "mutual-information" => Ok(FeatureScore::MutualInformation),
+            "chi2" | "chi-squared" => Ok(FeatureScore::ChiSquared),
+            _ => Err(BayesError::InvalidConfig(format!(
+                "unknown feature score: {}",
+                s
+            ))),
+        }
+    }
+}
+
+/// Keep only the `keep` symptoms that score highest with `score`.
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct FeatureSelection {
+    pub score: FeatureScore,
+    pub keep: usize,
+}
+
+/// Scores every symptom against the diseases of the (weighted) training records, highest first.
+/// Ties are broken by name so the ranking is always the same:
+pub fn rank_features(
+    diseases_map: &DiseaseRecords,
+    all_symptoms: &HashSet<Symbol>,
+    score: FeatureScore,
+) -> Vec<(String, f64)> {
+    // Weight of every disease and of every (disease, symptom) pair:
+    let mut disease_totals = BTreeMap::new();
+    let mut symptom_counts: HashMap<&str, BTreeMap<&str, f64>> = HashMap::new();
+    for (disease, symptoms_instances) in diseases_map {
+        let mut total = 0.0;
+        for (symptoms, weight) in symptoms_instances {
+            total += weight;
+            for symptom in symptoms.counts.keys() {
+                *symptom_counts
+                    .entry(symptom.as_str())
+                    .or_default()
+                    .entry(disease.as_str())
+                    .or_default() += weight;
+            }
+        }
+        disease_totals.insert(disease.as_str(), total);
+    }
+    let num_records: f64 = disease_totals.values().sum();
+
+    let mut ranking: Vec<(String, f64)> = all_symptoms
+        .iter()
+        .map(|symptom| {
+            let counts = symptom_counts.get(symptom.as_str());
+            let present: f64 = counts.map_or(0.0, |counts| counts.values().sum());
+
+            // Compare the observed (present/absent, disease) table with the one expected if
+            // the symptom and the disease were independent:
+            let mut value = 0.0;
+            for (disease, &disease_total) in &disease_totals {
+                let with = counts
+                    .and_then(|counts| counts.get(disease))
+                    .copied()
+                    .unwrap_or(0.0);
+                let cells = [
+                    (with, present),
+                    (disease_total - with, num_records - present),
+                ];
+                for (observed, symptom_total) in cells {
+                    let expected = symptom_total * disease_total / num_records;
+                    if expected <= 0.0 {
+                        continue;
+                    }
+                    value += match score {
+                        FeatureScore::MutualInformation if observed > 0.0 => {
+                            observed / num_records * (observed / expected).ln()
+                        }
+                        FeatureScore::MutualInformation => 0.0,
+                        FeatureScore::ChiSquared => (observed - expected).powi(2) / expected,
+                    };
+                }
+            }
+
+            (symptom.to_string(), value)
+        })
+        .collect();
+
+    ranking.sort_by(|(fa, a), (fb, b)| b.total_cmp(a).then_with(|| fa.cmp(fb)));
+    ranking
+}
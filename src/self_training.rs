@@ -0,0 +1,107 @@
+use crate::{
+    bayes::NaiveBayesClassifier,
+    config::Config,
+    error::{BayesError, Result},
+    features::Features,
+    rng::Rng,
+};
+
+/// Semi-supervised training: trains on the labeled records, labels the unlabeled records the
+/// classifier is confident about with its predictions, and trains again with those added, for a
+/// number of rounds. A pseudo-labeled record keeps its label for the rounds after.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SelfTraining {
+    /// The most rounds of pseudo-labeling, fewer if a round labels no new records.
+    pub rounds: usize,
+    /// Only records whose top probability is at least this get pseudo-labeled.
+    pub threshold: f64,
+    /// What every pseudo-labeled record counts as, compared to a labeled record of weight 1.
+    pub weight: f64,
+    /// Every round only considers a random sample of this many of the records left unlabeled
+    /// (seeded by the config's seed), which keeps rounds fast when there are many. All of them
+    /// are considered otherwise.
+    pub pool_size: Option<usize>,
+}
+
+impl Default for SelfTraining {
+    fn default() -> Self {
+        SelfTraining {
+            rounds: 5,
+            threshold: 0.9,
+            weight: 1.0,
+            pool_size: None,
+        }
+    }
+}
+
+impl SelfTraining {
+    /// Trains on the weighted labeled records and the unlabeled records:
+    pub fn fit<I, S>(
+        &self,
+        labeled: I,
+        unlabeled: &[Features],
+        config: Config,
+    ) -> Result<NaiveBayesClassifier>
+    where
+        I: IntoIterator<Item = (S, Features, f64)>,
+        S: Into<String>,
+    {
+        if self.threshold.is_nan() || self.threshold <= 0.0 || self.threshold > 1.0 {
+            return Err(BayesError::InvalidConfig(format!(
+                "pseudo-label threshold must be between 0 and 1: {}",
+                self.threshold
+            )));
+        }
+        if !self.weight.is_finite() || self.weight <= 0.0 {
+            return Err(BayesError::InvalidConfig(format!(
+                "pseudo-label weight must be positive: {}",
+                self.weight
+            )));
+        }
+
+        let labeled: Vec<(String, Features, f64)> = labeled
+            .into_iter()
+            .map(|(disease, features, weight)| (disease.into(), features, weight))
+            .collect();
+        let mut classifier = NaiveBayesClassifier::fit_weighted(labeled.clone(), config.clone())?;
+
+        let mut rng = Rng::seeded(config.seed.unwrap_or(0));
+        let mut pool: Vec<&Features> = unlabeled.iter().collect();
+        let mut pseudo_labeled: Vec<(String, Features, f64)> = Vec::new();
+        for round in 1..=self.rounds {
+            // The sample is moved to the front, the rest sits this round out:
+            let mut remaining = Vec::with_capacity(pool.len());
+            if let Some(size) = self.pool_size.filter(|&size| size < pool.len()) {
+                rng.shuffle(&mut pool);
+                remaining.extend(pool.drain(size..));
+            }
+            let before = pseudo_labeled.len();
+            for features in pool {
+                match classifier.predict_top_k(features, 1).first() {
+                    Some(&(disease, p)) if p >= self.threshold => {
+                        pseudo_labeled.push((disease.to_string(), features.clone(), self.weight))
+                    }
+                    _ => remaining.push(features),
+                }
+            }
+            pool = remaining;
+
+            let added = pseudo_labeled.len() - before;
+            log::info!(
+                "self-training round {}: pseudo-labeled {} records ({} left unlabeled)",
+                round,
+                added,
+                pool.len()
+            );
+            if added == 0 {
+                break;
+            }
+            classifier = NaiveBayesClassifier::fit_weighted(
+                labeled.iter().chain(&pseudo_labeled).cloned(),
+                config.clone(),
+            )?;
+        }
+
+        Ok(classifier)
+    }
+}
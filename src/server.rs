@@ -0,0 +1,94 @@
+use crate::{bayes::NaiveBayesClassifier, error::Result, format::JsonRecord, symbols::SymbolTable};
+use serde::{Deserialize, Serialize};
+use std::io;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+// How many requests are served between purging the names of the symptoms they had:
+const PURGE_EVERY: usize = 10_000;
+
+#[derive(Deserialize)]
+struct PredictRequest {
+    #[serde(flatten)]
+    record: JsonRecord,
+    k: Option<usize>,
+}
+
+#[derive(Serialize)]
+struct PredictResponse<'a> {
+    predictions: Vec<ClassProbability<'a>>,
+}
+
+#[derive(Serialize)]
+struct ClassProbability<'a> {
+    disease: &'a str,
+    probability: f64,
+}
+
+/// Serves predictions from the classifier over http until the process is killed:
+///
+/// - `POST /predict` takes a record like those in jsonl files along with the number of diseases
+///   to return, e.g. `{"symptoms": [...], "k": 3}`, and responds with the top k diseases.
+/// - `GET /health` responds with `ok`.
+pub fn serve(classifier: &NaiveBayesClassifier, addr: &str, default_k: usize) -> Result<()> {
+    let server = Server::http(addr)
+        .map_err(|e| io::Error::other(format!("failed to bind {}: {}", addr, e)))?;
+    log::info!("listening on {}", addr);
+
+    for (i, mut request) in server.incoming_requests().enumerate() {
+        if (i + 1) % PURGE_EVERY == 0 {
+            let purged = SymbolTable::global().purge();
+            log::info!("purged {} unused names", purged);
+        }
+
+        let (status, body) = match (request.method(), request.url()) {
+            (Method::Get, "/health") => (200, "ok".to_string()),
+            (Method::Post, "/predict") => match predict(classifier, &mut request, default_k) {
+                Ok(body) => (200, body),
+                Err(e) => (
+                    400,
+                    serde_json::json!({ "error": e.to_string() }).to_string(),
+                ),
+            },
+            _ => (404, serde_json::json!({ "error": "not found" }).to_string()),
+        };
+
+        let content_type = if body.starts_with('{') {
+            "application/json"
+        } else {
+            "text/plain"
+        };
+        let header = Header::from_bytes("Content-Type", content_type).unwrap();
+        let response = Response::from_string(body)
+            .with_status_code(status)
+            .with_header(header);
+
+        // A client hanging up shouldn't bring the server down:
+        if let Err(e) = request.respond(response) {
+            log::warn!("failed to respond: {}", e);
+        }
+    }
+
+    Ok(())
+}
+
+fn predict(
+    classifier: &NaiveBayesClassifier,
+    request: &mut Request,
+    default_k: usize,
+) -> Result<String> {
+    let request: PredictRequest = serde_json::from_reader(request.as_reader())?;
+
+    let k = request.k.unwrap_or(default_k);
+    let features = request.record.into_features(classifier.config());
+
+    let predictions = classifier
+        .predict_top_k(&features, k)
+        .into_iter()
+        .map(|(disease, probability)| ClassProbability {
+            disease,
+            probability,
+        })
+        .collect();
+
+    Ok(serde_json::to_string(&PredictResponse { predictions })?)
+}
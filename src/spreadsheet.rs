@@ -0,0 +1,88 @@
+use crate::{compress, config::Config, error::Result};
+#[cfg(feature = "xlsx")]
+use calamine::Reader;
+use serde::{Deserialize, Serialize};
+use std::{io::Read, path::Path};
+
+/// Which sheet of a workbook records are read from, and where its header is.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SheetConfig {
+    /// The name of the sheet, the first sheet of the workbook if there is none.
+    pub name: Option<String>,
+    /// The (1 based) row of the sheet holding the column headers, the rows above it are skipped.
+    pub header_row: usize,
+}
+
+impl Default for SheetConfig {
+    fn default() -> Self {
+        SheetConfig {
+            name: None,
+            header_row: 1,
+        }
+    }
+}
+
+/// Whether a file is a workbook, picked by its extension:
+pub fn is_spreadsheet<P: AsRef<Path>>(path: P) -> bool {
+    matches!(
+        path.as_ref().extension().and_then(|ext| ext.to_str()),
+        Some("xlsx") | Some("xlsm") | Some("xlsb") | Some("xls") | Some("ods")
+    )
+}
+
+/// Opens a file of records for reading. The configured sheet of a workbook is read as csv
+/// (starting at its header row), so its columns go through the schema like those of any csv file,
+/// while anything else is opened with `compress::open`. Rows of parse errors are counted from the
+/// header row:
+pub fn open<P: AsRef<Path>>(path: P, config: &Config) -> Result<Box<dyn Read>> {
+    let path = path.as_ref();
+    if !is_spreadsheet(path) {
+        return compress::open(path);
+    }
+    read_sheet(path, config)
+}
+
+#[cfg(feature = "xlsx")]
+fn read_sheet(path: &Path, config: &Config) -> Result<Box<dyn Read>> {
+    use crate::{dialect::Delimiter, error::BayesError};
+    use std::io::Cursor;
+
+    let sheet = &config.sheet;
+    let mut workbook = calamine::open_workbook_auto(path)?;
+    let name = match &sheet.name {
+        Some(name) => name.clone(),
+        None => workbook.sheet_names().first().cloned().ok_or_else(|| {
+            BayesError::InvalidConfig(format!("{} has no sheets.", path.display()))
+        })?,
+    };
+    let range = workbook.worksheet_range(&name)?;
+
+    // The range starts at the first cell that isn't empty, which may be below the header row:
+    let first_row = range.start().map_or(0, |(row, _)| row as usize);
+    let header_row = sheet.header_row.saturating_sub(1);
+    // The csv is UTF-8 whatever the configured encoding, which its byte order mark overrides, and
+    // is delimited the way it is read back:
+    let delimiter = match config.dialect.delimiter {
+        Delimiter::Byte(byte) => byte,
+        Delimiter::Sniff => b',',
+    };
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(delimiter)
+        .from_writer(b"\xEF\xBB\xBF".to_vec());
+    for (i, row) in range.rows().enumerate() {
+        if first_row + i >= header_row {
+            writer.write_record(row.iter().map(|cell| cell.to_string()))?;
+        }
+    }
+    let csv = writer.into_inner().map_err(|e| e.into_error())?;
+    Ok(Box::new(Cursor::new(csv)))
+}
+
+#[cfg(not(feature = "xlsx"))]
+fn read_sheet(path: &Path, _config: &Config) -> Result<Box<dyn Read>> {
+    Err(crate::error::BayesError::Unsupported(format!(
+        "{} is a spreadsheet, but xlsx support isn't enabled.",
+        path.display()
+    )))
+}
@@ -0,0 +1,127 @@
+use crate::{bayes::DiseaseRecords, features::Features, gaussian::Moments, symbols::Symbol};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet};
+
+/// Roughly what an entry of a table costs on top of its key's characters: the key's `String` (or
+/// `Symbol`), the value, and the tree node's share of bookkeeping.
+pub(crate) const ENTRY_BYTES: usize = 64;
+
+/// The weighted counts a classifier's probabilities are derived from. The counts of records
+/// trained on separately simply add up, so models trained on shards of a data set can be merged.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SufficientStats {
+    /// The weighted number of records of every disease.
+    pub records: BTreeMap<Symbol, f64>,
+    /// Every symptom seen.
+    pub vocabulary: BTreeSet<Symbol>,
+    /// disease -> symptom -> weighted sum of the symptom's counts.
+    pub symptom_counts: BTreeMap<Symbol, BTreeMap<Symbol, f64>>,
+    /// disease -> symptom -> weighted number of records with the symptom.
+    pub symptom_records: BTreeMap<Symbol, BTreeMap<Symbol, f64>>,
+    /// column -> disease -> value -> weighted number of records with the value.
+    pub categories: BTreeMap<Symbol, BTreeMap<Symbol, BTreeMap<Symbol, f64>>>,
+    /// column -> disease -> weighted moments of the values.
+    pub numbers: BTreeMap<Symbol, BTreeMap<Symbol, Moments>>,
+}
+
+impl SufficientStats {
+    /// Counts up the weighted training records:
+    pub fn collect(diseases_map: &DiseaseRecords) -> Self {
+        let mut stats = SufficientStats::default();
+        for (disease, symptoms_instances) in diseases_map {
+            for (features, weight) in symptoms_instances {
+                stats.add(disease, features, *weight);
+            }
+        }
+        stats
+    }
+
+    /// Counts up a single weighted training record:
+    pub fn add(&mut self, disease: &str, features: &Features, weight: f64) {
+        *entry(&mut self.records, disease) += weight;
+        let symptom_counts = entry(&mut self.symptom_counts, disease);
+        let symptom_records = entry(&mut self.symptom_records, disease);
+        for (symptom, count) in &features.counts {
+            if !self.vocabulary.contains(symptom) {
+                self.vocabulary.insert(symptom.clone());
+            }
+            *entry(symptom_counts, symptom) += weight * count;
+            *entry(symptom_records, symptom) += weight;
+        }
+        for (column, value) in &features.categories {
+            let column = entry(&mut self.categories, column);
+            *entry(entry(column, disease), value) += weight;
+        }
+        for (column, &x) in &features.numbers {
+            entry(entry(&mut self.numbers, column), disease).add(x, weight);
+        }
+    }
+
+    /// Adds the counts of other records:
+    pub fn merge(&mut self, other: &SufficientStats) {
+        fn add(counts: &mut BTreeMap<Symbol, f64>, other: &BTreeMap<Symbol, f64>) {
+            for (key, count) in other {
+                *counts.entry(key.clone()).or_default() += count;
+            }
+        }
+
+        add(&mut self.records, &other.records);
+        self.vocabulary.extend(other.vocabulary.iter().cloned());
+        for (disease, counts) in &other.symptom_counts {
+            add(
+                self.symptom_counts.entry(disease.clone()).or_default(),
+                counts,
+            );
+        }
+        for (disease, counts) in &other.symptom_records {
+            add(
+                self.symptom_records.entry(disease.clone()).or_default(),
+                counts,
+            );
+        }
+        for (column, diseases) in &other.categories {
+            let column = self.categories.entry(column.clone()).or_default();
+            for (disease, counts) in diseases {
+                add(column.entry(disease.clone()).or_default(), counts);
+            }
+        }
+        for (column, diseases) in &other.numbers {
+            let column = self.numbers.entry(column.clone()).or_default();
+            for (disease, moments) in diseases {
+                column.entry(disease.clone()).or_default().merge(moments);
+            }
+        }
+    }
+
+    /// Roughly how many bytes the counts take up in memory:
+    pub fn estimated_bytes(&self) -> usize {
+        // Names are interned, so they are only counted once (as the vocabulary and diseases):
+        fn table<V>(table: &BTreeMap<Symbol, V>, value: impl Fn(&V) -> usize) -> usize {
+            table.values().map(|v| ENTRY_BYTES + value(v)).sum()
+        }
+        let counts = |counts: &BTreeMap<Symbol, f64>| table(counts, |_| 0);
+
+        let vocabulary: usize = self.vocabulary.iter().map(|s| ENTRY_BYTES + s.len()).sum();
+        let diseases: usize = self.records.keys().map(|s| ENTRY_BYTES + s.len()).sum();
+        diseases
+            + vocabulary
+            + table(&self.symptom_counts, counts)
+            + table(&self.symptom_records, counts)
+            + table(&self.categories, |diseases| table(diseases, counts))
+            + table(&self.numbers, |diseases| table(diseases, |_| 0))
+    }
+
+    /// The weighted number of records over all diseases:
+    pub fn num_records(&self) -> f64 {
+        self.records.values().sum()
+    }
+}
+
+/// The value of a key, inserting the default if there is none. Unlike `entry`, this only interns
+/// the key the first time it is seen, which adds up when counting millions of records:
+fn entry<'a, V: Default>(table: &'a mut BTreeMap<Symbol, V>, key: &str) -> &'a mut V {
+    if !table.contains_key(key) {
+        table.insert(Symbol::new(key), V::default());
+    }
+    table.get_mut(key).unwrap()
+}
@@ -0,0 +1,194 @@
+use serde::{
+    de::{self, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use std::{
+    borrow::Borrow,
+    cmp::Ordering,
+    collections::HashSet,
+    fmt,
+    hash::{Hash, Hasher},
+    ops::Deref,
+    sync::{Arc, OnceLock, RwLock},
+};
+
+/// An interned class, feature, column, or category name. Every symbol of the same name shares a
+/// single allocation, so it's cheap to clone into every table keyed by it. It compares, hashes,
+/// and (de)serializes like the string it holds, so maps keyed by symbols can be looked up by
+/// `&str`.
+#[derive(Clone)]
+pub struct Symbol(Arc<str>);
+
+impl Symbol {
+    /// Interns a name in the global symbol table:
+    pub fn new(name: &str) -> Self {
+        SymbolTable::global().intern(name)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Deref for Symbol {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl Borrow<str> for Symbol {
+    fn borrow(&self) -> &str {
+        &self.0
+    }
+}
+
+impl AsRef<str> for Symbol {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl PartialEq for Symbol {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0) || self.0 == other.0
+    }
+}
+
+impl Eq for Symbol {}
+
+impl PartialOrd for Symbol {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Ordered by name (not by address) so iterating over tables is the same every run:
+impl Ord for Symbol {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.cmp(&other.0)
+    }
+}
+
+impl Hash for Symbol {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
+
+impl PartialEq<str> for Symbol {
+    fn eq(&self, other: &str) -> bool {
+        &*self.0 == other
+    }
+}
+
+impl From<&str> for Symbol {
+    fn from(name: &str) -> Self {
+        Symbol::new(name)
+    }
+}
+
+impl From<&String> for Symbol {
+    fn from(name: &String) -> Self {
+        Symbol::new(name)
+    }
+}
+
+impl From<String> for Symbol {
+    fn from(name: String) -> Self {
+        Symbol::new(&name)
+    }
+}
+
+impl fmt::Debug for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(&*self.0, f)
+    }
+}
+
+impl fmt::Display for Symbol {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl Serialize for Symbol {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.0)
+    }
+}
+
+impl<'de> Deserialize<'de> for Symbol {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct SymbolVisitor;
+
+        impl Visitor<'_> for SymbolVisitor {
+            type Value = Symbol;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a string")
+            }
+
+            // Names are interned straight from the input, without allocating a string first:
+            fn visit_str<E: de::Error>(self, name: &str) -> Result<Symbol, E> {
+                Ok(Symbol::new(name))
+            }
+        }
+
+        deserializer.deserialize_str(SymbolVisitor)
+    }
+}
+
+/// Hands out one shared `Symbol` per distinct name. There is only the global table, shared by the
+/// loaders (the names of every record's features), the models, and the prediction writers.
+///
+/// A name stays in the table for as long as the process runs unless it's purged: a long running
+/// host that keeps seeing new names (e.g. a server predicting on free text, or one that retrains
+/// on new vocabularies) should call `purge` now and then to drop the names nothing uses anymore.
+pub struct SymbolTable {
+    symbols: RwLock<HashSet<Arc<str>>>,
+}
+
+impl SymbolTable {
+    /// The table every `Symbol` is interned in:
+    pub fn global() -> &'static SymbolTable {
+        static GLOBAL: OnceLock<SymbolTable> = OnceLock::new();
+        GLOBAL.get_or_init(|| SymbolTable {
+            symbols: RwLock::new(HashSet::new()),
+        })
+    }
+
+    /// The symbol of a name, adding it to the table the first time it's seen:
+    pub fn intern(&self, name: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.read().unwrap().get(name) {
+            return Symbol(symbol.clone());
+        }
+        let mut symbols = self.symbols.write().unwrap();
+        // Another thread may have added it in between:
+        if let Some(symbol) = symbols.get(name) {
+            return Symbol(symbol.clone());
+        }
+        let symbol: Arc<str> = Arc::from(name);
+        symbols.insert(symbol.clone());
+        Symbol(symbol)
+    }
+
+    /// Removes the names no symbol refers to anymore, returning how many were removed. Symbols
+    /// still in use are kept, so they stay shared with the ones interned after:
+    pub fn purge(&self) -> usize {
+        let mut symbols = self.symbols.write().unwrap();
+        let before = symbols.len();
+        symbols.retain(|symbol| Arc::strong_count(symbol) > 1);
+        before - symbols.len()
+    }
+
+    /// How many distinct names have been interned:
+    pub fn len(&self) -> usize {
+        self.symbols.read().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
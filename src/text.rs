@@ -0,0 +1,140 @@
+use crate::features::Features;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+
+/// A few very common english words that carry little meaning on their own.
+const ENGLISH_STOP_WORDS: &[&str] = &[
+    "a", "about", "after", "all", "also", "am", "an", "and", "any", "are", "as", "at", "be",
+    "been", "being", "but", "by", "can", "could", "did", "do", "does", "for", "from", "had", "has",
+    "have", "he", "her", "him", "his", "how", "i", "if", "in", "into", "is", "it", "its", "me",
+    "my", "no", "not", "of", "on", "or", "our", "she", "so", "than", "that", "the", "their",
+    "them", "then", "there", "these", "they", "this", "those", "to", "too", "up", "us", "was",
+    "we", "were", "what", "when", "which", "who", "will", "with", "would", "you", "your",
+];
+
+/// Splits raw text into the tokens (and n-grams of tokens) used as features in text mode.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tokenizer {
+    /// Tokens are the matches of this pattern, or runs of non-whitespace if there is none.
+    #[serde(with = "pattern")]
+    pub pattern: Option<Regex>,
+    /// Whether text is lowercased before it is split.
+    pub lowercase: bool,
+    /// Tokens that are dropped (compared after lowercasing).
+    pub stop_words: HashSet<String>,
+    /// The longest n-grams produced, 1 only produces the tokens themselves.
+    pub ngrams: usize,
+}
+
+impl Default for Tokenizer {
+    fn default() -> Self {
+        Tokenizer {
+            pattern: None,
+            lowercase: true,
+            stop_words: HashSet::new(),
+            ngrams: 1,
+        }
+    }
+}
+
+impl Tokenizer {
+    /// The built in list of english stop words:
+    pub fn english_stop_words() -> HashSet<String> {
+        ENGLISH_STOP_WORDS.iter().map(|w| w.to_string()).collect()
+    }
+
+    /// Splits the text into tokens followed by all of the n-grams (joined by spaces):
+    pub fn tokenize(&self, text: &str) -> Vec<String> {
+        let text = if self.lowercase {
+            text.to_lowercase()
+        } else {
+            text.to_string()
+        };
+
+        let words: Vec<&str> = match &self.pattern {
+            Some(pattern) => pattern.find_iter(&text).map(|m| m.as_str()).collect(),
+            None => text.split_whitespace().collect(),
+        };
+        let words: Vec<&str> = words
+            .into_iter()
+            .filter(|word| !word.is_empty() && !self.stop_words.contains(&word.to_lowercase()))
+            .collect();
+
+        let mut tokens = Vec::new();
+        for n in 1..=self.ngrams.max(1) {
+            for window in words.windows(n) {
+                tokens.push(window.join(" "));
+            }
+        }
+        tokens
+    }
+}
+
+// Regexes are (de)serialized as their pattern:
+mod pattern {
+    use regex::Regex;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(pattern: &Option<Regex>, s: S) -> Result<S::Ok, S::Error> {
+        match pattern {
+            Some(pattern) => s.serialize_some(pattern.as_str()),
+            None => s.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(d: D) -> Result<Option<Regex>, D::Error> {
+        Option::<String>::deserialize(d)?
+            .map(|pattern| Regex::new(&pattern).map_err(D::Error::custom))
+            .transpose()
+    }
+}
+
+/// Re-weights token counts by how rare each token is across the training documents, so that
+/// tokens showing up everywhere don't dominate long documents.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TfIdf {
+    idf: HashMap<String, f64>,
+    num_documents: f64,
+}
+
+impl TfIdf {
+    /// Computes the (smoothed) inverse document frequency of every token in the documents:
+    pub fn fit<'a, I: IntoIterator<Item = &'a Features>>(documents: I) -> Self {
+        let mut document_counts: HashMap<&str, f64> = HashMap::new();
+        let mut num_documents = 0.0;
+        for features in documents {
+            num_documents += 1.0;
+            for token in features.counts.keys() {
+                *document_counts.entry(token.as_str()).or_default() += 1.0;
+            }
+        }
+
+        let idf = document_counts
+            .into_iter()
+            .map(|(token, count)| (token.to_string(), Self::smoothed_idf(num_documents, count)))
+            .collect();
+
+        TfIdf { idf, num_documents }
+    }
+
+    /// Replaces every count with count * idf and scales the document to unit (l2) length:
+    pub fn transform(&self, features: &mut Features) {
+        for (token, value) in features.counts.iter_mut() {
+            let idf = match self.idf.get(token.as_str()) {
+                Some(&idf) => idf,
+                None => Self::smoothed_idf(self.num_documents, 0.0),
+            };
+            *value *= idf;
+        }
+
+        let norm = features.counts.values().map(|v| v * v).sum::<f64>().sqrt();
+        if norm > 0.0 {
+            features.counts.values_mut().for_each(|v| *v /= norm);
+        }
+    }
+
+    fn smoothed_idf(num_documents: f64, document_count: f64) -> f64 {
+        ((1.0 + num_documents) / (1.0 + document_count)).ln() + 1.0
+    }
+}
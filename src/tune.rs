@@ -0,0 +1,184 @@
+use crate::{
+    bayes::NaiveBayesClassifier,
+    config::{Config, ModelKind},
+    error::{BayesError, Result},
+    format::TrainingRecord,
+    rng::Rng,
+};
+use std::{cmp::Ordering, fmt, sync::Mutex, thread};
+
+/// The hyperparameter values tried by a grid search. A dimension left empty keeps the value of
+/// the base config.
+#[derive(Clone, Debug, Default)]
+pub struct Grid {
+    pub smoothing: Vec<f64>,
+    pub model_kinds: Vec<ModelKind>,
+    pub min_feature_counts: Vec<usize>,
+}
+
+impl Grid {
+    /// Every combination of the grid's values applied to the base config:
+    pub fn configs(&self, base: &Config) -> Vec<Config> {
+        fn or_base<T: Copy>(values: &[T], base: T) -> Vec<T> {
+            if values.is_empty() {
+                vec![base]
+            } else {
+                values.to_vec()
+            }
+        }
+
+        let mut configs = Vec::new();
+        for &smoothing in &or_base(&self.smoothing, base.smoothing) {
+            for &model_kind in &or_base(&self.model_kinds, base.model_kind) {
+                for &min_feature_count in &or_base(&self.min_feature_counts, base.min_feature_count)
+                {
+                    configs.push(Config {
+                        smoothing,
+                        model_kind,
+                        min_feature_count,
+                        ..base.clone()
+                    });
+                }
+            }
+        }
+        configs
+    }
+}
+
+/// The cross validated scores of one combination of the grid.
+#[derive(Clone, Debug)]
+pub struct TuneResult {
+    pub smoothing: f64,
+    pub model_kind: ModelKind,
+    pub min_feature_count: usize,
+    /// The accuracy on each held out fold.
+    pub fold_accuracies: Vec<f64>,
+    pub mean_accuracy: f64,
+    pub std_accuracy: f64,
+}
+
+/// The accuracy of the config on each of `folds` held out folds, training on the rest. Record i
+/// goes to fold i % folds, or if the config has a seed, to the fold of its position in a shuffled
+/// order:
+pub fn cross_validate(
+    records: &[TrainingRecord],
+    config: &Config,
+    folds: usize,
+) -> Result<Vec<f64>> {
+    if folds < 2 || folds > records.len() {
+        return Err(BayesError::InvalidConfig(format!(
+            "need between 2 and {} folds: {}",
+            records.len(),
+            folds
+        )));
+    }
+
+    let mut order: Vec<usize> = (0..records.len()).collect();
+    if let Some(seed) = config.seed {
+        Rng::seeded(seed).shuffle(&mut order);
+    }
+    let mut record_folds = vec![0; records.len()];
+    for (position, &i) in order.iter().enumerate() {
+        record_folds[i] = position % folds;
+    }
+
+    let mut accuracies = Vec::with_capacity(folds);
+    for fold in 0..folds {
+        let (held_out, training): (Vec<_>, Vec<_>) = records
+            .iter()
+            .enumerate()
+            .partition(|&(i, _)| record_folds[i] == fold);
+
+        let training = training.into_iter().map(|(_, record)| record.clone());
+        let classifier = NaiveBayesClassifier::fit_weighted(training, config.clone())?;
+
+        let num_correct = held_out
+            .iter()
+            .filter(|(_, (disease, features, _))| {
+                classifier
+                    .predict_top_k(features, 1)
+                    .first()
+                    .is_some_and(|&(predicted, _)| predicted == disease)
+            })
+            .count();
+        accuracies.push(num_correct as f64 / held_out.len() as f64);
+    }
+    Ok(accuracies)
+}
+
+/// Cross validates every combination of the grid (in parallel), returning the results best
+/// first. Ties keep the order of the grid:
+pub fn grid_search(
+    records: &[TrainingRecord],
+    base: &Config,
+    grid: &Grid,
+    folds: usize,
+) -> Result<Vec<TuneResult>> {
+    let configs = grid.configs(base);
+    let num_threads = thread::available_parallelism()
+        .map_or(1, |n| n.get())
+        .min(configs.len());
+
+    // Each thread takes the next untried combination until there are none left:
+    let next = Mutex::new(0);
+    let mut results: Vec<Option<Result<TuneResult>>> = configs.iter().map(|_| None).collect();
+    let results_lock = Mutex::new(&mut results);
+    thread::scope(|scope| {
+        for _ in 0..num_threads {
+            scope.spawn(|| loop {
+                let i = {
+                    let mut next = next.lock().unwrap();
+                    *next += 1;
+                    *next - 1
+                };
+                let Some(config) = configs.get(i) else {
+                    break;
+                };
+
+                let result = cross_validate(records, config, folds).map(|fold_accuracies| {
+                    let n = fold_accuracies.len() as f64;
+                    let mean = fold_accuracies.iter().sum::<f64>() / n;
+                    let variance = fold_accuracies
+                        .iter()
+                        .map(|a| (a - mean).powi(2))
+                        .sum::<f64>()
+                        / n;
+                    TuneResult {
+                        smoothing: config.smoothing,
+                        model_kind: config.model_kind,
+                        min_feature_count: config.min_feature_count,
+                        fold_accuracies,
+                        mean_accuracy: mean,
+                        std_accuracy: variance.sqrt(),
+                    }
+                });
+                results_lock.lock().unwrap()[i] = Some(result);
+            });
+        }
+    });
+
+    let mut results = results
+        .into_iter()
+        .map(|result| result.unwrap())
+        .collect::<Result<Vec<_>>>()?;
+    results.sort_by(|a, b| {
+        b.mean_accuracy
+            .partial_cmp(&a.mean_accuracy)
+            .unwrap_or(Ordering::Equal)
+    });
+    Ok(results)
+}
+
+impl fmt::Display for TuneResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:>10}  {:<12}  {:>17}  {:.4} ± {:.4}",
+            self.smoothing,
+            format!("{:?}", self.model_kind).to_lowercase(),
+            self.min_feature_count,
+            self.mean_accuracy,
+            self.std_accuracy
+        )
+    }
+}
@@ -0,0 +1,108 @@
+//! Bindings for running the classifier in the browser. Build with
+//! `--target wasm32-unknown-unknown --no-default-features --features wasm`.
+
+use crate::{
+    bayes::{NaiveBayesClassifier, Prediction},
+    config::{Config, ModelKind},
+    format::{self, JsonRecord},
+};
+use serde::{Deserialize, Serialize};
+use wasm_bindgen::prelude::*;
+
+/// Training options, all of which are optional.
+#[derive(Default, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+struct Options {
+    smoothing: Option<f64>,
+    model: Option<String>,
+    min_feature_count: Option<usize>,
+    min_confidence: Option<f64>,
+    min_margin: Option<f64>,
+}
+
+#[derive(Serialize)]
+struct ClassProbability<'a> {
+    disease: &'a str,
+    probability: f64,
+}
+
+/// A classifier trained (in the browser) on the contents of a training csv file.
+#[wasm_bindgen(js_name = NaiveBayes)]
+pub struct WasmClassifier {
+    classifier: NaiveBayesClassifier,
+}
+
+#[wasm_bindgen(js_class = NaiveBayes)]
+impl WasmClassifier {
+    /// Trains on csv text, e.g. `new NaiveBayes(text, { smoothing: 0.5 })`.
+    #[wasm_bindgen(constructor)]
+    pub fn new(csv: &str, options: JsValue) -> Result<WasmClassifier, JsError> {
+        let options: Options = if options.is_undefined() || options.is_null() {
+            Options::default()
+        } else {
+            serde_wasm_bindgen::from_value(options)?
+        };
+
+        let defaults = Config::default();
+        let config = Config {
+            smoothing: options.smoothing.unwrap_or(defaults.smoothing),
+            model_kind: match options.model {
+                Some(model) => model.parse::<ModelKind>()?,
+                None => defaults.model_kind,
+            },
+            min_feature_count: options
+                .min_feature_count
+                .unwrap_or(defaults.min_feature_count),
+            min_confidence: options.min_confidence.unwrap_or(defaults.min_confidence),
+            min_margin: options.min_margin.unwrap_or(defaults.min_margin),
+            ..defaults
+        };
+
+        let (records, _) = format::read_training_records_from(csv.as_bytes(), &config)?;
+        let classifier = NaiveBayesClassifier::fit_weighted(records, config)?;
+        Ok(WasmClassifier { classifier })
+    }
+
+    /// Every disease the classifier knows about.
+    pub fn classes(&self) -> Vec<String> {
+        self.classifier
+            .classes()
+            .into_iter()
+            .map(String::from)
+            .collect()
+    }
+
+    /// Predicts the disease of a record like those in jsonl files, e.g. `{ symptoms: [...] }`.
+    /// Returns `{ disease, probability }`, or `null` when the classifier isn't confident enough.
+    pub fn predict(&self, record: JsValue) -> Result<JsValue, JsError> {
+        let record: JsonRecord = serde_wasm_bindgen::from_value(record)?;
+        let features = record.into_features(self.classifier.config());
+        Ok(match self.classifier.predict_single(&features) {
+            Prediction::Disease {
+                disease,
+                probability,
+            } => serde_wasm_bindgen::to_value(&ClassProbability {
+                disease,
+                probability,
+            })?,
+            Prediction::Uncertain => JsValue::NULL,
+        })
+    }
+
+    /// The `k` most likely diseases of a record as `[{ disease, probability }]`.
+    #[wasm_bindgen(js_name = predictTopK)]
+    pub fn predict_top_k(&self, record: JsValue, k: usize) -> Result<JsValue, JsError> {
+        let record: JsonRecord = serde_wasm_bindgen::from_value(record)?;
+        let features = record.into_features(self.classifier.config());
+        let top: Vec<_> = self
+            .classifier
+            .predict_top_k(&features, k)
+            .into_iter()
+            .map(|(disease, probability)| ClassProbability {
+                disease,
+                probability,
+            })
+            .collect();
+        Ok(serde_wasm_bindgen::to_value(&top)?)
+    }
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use common::{features, labeled, training};
+use cs145_bayes::{
+    bayes::NaiveBayesClassifier, calibration::CalibrationMethod, config::Config, features::Features,
+};
+
+// A few records the classifier wasn't trained on, some of them mislabeled by its standards:
+fn holdout() -> Vec<(String, Features)> {
+    labeled(&[
+        ("Flu", &["fever", "cough"]),
+        ("Flu", &["chills", "sneeze"]),
+        ("Cold", &["sneeze", "cough"]),
+        ("Cold", &["fever", "runny nose"]),
+        ("Allergy", &["itchy eyes", "sneeze"]),
+        ("Allergy", &["runny nose"]),
+    ])
+}
+
+fn queries() -> Vec<Features> {
+    vec![
+        features(&["fever", "cough"]),
+        features(&["sneeze"]),
+        features(&["itchy eyes", "runny nose"]),
+        features(&["chills", "runny nose", "sneeze"]),
+        features(&["unseen"]),
+    ]
+}
+
+#[test]
+fn calibrated_probabilities_are_monotone_and_sum_to_one() {
+    let raw = NaiveBayesClassifier::fit(training(), Config::default()).unwrap();
+    for method in [CalibrationMethod::Platt, CalibrationMethod::Isotonic] {
+        let mut calibrated = NaiveBayesClassifier::fit(training(), Config::default()).unwrap();
+        calibrated.calibrate(&holdout(), method).unwrap();
+
+        for query in queries() {
+            let before = raw.predict_top_k(&query, usize::MAX);
+            let after = calibrated.predict_top_k(&query, usize::MAX);
+            assert_eq!(before.len(), after.len());
+
+            let total: f64 = after.iter().map(|&(_, p)| p).sum();
+            assert!((total - 1.0).abs() < 1e-9, "{:?}: {}", method, total);
+            assert!(after.iter().all(|&(_, p)| (0.0..=1.0).contains(&p)));
+
+            // A disease more likely than another before calibrating is never less likely after:
+            let calibrated_p =
+                |disease: &str| after.iter().find(|&&(d, _)| d == disease).unwrap().1;
+            for &(a, p) in &before {
+                for &(b, q) in &before {
+                    if p > q {
+                        assert!(
+                            calibrated_p(a) >= calibrated_p(b),
+                            "{:?}: {} < {}",
+                            method,
+                            a,
+                            b
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn calibrating_needs_a_holdout_set() {
+    let mut classifier = NaiveBayesClassifier::fit(training(), Config::default()).unwrap();
+    assert!(classifier.calibrate(&[], CalibrationMethod::Platt).is_err());
+}
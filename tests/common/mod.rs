@@ -0,0 +1,33 @@
+// Every test crate includes this module, but not every one of them uses all of it:
+#![allow(dead_code)]
+
+use cs145_bayes::{features::Features, symbols::Symbol};
+
+/// A record with the given symptoms present:
+pub fn features(symptoms: &[&str]) -> Features {
+    Features {
+        counts: symptoms.iter().map(|&s| (Symbol::new(s), 1.0)).collect(),
+        ..Features::default()
+    }
+}
+
+pub fn labeled(records: &[(&str, &[&str])]) -> Vec<(String, Features)> {
+    records
+        .iter()
+        .map(|(disease, symptoms)| (disease.to_string(), features(symptoms)))
+        .collect()
+}
+
+/// Three diseases with overlapping symptoms:
+pub fn training() -> Vec<(String, Features)> {
+    labeled(&[
+        ("Flu", &["fever", "cough", "chills"]),
+        ("Flu", &["fever", "chills"]),
+        ("Flu", &["fever", "cough", "sneeze"]),
+        ("Cold", &["cough", "sneeze"]),
+        ("Cold", &["sneeze", "runny nose"]),
+        ("Cold", &["cough", "runny nose", "fever"]),
+        ("Allergy", &["sneeze", "itchy eyes", "runny nose"]),
+        ("Allergy", &["itchy eyes"]),
+    ])
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{features, training};
+use cs145_bayes::{
+    config::Config,
+    cost::CostMatrix,
+    ensemble::{BaggingConfig, EnsembleClassifier},
+    features::Features,
+    hierarchy::{HierarchicalClassifier, Hierarchy},
+};
+
+fn records() -> Vec<(String, Features, f64)> {
+    training()
+        .into_iter()
+        .map(|(disease, features)| (disease, features, 1.0))
+        .collect()
+}
+
+// Missing flu is much worse than any other mistake:
+fn costly_flu() -> Config {
+    let mut costs = CostMatrix::new();
+    costs.set("Flu", "Cold", 50.0);
+    costs.set("Flu", "Allergy", 50.0);
+    Config {
+        costs: Some(costs),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn ensembles_predict_by_cost() {
+    let record = [features(&["sneeze"])];
+    let bagging = BaggingConfig::default();
+    let plain = EnsembleClassifier::fit_weighted(records(), Config::default(), &bagging).unwrap();
+    assert_ne!(&*plain.predict_batch(&record)[0].disease, "Flu");
+
+    let costly = EnsembleClassifier::fit_weighted(records(), costly_flu(), &bagging).unwrap();
+    assert_eq!(&*costly.predict_batch(&record)[0].disease, "Flu");
+}
+
+#[test]
+fn hierarchies_predict_by_cost() {
+    let mut hierarchy = Hierarchy::new();
+    hierarchy.insert("Flu", "Infection");
+    hierarchy.insert("Cold", "Infection");
+    hierarchy.insert("Allergy", "Immune");
+
+    let record = [features(&["sneeze"])];
+    let plain =
+        HierarchicalClassifier::fit_weighted(records(), Config::default(), hierarchy.clone())
+            .unwrap();
+    assert_ne!(&*plain.predict_batch(&record)[0].disease, "Flu");
+
+    let costly = HierarchicalClassifier::fit_weighted(records(), costly_flu(), hierarchy).unwrap();
+    assert_eq!(&*costly.predict_batch(&record)[0].disease, "Flu");
+}
@@ -0,0 +1,164 @@
+use cs145_bayes::{
+    bayes::NaiveBayesClassifier,
+    config::Config,
+    ensemble::{BaggingConfig, EnsembleClassifier},
+    features::Features,
+    format::{self, Format, TrainingRecord},
+    interval::{IntervalConfig, PosteriorSampler},
+    self_training::SelfTraining,
+    tune,
+};
+use std::{fs, path::PathBuf};
+
+const TRAIN: &str = "\
+Disease,S1,S2,S3
+Flu,fever,cough,chills
+Flu,fever,chills,
+Cold,cough,sneeze,
+Cold,sneeze,runny nose,
+Measles,rash,fever,
+Rubella,rash,fever,
+Allergy,sneeze,itchy eyes,runny nose
+";
+
+const TEST: &str = "\
+ID,S1,S2,S3
+1,fever,cough,
+2,rash,fever,
+3,sneeze,,
+4,unseen,,
+5,runny nose,itchy eyes,sneeze
+";
+
+fn temp_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("nb_determinism_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn training_twice_predicts_identically() {
+    let dir = temp_dir();
+    let train = dir.join("train.csv");
+    let test = dir.join("test.csv");
+    fs::write(&train, TRAIN).unwrap();
+    fs::write(&test, TEST).unwrap();
+
+    let mut outputs = Vec::new();
+    for i in 0..2 {
+        let output = dir.join(format!("result{}.jsonl", i));
+        let classifier = NaiveBayesClassifier::new(&train).unwrap();
+        classifier.predict(&test, &output, None).unwrap();
+        outputs.push(fs::read_to_string(&output).unwrap());
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(outputs[0], outputs[1]);
+
+    // Measles and Rubella are indistinguishable and equally likely, so the tie goes to the
+    // alphabetically first one:
+    let second = outputs[0].lines().nth(1).unwrap();
+    assert!(second.contains(r#""disease":"Measles""#), "{}", second);
+}
+
+fn seeded(seed: u64) -> Config {
+    Config {
+        seed: Some(seed),
+        ..Config::default()
+    }
+}
+
+fn training_records(config: &Config) -> Vec<TrainingRecord> {
+    format::read_training_records_from(TRAIN.as_bytes(), config)
+        .unwrap()
+        .0
+}
+
+fn test_records(config: &Config) -> Vec<Features> {
+    let mut records = Vec::new();
+    format::for_each_record(TEST.as_bytes(), Format::Csv, config, |_, features| {
+        records.push(features);
+        Ok(())
+    })
+    .unwrap();
+    records
+}
+
+#[test]
+fn seeded_bagging_is_reproducible() {
+    let config = seeded(7);
+    // The config's seed takes the place of the bagging config's own:
+    let ensembles: Vec<EnsembleClassifier> = [1, 2]
+        .into_iter()
+        .map(|seed| {
+            let bagging = BaggingConfig {
+                num_models: 5,
+                seed,
+                ..BaggingConfig::default()
+            };
+            EnsembleClassifier::fit_weighted(training_records(&config), config.clone(), &bagging)
+                .unwrap()
+        })
+        .collect();
+
+    for features in test_records(&config) {
+        assert_eq!(
+            ensembles[0].predict_top_k(&features, usize::MAX),
+            ensembles[1].predict_top_k(&features, usize::MAX)
+        );
+    }
+}
+
+#[test]
+fn seeded_cross_validation_is_reproducible() {
+    let config = seeded(11);
+    let records = training_records(&config);
+    assert_eq!(
+        tune::cross_validate(&records, &config, 3).unwrap(),
+        tune::cross_validate(&records, &config, 3).unwrap()
+    );
+}
+
+#[test]
+fn seeded_self_training_is_reproducible() {
+    let config = seeded(3);
+    let self_training = SelfTraining {
+        threshold: 0.3,
+        pool_size: Some(2),
+        ..SelfTraining::default()
+    };
+    let unlabeled = test_records(&config);
+    let models: Vec<String> = (0..2)
+        .map(|_| {
+            let classifier = self_training
+                .fit(training_records(&config), &unlabeled, config.clone())
+                .unwrap();
+            serde_json::to_string(&classifier).unwrap()
+        })
+        .collect();
+    assert_eq!(models[0], models[1]);
+}
+
+#[test]
+fn seeded_posterior_samples_are_reproducible() {
+    let config = seeded(5);
+    let classifier =
+        NaiveBayesClassifier::fit_weighted(training_records(&config), config.clone()).unwrap();
+    // The config's seed takes the place of the interval config's own:
+    let mut samplers: Vec<PosteriorSampler> = [1, 2]
+        .into_iter()
+        .map(|seed| {
+            let intervals = IntervalConfig {
+                samples: 50,
+                seed,
+                ..IntervalConfig::default()
+            };
+            PosteriorSampler::new(&classifier, intervals).unwrap()
+        })
+        .collect();
+
+    for features in test_records(&config) {
+        let first = samplers[0].intervals(&features);
+        assert_eq!(first, samplers[1].intervals(&features));
+    }
+}
@@ -0,0 +1,73 @@
+use cs145_bayes::dialect::{CsvDialect, Delimiter, Encoding};
+
+const SNIFF: CsvDialect = CsvDialect {
+    encoding: Encoding::Utf8,
+    delimiter: Delimiter::Sniff,
+};
+
+/// Reads a fixture in the dialect, returning its header and rows:
+fn read(dialect: CsvDialect, bytes: &[u8]) -> (Vec<String>, Vec<Vec<String>>) {
+    let mut reader = dialect.reader(bytes).unwrap();
+    let headers = reader.headers().unwrap().iter().map(String::from).collect();
+    let rows = reader
+        .records()
+        .map(|record| record.unwrap().iter().map(String::from).collect())
+        .collect();
+    (headers, rows)
+}
+
+#[test]
+fn sniffs_semicolons() {
+    // A comma inside a field doesn't throw it off, as it doesn't split every row the same way:
+    let (headers, rows) = read(SNIFF, include_bytes!("fixtures/dialect/semicolon.csv"));
+    assert_eq!(headers, ["Disease", "S1", "S2", "S3"]);
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0], ["Flu", "fever", "cough, dry", "chills"]);
+}
+
+#[test]
+fn sniffs_tabs() {
+    let (headers, rows) = read(SNIFF, include_bytes!("fixtures/dialect/tab.tsv"));
+    assert_eq!(headers, ["Disease", "S1", "S2", "S3"]);
+    assert_eq!(rows[1], ["Cold", "cough", "sneeze", ""]);
+    assert_eq!(rows[2], ["Allergy", "sneeze", "itchy eyes", "runny nose"]);
+}
+
+#[test]
+fn sniffs_pipes() {
+    let (headers, rows) = read(SNIFF, include_bytes!("fixtures/dialect/pipe.csv"));
+    assert_eq!(headers, ["Disease", "S1", "S2", "S3"]);
+    assert_eq!(
+        rows[2],
+        ["Allergy", "sneeze", "itchy eyes", "runny nose; watery eyes"]
+    );
+}
+
+#[test]
+fn drops_a_utf8_byte_order_mark() {
+    let bytes = include_bytes!("fixtures/dialect/bom.csv");
+    assert!(bytes.starts_with(b"\xef\xbb\xbf"));
+    for dialect in [CsvDialect::default(), SNIFF] {
+        let (headers, rows) = read(dialect, bytes);
+        assert_eq!(headers, ["Disease", "S1", "S2", "S3"]);
+        assert_eq!(rows[0], ["Flu", "fever", "cough", "chills"]);
+    }
+}
+
+#[test]
+fn decodes_latin1() {
+    let dialect = CsvDialect {
+        encoding: Encoding::Windows1252,
+        delimiter: Delimiter::Sniff,
+    };
+    let (headers, rows) = read(dialect, include_bytes!("fixtures/dialect/latin1.csv"));
+    assert_eq!(headers, ["Maladie", "S1", "S2"]);
+    assert_eq!(rows[0], ["Grippe", "fièvre", "toux"]);
+    assert_eq!(rows[1], ["Rhume", "éternuement", "toux"]);
+
+    // Read as UTF-8 the accented letters aren't valid:
+    let mut reader = SNIFF
+        .reader(&include_bytes!("fixtures/dialect/latin1.csv")[..])
+        .unwrap();
+    assert!(reader.records().any(|record| record.is_err()));
+}
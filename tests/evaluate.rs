@@ -0,0 +1,93 @@
+mod common;
+
+use common::labeled;
+use cs145_bayes::{
+    bayes::NaiveBayesClassifier, config::Config, evaluate::evaluate, features::Features,
+};
+
+// Two equally likely diseases, each with symptoms of its own:
+fn classifier() -> NaiveBayesClassifier {
+    let training = labeled(&[
+        ("Flu", &["fever", "chills"]),
+        ("Flu", &["fever"]),
+        ("Cold", &["sneeze", "runny nose"]),
+        ("Cold", &["sneeze"]),
+    ]);
+    NaiveBayesClassifier::fit(training, Config::default()).unwrap()
+}
+
+fn roc_aucs(records: &[(String, Features)]) -> Vec<(String, Option<f64>)> {
+    evaluate(&classifier(), records, 10)
+        .classes
+        .into_iter()
+        .map(|class| (class.class, class.roc_auc))
+        .collect()
+}
+
+#[test]
+fn perfect_ranking_has_an_auc_of_one() {
+    let records = labeled(&[
+        ("Flu", &["fever", "chills"]),
+        ("Flu", &["fever"]),
+        ("Cold", &["sneeze"]),
+        ("Cold", &["runny nose"]),
+    ]);
+    for (class, auc) in roc_aucs(&records) {
+        assert_eq!(auc, Some(1.0), "{}", class);
+    }
+}
+
+#[test]
+fn reversed_ranking_has_an_auc_of_zero() {
+    // Every record is more likely the disease it isn't:
+    let records = labeled(&[
+        ("Cold", &["fever", "chills"]),
+        ("Cold", &["fever"]),
+        ("Flu", &["sneeze"]),
+        ("Flu", &["runny nose"]),
+    ]);
+    for (class, auc) in roc_aucs(&records) {
+        assert_eq!(auc, Some(0.0), "{}", class);
+    }
+}
+
+#[test]
+fn tied_scores_count_half() {
+    // Unseen symptoms leave only the (equal) priors, so every record gets the same score:
+    let records = labeled(&[
+        ("Flu", &["rash"]),
+        ("Cold", &["rash"]),
+        ("Flu", &["headache"]),
+        ("Cold", &["headache"]),
+    ]);
+    for (class, auc) in roc_aucs(&records) {
+        assert_eq!(auc, Some(0.5), "{}", class);
+    }
+
+    // One record ranked above the rest, and the others tied with each other:
+    let records = labeled(&[("Flu", &["fever"]), ("Flu", &["rash"]), ("Cold", &["rash"])]);
+    let aucs = roc_aucs(&records);
+    let flu = aucs.iter().find(|(class, _)| class == "Flu").unwrap().1;
+    assert_eq!(flu, Some(0.75));
+}
+
+#[test]
+fn auc_is_undefined_without_both_kinds_of_records() {
+    let records = labeled(&[("Flu", &["fever"]), ("Flu", &["chills"])]);
+    for (_, auc) in roc_aucs(&records) {
+        assert_eq!(auc, None);
+    }
+}
+
+#[test]
+fn undefined_areas_are_shown_as_a_dash() {
+    // Nothing is a cold, so its curves have no positives:
+    let records = labeled(&[("Flu", &["fever"]), ("Flu", &["sneeze"])]);
+    let report = evaluate(&classifier(), &records, 10).to_string();
+    let cold = report
+        .lines()
+        .find(|line| line.ends_with("  Cold"))
+        .unwrap();
+    let columns: Vec<&str> = cold.split_whitespace().collect();
+    assert_eq!(columns, ["0", "-", "-", "Cold"]);
+}
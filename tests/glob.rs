@@ -0,0 +1,34 @@
+use cs145_bayes::glob::glob;
+use std::{fs, path::PathBuf};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("nb_glob_{}_{}", name, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+#[test]
+fn directories_dont_match() {
+    let dir = temp_dir("directories");
+    fs::write(dir.join("a.csv"), "").unwrap();
+    fs::write(dir.join("b.csv"), "").unwrap();
+    fs::create_dir_all(dir.join("nested.csv")).unwrap();
+
+    let paths = glob(dir.join("*")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(paths, [dir.join("a.csv"), dir.join("b.csv")]);
+}
+
+#[cfg(unix)]
+#[test]
+fn non_utf8_names_are_kept_as_they_are() {
+    use std::{ffi::OsStr, os::unix::ffi::OsStrExt};
+
+    let dir = temp_dir("non_utf8");
+    let name = OsStr::from_bytes(b"fi\xe8vre.csv");
+    fs::write(dir.join(name), "").unwrap();
+
+    let paths = glob(dir.join("*.csv")).unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+    assert_eq!(paths, [dir.join(name)]);
+}
@@ -0,0 +1,87 @@
+mod common;
+
+use common::{features, training};
+use cs145_bayes::{
+    bayes::{NaiveBayesClassifier, Prediction},
+    config::Config,
+    format,
+    hashing::{FeatureHasher, BUCKET_PREFIX},
+};
+
+// The shared training records as a csv file:
+const TRAIN: &str = "\
+Disease,S1,S2,S3
+Flu,fever,cough,chills
+Flu,fever,chills,
+Flu,fever,cough,sneeze
+Cold,cough,sneeze,
+Cold,sneeze,runny nose,
+Cold,cough,runny nose,fever
+Allergy,sneeze,itchy eyes,runny nose
+Allergy,itchy eyes,,
+";
+
+fn hashed(buckets: usize) -> Config {
+    Config {
+        feature_hasher: Some(FeatureHasher::new(buckets)),
+        ..Config::default()
+    }
+}
+
+#[test]
+fn fit_hashes_hand_built_features() {
+    let hasher = FeatureHasher::new(4);
+    let classifier = NaiveBayesClassifier::fit(training(), hashed(4)).unwrap();
+
+    // Only buckets are features, and only the ones symptoms landed in:
+    let symptoms = [
+        "fever",
+        "cough",
+        "chills",
+        "sneeze",
+        "runny nose",
+        "itchy eyes",
+    ];
+    let mut expected: Vec<String> = symptoms
+        .iter()
+        .map(|symptom| hasher.hash_name(symptom))
+        .collect();
+    expected.sort();
+    expected.dedup();
+    let vocabulary = classifier.features();
+    assert_eq!(vocabulary, expected);
+    assert!(vocabulary.iter().all(|f| f.starts_with(BUCKET_PREFIX)));
+    assert!(vocabulary.len() <= 4);
+
+    // Hand-built records are hashed at prediction time too:
+    let fever = classifier.feature_log_prob("Flu", &hasher.hash_name("fever"));
+    assert!(fever.is_some());
+    assert!(matches!(
+        classifier.predict_single(&features(&["fever", "chills"])),
+        Prediction::Disease { disease: "Flu", .. }
+    ));
+}
+
+#[test]
+fn fit_and_csv_training_agree() {
+    // Every record goes through the classifier's own hashing, whichever way it came in:
+    let config = hashed(8);
+    let fitted = NaiveBayesClassifier::fit(training(), config.clone()).unwrap();
+    let (read, _) = format::read_training_records_from(TRAIN.as_bytes(), &config).unwrap();
+    let from_csv = NaiveBayesClassifier::fit_weighted(read, config).unwrap();
+
+    assert_eq!(fitted.features(), from_csv.features());
+    for class in fitted.classes() {
+        for feature in fitted.features() {
+            assert_eq!(
+                fitted.feature_log_prob(class, feature),
+                from_csv.feature_log_prob(class, feature)
+            );
+        }
+    }
+    let record = features(&["sneeze", "itchy eyes"]);
+    assert_eq!(
+        fitted.predict_top_k(&record, usize::MAX),
+        from_csv.predict_top_k(&record, usize::MAX)
+    );
+}
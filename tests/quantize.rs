@@ -0,0 +1,119 @@
+mod common;
+
+use common::{features, training};
+use cs145_bayes::{
+    bayes::NaiveBayesClassifier,
+    calibration::CalibrationMethod,
+    config::Config,
+    features::Features,
+    quantize::{f16_to_f32, f32_to_f16, Precision, QuantizedClassifier},
+};
+use std::{fs, path::PathBuf};
+
+fn queries() -> Vec<Features> {
+    vec![
+        features(&["fever", "cough"]),
+        features(&["sneeze"]),
+        features(&["itchy eyes", "sneeze"]),
+        features(&["chills", "runny nose"]),
+        features(&["unseen"]),
+    ]
+}
+
+fn temp_path(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("nb_quantize_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    dir.join(name)
+}
+
+/// Quantizes the classifier, saves and loads it, and checks that it predicts like the original:
+fn assert_round_trip(classifier: &NaiveBayesClassifier, precision: Precision, name: &str) {
+    let path = temp_path(name);
+    QuantizedClassifier::quantize(classifier, precision)
+        .unwrap()
+        .save(&path)
+        .unwrap();
+    let quantized = QuantizedClassifier::load(&path).unwrap();
+    fs::remove_file(&path).unwrap();
+
+    assert_eq!(quantized.precision(), precision);
+    let mut classes = classifier.classes();
+    classes.sort();
+    let mut quantized_classes = quantized.classes();
+    quantized_classes.sort();
+    assert_eq!(quantized_classes, classes);
+
+    let full = classifier.predict_batch(&queries());
+    let predicted = quantized.predict_batch(&queries());
+    for (full, predicted) in full.iter().zip(&predicted) {
+        assert_eq!(full.disease, predicted.disease);
+        assert!((full.score - predicted.score).abs() <= 1e-2 * full.score);
+    }
+    for query in queries() {
+        let full = classifier.predict_top_k(&query, usize::MAX);
+        let predicted = quantized.predict_top_k(&query, usize::MAX);
+        for ((class, p), (other, q)) in full.iter().zip(&predicted) {
+            assert_eq!(class, other);
+            assert!((p - q).abs() < 1e-2);
+        }
+    }
+}
+
+#[test]
+fn loaded_models_predict_like_the_full_model() {
+    let classifier = NaiveBayesClassifier::fit(training(), Config::default()).unwrap();
+    assert_round_trip(&classifier, Precision::F16, "f16.nbq");
+    assert_round_trip(&classifier, Precision::I16, "i16.nbq");
+}
+
+#[test]
+fn loaded_models_keep_the_calibrator() {
+    let mut classifier = NaiveBayesClassifier::fit(training(), Config::default()).unwrap();
+    classifier
+        .calibrate(&training(), CalibrationMethod::Platt)
+        .unwrap();
+    assert_round_trip(&classifier, Precision::F16, "calibrated.nbq");
+}
+
+#[test]
+fn corrupt_lengths_are_rejected() {
+    let classifier = NaiveBayesClassifier::fit(training(), Config::default()).unwrap();
+    let path = temp_path("corrupt.nbq");
+    QuantizedClassifier::quantize(&classifier, Precision::F16)
+        .unwrap()
+        .save(&path)
+        .unwrap();
+
+    // The length of the config follows the magic number and the precision:
+    let mut bytes = fs::read(&path).unwrap();
+    bytes[5..9].copy_from_slice(&u32::MAX.to_le_bytes());
+    fs::write(&path, bytes).unwrap();
+    let result = QuantizedClassifier::load(&path);
+    fs::remove_file(&path).unwrap();
+    assert!(result.is_err());
+}
+
+#[test]
+fn f16_conversion_edge_cases() {
+    // The smallest subnormal, and one halfway below it (which rounds to even, i.e. zero):
+    let smallest = 2f32.powi(-24);
+    assert_eq!(f32_to_f16(smallest), 0x0001);
+    assert_eq!(f16_to_f32(0x0001), smallest);
+    assert_eq!(f32_to_f16(smallest / 2.0), 0x0000);
+    assert_eq!(f32_to_f16(-3.0 * smallest), 0x8003);
+    assert_eq!(f16_to_f32(0x8003), -3.0 * smallest);
+    // The largest subnormal:
+    assert_eq!(f16_to_f32(f32_to_f16(1023.0 * smallest)), 1023.0 * smallest);
+
+    // Past the largest half precision float (65504) everything overflows to infinity:
+    assert_eq!(f32_to_f16(65504.0), 0x7bff);
+    assert_eq!(f32_to_f16(65520.0), 0x7c00);
+    assert_eq!(f32_to_f16(1e10), 0x7c00);
+    assert_eq!(f32_to_f16(-1e10), 0xfc00);
+
+    // Infinities round trip, and NaN stays a NaN:
+    assert_eq!(f32_to_f16(f32::NEG_INFINITY), 0xfc00);
+    assert_eq!(f16_to_f32(0xfc00), f32::NEG_INFINITY);
+    assert_eq!(f16_to_f32(f32_to_f16(f32::INFINITY)), f32::INFINITY);
+    assert!(f16_to_f32(f32_to_f16(f32::NAN)).is_nan());
+}
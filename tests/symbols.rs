@@ -0,0 +1,29 @@
+use cs145_bayes::{
+    config::Config,
+    format,
+    symbols::{Symbol, SymbolTable},
+};
+
+#[test]
+fn purging_keeps_the_names_in_use() {
+    let table = SymbolTable::global();
+    let (records, _) = format::read_training_records_from(
+        "Disease,S1\nFlu,purge kept\n".as_bytes(),
+        &Config::default(),
+    )
+    .unwrap();
+    drop(Symbol::new("purge dropped"));
+
+    let len = table.len();
+    let purged = table.purge();
+    assert!(purged >= 1);
+    assert_eq!(table.len(), len - purged);
+
+    // The loaded record's symptom is still interned, so interning it again finds it:
+    let len = table.len();
+    let kept = Symbol::new("purge kept");
+    assert!(records[0].1.counts.contains_key(&kept));
+    assert_eq!(table.len(), len);
+    Symbol::new("purge dropped");
+    assert_eq!(table.len(), len + 1);
+}